@@ -0,0 +1,20 @@
+-- Store Redis instance passwords encrypted (AES-256-GCM) instead of bcrypt-hashed
+-- redis_instances.password_hash is a one-way hash and cannot be used to connect.
+-- Instances created before this migration have no stored password until it is rotated.
+
+CREATE TABLE IF NOT EXISTS redis_instance_credentials (
+    instance_id UUID PRIMARY KEY REFERENCES redis_instances(id) ON DELETE CASCADE,
+    -- base64(nonce || ciphertext || tag), authenticated with the instance id
+    password_ciphertext TEXT NOT NULL,
+    -- Version of the master key used to encrypt, for key rotation
+    key_version INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Find secrets that still need re-encryption after a key rotation
+CREATE INDEX IF NOT EXISTS idx_redis_instance_credentials_key_version
+    ON redis_instance_credentials(key_version);
+
+COMMENT ON COLUMN redis_instances.password_hash IS
+    'Deprecated: bcrypt hash of the instance password. Connections use redis_instance_credentials.';
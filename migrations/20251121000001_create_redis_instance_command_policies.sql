@@ -0,0 +1,15 @@
+-- Per-instance command policy enforced by the gateway before commands reach Redis
+-- Instances without a row use the built-in default, which blocks administrative commands.
+
+CREATE TABLE IF NOT EXISTS redis_instance_command_policies (
+    instance_id UUID PRIMARY KEY REFERENCES redis_instances(id) ON DELETE CASCADE,
+    -- When non-empty, only these commands may run
+    allowed_commands TEXT[] NOT NULL DEFAULT '{}',
+    -- Commands that may never run
+    denied_commands TEXT[] NOT NULL DEFAULT '{}',
+    -- Original command name -> name clients must use instead ("" disables the command)
+    renamed_commands JSONB NOT NULL DEFAULT '{}',
+    updated_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,13 @@
+-- Named Lua scripts registered per instance and invoked through the gateway with EVALSHA
+
+CREATE TABLE IF NOT EXISTS redis_instance_scripts (
+    instance_id UUID NOT NULL REFERENCES redis_instances(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    source TEXT NOT NULL,
+    -- SHA1 of the source, as used by EVALSHA
+    sha1 CHAR(40) NOT NULL,
+    updated_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (instance_id, name)
+);
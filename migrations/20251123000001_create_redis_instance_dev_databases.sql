@@ -0,0 +1,7 @@
+-- Logical database of the shared local Redis assigned to each development instance
+
+CREATE TABLE IF NOT EXISTS redis_instance_dev_databases (
+    instance_id UUID PRIMARY KEY REFERENCES redis_instances(id) ON DELETE CASCADE,
+    db_index INTEGER NOT NULL UNIQUE CHECK (db_index > 0),
+    assigned_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,5 @@
+-- Whether an instance is a single Redis server or a Redis Cluster
+
+ALTER TABLE redis_instances
+    ADD COLUMN IF NOT EXISTS topology VARCHAR(20) NOT NULL DEFAULT 'standalone'
+        CHECK (topology IN ('standalone', 'cluster'));
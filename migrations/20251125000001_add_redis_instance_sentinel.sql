@@ -0,0 +1,11 @@
+-- Sentinel settings of instances run with Redis Sentinel for automatic failover
+
+ALTER TABLE redis_instances
+    ADD COLUMN IF NOT EXISTS sentinel_master_name VARCHAR(255),
+    ADD COLUMN IF NOT EXISTS sentinel_endpoints TEXT[] NOT NULL DEFAULT '{}';
+
+ALTER TABLE redis_instances
+    ADD CONSTRAINT redis_instances_sentinel_check CHECK (
+        sentinel_master_name IS NULL
+        OR (cardinality(sentinel_endpoints) > 0 AND topology = 'standalone')
+    );
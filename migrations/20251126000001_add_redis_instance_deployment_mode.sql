@@ -0,0 +1,11 @@
+-- Whether an instance was deployed to Kubernetes or created in development mode, where it
+-- is a database of the shared local Redis
+
+ALTER TABLE redis_instances
+    ADD COLUMN IF NOT EXISTS deployment_mode VARCHAR(20) NOT NULL DEFAULT 'kubernetes'
+        CHECK (deployment_mode IN ('kubernetes', 'development'));
+
+-- Development mode gave instances the domain dev-<slug>
+UPDATE redis_instances
+SET deployment_mode = 'development'
+WHERE domain = 'dev-' || slug;
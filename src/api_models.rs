@@ -2,9 +2,12 @@
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use uuid::Uuid;
 use validator::Validate;
 
+use crate::services::cluster::Topology;
+
 use lazy_static::lazy_static;
 use regex::Regex;
 
@@ -127,6 +130,8 @@ pub struct CreateRedisInstanceRequest {
     #[validate(range(min = 1048576, max = 17179869184i64))] // 1MB to 16GB
     pub max_memory: i64,
     pub redis_version: Option<String>,
+    /// Defaults to standalone; cluster instances are served through a cluster client
+    pub topology: Option<Topology>,
     pub persistence_enabled: Option<bool>,
     pub backup_enabled: Option<bool>,
 }
@@ -144,6 +149,14 @@ pub struct RedisInstanceResponse {
     pub max_memory: i64,
     pub current_memory: i64,
     pub redis_version: String,
+    pub topology: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sentinel_master_name: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub sentinel_endpoints: Vec<String>,
+    /// `host:port` of the master Sentinel reports; only filled in by the status endpoint
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current_master: Option<String>,
     pub namespace: String,
     pub status: String,
     pub health_status: String,
@@ -158,6 +171,83 @@ pub struct RedisInstanceResponse {
     pub updated_at: DateTime<Utc>,
 }
 
+// Connection details of a Redis instance
+#[derive(Debug, Serialize)]
+pub struct RedisCredentialsResponse {
+    pub instance_id: Uuid,
+    pub host: String,
+    pub port: u16,
+    pub password: Option<String>,
+    pub tls: bool,
+    /// `redis://` or `rediss://` URL for native Redis clients
+    pub redis_url: String,
+    /// Base URL of the HTTP gateway for this instance
+    pub rest_url: String,
+}
+
+// Command policy update request
+#[derive(Debug, Deserialize)]
+pub struct UpdateCommandPolicyRequest {
+    /// When non-empty, only these commands may run
+    #[serde(default)]
+    pub allowed_commands: Vec<String>,
+    /// Omit to keep the default list of blocked administrative commands
+    pub denied_commands: Option<Vec<String>>,
+    /// Original command name -> name clients must use instead ("" disables the command)
+    #[serde(default)]
+    pub renamed_commands: BTreeMap<String, String>,
+}
+
+// Command policy response
+#[derive(Debug, Serialize)]
+pub struct CommandPolicyResponse {
+    pub instance_id: Uuid,
+    pub allowed_commands: Vec<String>,
+    pub denied_commands: Vec<String>,
+    pub renamed_commands: BTreeMap<String, String>,
+}
+
+// Lua script create/replace request
+#[derive(Debug, Deserialize)]
+pub struct UpdateScriptRequest {
+    pub source: String,
+}
+
+// Sentinel settings update request
+#[derive(Debug, Deserialize)]
+pub struct UpdateSentinelRequest {
+    /// Name the sentinels know the master by; null removes the Sentinel settings
+    pub master_name: Option<String>,
+    /// `host:port` of each sentinel (port defaults to 26379)
+    #[serde(default)]
+    pub endpoints: Vec<String>,
+}
+
+// Lua script response
+#[derive(Debug, Serialize)]
+pub struct ScriptResponse {
+    pub instance_id: Uuid,
+    pub name: String,
+    pub source: String,
+    pub sha1: String,
+    /// Redis commands the script calls
+    pub commands: Vec<String>,
+    /// True if some command names are computed at run time, so `commands` may be incomplete
+    pub dynamic_commands: bool,
+    /// Scope class an API key needs to run the script (`read`, `write`, `admin` or `dangerous`)
+    pub access_class: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+// Password rotation response
+#[derive(Debug, Serialize)]
+pub struct RotatePasswordResponse {
+    pub instance_id: Uuid,
+    /// False for development instances, which share the local Redis and have no password
+    pub applied_live: bool,
+    pub rotated_at: DateTime<Utc>,
+}
+
 // Generic API response wrapper
 #[derive(Debug, Serialize)]
 pub struct ApiResponse<T> {
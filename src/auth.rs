@@ -141,7 +141,7 @@ pub fn verify_password(password: &str, hash: &str) -> Result<bool, bcrypt::Bcryp
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::{DateTime, Utc};
+    use chrono::Utc;
     use uuid::Uuid;
     
     #[test]
@@ -167,7 +167,7 @@ mod tests {
         assert_eq!(claims.organization_id, organization_id);
         assert_eq!(claims.scopes, scopes);
         assert_eq!(claims.key_prefix, key_prefix);
-        assert_eq!(claims.exp, expires_at.unwrap().timestamp());
+        assert_eq!(claims.exp, expires_at.map(|at| at.timestamp()).unwrap_or_default());
     }
 
     #[test]
@@ -25,11 +25,11 @@ fn main() {
             println!("Email: {}", token_data.claims.email);
             println!("Org ID: {:?}", token_data.claims.org_id);
             println!("Issued at: {} ({})", token_data.claims.iat,
-                chrono::NaiveDateTime::from_timestamp_opt(token_data.claims.iat, 0)
+                chrono::DateTime::from_timestamp(token_data.claims.iat, 0)
                     .map(|dt| dt.to_string())
                     .unwrap_or_else(|| "Invalid".to_string()));
             println!("Expires at: {} ({})", token_data.claims.exp,
-                chrono::NaiveDateTime::from_timestamp_opt(token_data.claims.exp, 0)
+                chrono::DateTime::from_timestamp(token_data.claims.exp, 0)
                     .map(|dt| dt.to_string())
                     .unwrap_or_else(|| "Invalid".to_string()));
 
@@ -37,7 +37,7 @@ fn main() {
             if token_data.claims.exp < now {
                 println!("\n⚠️  TOKEN HAS EXPIRED!");
                 println!("Current time: {} ({})", now,
-                    chrono::NaiveDateTime::from_timestamp_opt(now, 0)
+                    chrono::DateTime::from_timestamp(now, 0)
                         .map(|dt| dt.to_string())
                         .unwrap_or_else(|| "Invalid".to_string()));
             } else {
@@ -29,6 +29,12 @@ pub struct Config {
     pub health: HealthConfig,
     pub security: SecurityConfig,
     pub logging: LoggingConfig,
+
+    #[serde(default)]
+    pub proxy: ProxyConfig,
+
+    #[serde(default)]
+    pub credentials: CredentialsConfig,
 }
 
 /// HTTP server configuration
@@ -48,6 +54,24 @@ pub struct ServerConfig {
 
     #[serde(default = "default_max_request_size")]
     pub max_request_size_mb: usize,
+
+    /// Base URL clients use to reach the gateway (e.g. `https://redis.example.com`).
+    /// Defaults to `http://<host>:<port>`.
+    #[serde(default)]
+    pub public_url: Option<String>,
+}
+
+impl ServerConfig {
+    /// Base URL of the gateway as seen by clients, without a trailing slash
+    pub fn public_base_url(&self) -> String {
+        match &self.public_url {
+            Some(url) => url.trim_end_matches('/').to_string(),
+            None => {
+                let host = if self.host == "0.0.0.0" { "localhost" } else { self.host.as_str() };
+                format!("http://{}:{}", host, self.port)
+            }
+        }
+    }
 }
 
 /// Database configuration
@@ -85,6 +109,30 @@ pub struct RedisConfig {
 
     #[serde(default = "default_pool_size")]
     pub pool_size: usize,
+
+    /// Instances are only reachable over TLS; connection URLs handed out use `rediss://`
+    #[serde(default)]
+    pub instance_tls: bool,
+
+    /// Logical databases of the local Redis (its `databases` setting); development instances
+    /// each get one of databases 1 and up
+    #[serde(default = "default_dev_databases")]
+    pub dev_databases: u32,
+
+    /// Where data-plane commands run
+    #[serde(default)]
+    pub backend: RedisBackendKind,
+}
+
+/// Backend serving data-plane commands
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RedisBackendKind {
+    /// The instances' Redis servers
+    #[default]
+    Redis,
+    /// Data kept in the gateway process and lost on restart; for tests and demos
+    Memory,
 }
 
 /// Rate limiting configuration
@@ -142,6 +190,10 @@ pub struct SecurityConfig {
 
     #[serde(default = "default_cors_origins")]
     pub cors_allowed_origins: Vec<String>,
+
+    /// How long API key revocation status is cached before re-checking the database
+    #[serde(default = "default_api_key_cache_ttl")]
+    pub api_key_cache_ttl_seconds: u64,
 }
 
 /// Logging configuration
@@ -160,6 +212,49 @@ pub struct LoggingConfig {
     pub log_file_path: String,
 }
 
+/// Native RESP proxy listener configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProxyConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    #[serde(default = "default_host")]
+    pub host: String,
+
+    #[serde(default = "default_proxy_port")]
+    pub port: u16,
+
+    #[serde(default = "default_proxy_max_clients")]
+    pub max_clients: usize,
+}
+
+/// Encryption of stored instance credentials
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CredentialsConfig {
+    /// Base64-encoded 32-byte AES-256 key; takes precedence over `master_key_file`
+    #[serde(default)]
+    pub master_key: Option<String>,
+
+    /// File holding the base64-encoded master key (generated on first start if missing)
+    #[serde(default = "default_master_key_file")]
+    pub master_key_file: String,
+
+    /// Version of the current master key, stored next to every encrypted secret
+    #[serde(default = "default_key_version")]
+    pub key_version: i32,
+
+    /// Retired keys that are still needed to decrypt secrets encrypted before a rotation
+    #[serde(default)]
+    pub previous_keys: Vec<PreviousKeyConfig>,
+}
+
+/// A retired master key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreviousKeyConfig {
+    pub version: i32,
+    pub key: String,
+}
+
 // Default value functions
 fn default_host() -> String { "0.0.0.0".to_string() }
 fn default_port() -> u16 { 3000 }
@@ -176,6 +271,7 @@ fn default_redis_timeout() -> u64 { 5000 }
 fn default_max_retries() -> u32 { 3 }
 fn default_retry_delay() -> u64 { 1000 }
 fn default_pool_size() -> usize { 10 }
+fn default_dev_databases() -> u32 { 16 }
 
 fn default_rate_limit_rps() -> u32 { 100 }
 fn default_burst_size() -> u32 { 20 }
@@ -190,13 +286,113 @@ fn default_check_interval() -> u64 { 30 }
 
 fn default_token_expiry() -> u64 { 24 }
 fn default_api_key_expiry() -> u64 { 365 }
+fn default_api_key_cache_ttl() -> u64 { 10 }
 fn default_cors_origins() -> Vec<String> {
     vec!["http://localhost:3000".to_string()]
 }
 
+fn default_proxy_port() -> u16 { 6380 }
+fn default_proxy_max_clients() -> usize { 10_000 }
+
+fn default_master_key_file() -> String { "keys/master.key".to_string() }
+fn default_key_version() -> i32 { 1 }
+
 fn default_log_level() -> String { "info".to_string() }
 fn default_log_file() -> String { "logs/redisgate.log".to_string() }
 
+impl Default for RedisConfig {
+    fn default() -> Self {
+        Self {
+            default_timeout_ms: default_redis_timeout(),
+            max_retries: default_max_retries(),
+            retry_delay_ms: default_retry_delay(),
+            pool_size: default_pool_size(),
+            instance_tls: false,
+            dev_databases: default_dev_databases(),
+            backend: RedisBackendKind::default(),
+        }
+    }
+}
+
+impl Default for ProxyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: default_host(),
+            port: default_proxy_port(),
+            max_clients: default_proxy_max_clients(),
+        }
+    }
+}
+
+impl Default for CredentialsConfig {
+    fn default() -> Self {
+        Self {
+            master_key: None,
+            master_key_file: default_master_key_file(),
+            key_version: default_key_version(),
+            previous_keys: Vec::new(),
+        }
+    }
+}
+
+impl Default for Config {
+    /// Built-in defaults, used when no configuration file is available (e.g. in tests).
+    /// The database URL and JWT secret are left empty and must be provided separately.
+    fn default() -> Self {
+        Self {
+            server: ServerConfig {
+                host: default_host(),
+                port: default_port(),
+                workers: default_workers(),
+                request_timeout_seconds: default_request_timeout(),
+                max_request_size_mb: default_max_request_size(),
+                public_url: None,
+            },
+            database: DatabaseConfig {
+                url: String::new(),
+                max_connections: default_max_connections(),
+                min_connections: default_min_connections(),
+                connection_timeout_seconds: default_connection_timeout(),
+                idle_timeout_seconds: default_idle_timeout(),
+                enable_logging: false,
+            },
+            redis: RedisConfig::default(),
+            rate_limit: RateLimitConfig {
+                default_requests_per_second: default_rate_limit_rps(),
+                burst_size: default_burst_size(),
+                enabled: default_enabled(),
+            },
+            metrics: MetricsConfig {
+                enabled: default_enabled(),
+                path: default_metrics_path(),
+                histogram_buckets: default_histogram_buckets(),
+            },
+            health: HealthConfig {
+                enabled: default_enabled(),
+                check_interval_seconds: default_check_interval(),
+            },
+            security: SecurityConfig {
+                jwt_secret: String::new(),
+                token_expiry_hours: default_token_expiry(),
+                api_key_expiry_days: default_api_key_expiry(),
+                enable_https: false,
+                enable_cors: default_enabled(),
+                cors_allowed_origins: default_cors_origins(),
+                api_key_cache_ttl_seconds: default_api_key_cache_ttl(),
+            },
+            logging: LoggingConfig {
+                level: default_log_level(),
+                json_format: false,
+                log_to_file: false,
+                log_file_path: default_log_file(),
+            },
+            proxy: ProxyConfig::default(),
+            credentials: CredentialsConfig::default(),
+        }
+    }
+}
+
 impl Config {
     /// Load configuration from file
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
@@ -249,6 +445,10 @@ impl Config {
             info!("Override: SERVER_PORT = {}", port);
             self.server.port = port;
         }
+        if let Ok(url) = std::env::var("SERVER_PUBLIC_URL") {
+            info!("Override: SERVER_PUBLIC_URL = {}", url);
+            self.server.public_url = Some(url);
+        }
 
         // Database overrides
         if let Ok(url) = std::env::var("DATABASE_URL") {
@@ -262,6 +462,12 @@ impl Config {
             self.security.jwt_secret = secret;
         }
 
+        // Credentials overrides
+        if let Ok(key) = std::env::var("CREDENTIALS_MASTER_KEY") {
+            info!("Override: CREDENTIALS_MASTER_KEY (hidden)");
+            self.credentials.master_key = Some(key);
+        }
+
         // Rate limit overrides
         if let Ok(rps) = std::env::var("RATE_LIMIT_RPS") {
             let rps: u32 = rps.parse().map_err(|_| {
@@ -305,6 +511,25 @@ impl Config {
             warn!("JWT secret is shorter than recommended 32 characters");
         }
 
+        // Validate proxy config
+        if self.proxy.enabled && self.proxy.port == self.server.port {
+            return Err(ConfigError::Validation(
+                "Proxy port must differ from the HTTP server port".to_string()
+            ));
+        }
+
+        // Validate credentials config
+        if self
+            .credentials
+            .previous_keys
+            .iter()
+            .any(|k| k.version == self.credentials.key_version)
+        {
+            return Err(ConfigError::Validation(
+                "Previous credential key versions must differ from the current key_version".to_string()
+            ));
+        }
+
         // Validate rate limit
         if self.rate_limit.enabled && self.rate_limit.default_requests_per_second == 0 {
             return Err(ConfigError::Validation(
@@ -4,10 +4,12 @@ use axum::{
     response::{IntoResponse, Response},
     Json,
 };
-use serde_json::json;
+use serde_json::{json, Value};
 use thiserror::Error;
 use tracing::{error, warn, debug};
 
+use crate::services::cluster::ClusterError;
+
 /// Application-wide error types
 #[derive(Error, Debug)]
 pub enum AppError {
@@ -32,6 +34,38 @@ pub enum AppError {
     #[error("Redis error: {0}")]
     Redis(String),
 
+    /// Redis refused the command itself, e.g. wrong arity, bad syntax or a non-integer value
+    #[error("Redis command error: {0}")]
+    RedisCommand(String),
+
+    /// The command does not apply to the type of value stored at the key
+    #[error("Redis wrong type: {0}")]
+    RedisWrongType(String),
+
+    /// EVALSHA named a script the server has not loaded
+    #[error("Redis script not loaded: {0}")]
+    RedisNoScript(String),
+
+    /// The server is at `maxmemory` and refuses writes
+    #[error("Redis out of memory: {0}")]
+    RedisOutOfMemory(String),
+
+    /// The server is a read-only replica, e.g. during failover
+    #[error("Redis read-only: {0}")]
+    RedisReadOnly(String),
+
+    /// Keys of a command on a cluster instance hash to different slots
+    #[error("Redis cross-slot: {0}")]
+    RedisCrossSlot(String),
+
+    /// The Redis server could not be reached or dropped the connection
+    #[error("Redis unavailable: {0}")]
+    BackendUnavailable(String),
+
+    /// Redis did not reply in time
+    #[error("Redis timeout: {0}")]
+    Timeout(String),
+
     #[error("Kubernetes error: {0}")]
     Kubernetes(String),
 
@@ -45,9 +79,35 @@ pub enum AppError {
     BadRequest(String),
 }
 
-impl IntoResponse for AppError {
-    fn into_response(self) -> Response {
-        let (status, error_message) = match &self {
+impl AppError {
+    /// Stable machine-readable code sent as `code` in error responses
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Database(_) => "DATABASE_ERROR",
+            AppError::Authentication(_) => "UNAUTHENTICATED",
+            AppError::Authorization(_) => "FORBIDDEN",
+            AppError::Validation(_) => "VALIDATION_ERROR",
+            AppError::NotFound(_) => "NOT_FOUND",
+            AppError::Conflict(_) => "CONFLICT",
+            AppError::Redis(_) => "REDIS_ERROR",
+            AppError::RedisCommand(_) => "COMMAND_ERROR",
+            AppError::RedisWrongType(_) => "WRONGTYPE",
+            AppError::RedisNoScript(_) => "NOSCRIPT",
+            AppError::RedisOutOfMemory(_) => "OOM",
+            AppError::RedisReadOnly(_) => "READONLY",
+            AppError::RedisCrossSlot(_) => "CROSSSLOT",
+            AppError::BackendUnavailable(_) => "BACKEND_UNAVAILABLE",
+            AppError::Timeout(_) => "TIMEOUT",
+            AppError::Kubernetes(_) => "KUBERNETES_ERROR",
+            AppError::RateLimitExceeded => "RATE_LIMITED",
+            AppError::Internal(_) => "INTERNAL_ERROR",
+            AppError::BadRequest(_) => "BAD_REQUEST",
+        }
+    }
+
+    /// Log the error and build the status and JSON envelope sent to the client
+    fn to_response_parts(&self) -> (StatusCode, Json<Value>) {
+        let (status, error_message) = match self {
             AppError::Database(e) => {
                 error!("Database error: {}", e);
                 (StatusCode::INTERNAL_SERVER_ERROR, "Database error occurred")
@@ -76,6 +136,36 @@ impl IntoResponse for AppError {
                 error!("Redis error: {}", msg);
                 (StatusCode::INTERNAL_SERVER_ERROR, msg.as_str())
             }
+            AppError::RedisCommand(msg) => {
+                debug!("Redis command error: {}", msg);
+                (StatusCode::BAD_REQUEST, msg.as_str())
+            }
+            AppError::RedisWrongType(msg) => {
+                debug!("Redis wrong type: {}", msg);
+                (StatusCode::BAD_REQUEST, msg.as_str())
+            }
+            AppError::RedisNoScript(msg) => {
+                // The client can load the script and retry
+                debug!("Redis script not loaded: {}", msg);
+                (StatusCode::CONFLICT, msg.as_str())
+            }
+            AppError::RedisCrossSlot(msg) => {
+                // Fixed by using hash tags, e.g. {user:1}:name and {user:1}:email
+                debug!("Redis cross-slot request: {}", msg);
+                (StatusCode::BAD_REQUEST, msg.as_str())
+            }
+            AppError::RedisOutOfMemory(msg) | AppError::RedisReadOnly(msg) => {
+                warn!("Redis refused write: {}", msg);
+                (StatusCode::SERVICE_UNAVAILABLE, msg.as_str())
+            }
+            AppError::BackendUnavailable(msg) => {
+                error!("Redis unavailable: {}", msg);
+                (StatusCode::SERVICE_UNAVAILABLE, msg.as_str())
+            }
+            AppError::Timeout(msg) => {
+                error!("Redis timeout: {}", msg);
+                (StatusCode::GATEWAY_TIMEOUT, msg.as_str())
+            }
             AppError::Kubernetes(msg) => {
                 error!("Kubernetes error: {}", msg);
                 (StatusCode::INTERNAL_SERVER_ERROR, msg.as_str())
@@ -97,10 +187,63 @@ impl IntoResponse for AppError {
         let body = Json(json!({
             "success": false,
             "error": error_message,
+            "code": self.code(),
             "timestamp": chrono::Utc::now().to_rfc3339(),
         }));
 
-        (status, body).into_response()
+        (status, body)
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        self.to_response_parts().into_response()
+    }
+}
+
+/// Lets handlers that return `(StatusCode, Json<Value>)` errors use `?` on `AppError`
+impl From<AppError> for (StatusCode, Json<Value>) {
+    fn from(e: AppError) -> Self {
+        e.to_response_parts()
+    }
+}
+
+/// Classify a Redis failure by the server's error code or the kind of client failure
+impl From<redis::RedisError> for AppError {
+    fn from(e: redis::RedisError) -> Self {
+        let message = redis_error_message(&e);
+        match e.code() {
+            Some("WRONGTYPE") => AppError::RedisWrongType(message),
+            Some("NOSCRIPT") => AppError::RedisNoScript(message),
+            Some("OOM") => AppError::RedisOutOfMemory(message),
+            Some("READONLY") => AppError::RedisReadOnly(message),
+            Some("CROSSSLOT") => AppError::RedisCrossSlot(message),
+            // Generic errors, and EXEC refusing a transaction with such a command queued
+            Some("ERR" | "EXECABORT") => AppError::RedisCommand(message),
+            // Replies that mean the server is up but cannot serve data yet
+            Some("LOADING" | "MASTERDOWN" | "CLUSTERDOWN" | "TRYAGAIN") => AppError::BackendUnavailable(message),
+            _ if e.is_timeout() => AppError::Timeout(message),
+            _ if e.is_io_error() || e.is_connection_dropped() || e.is_connection_refusal() => {
+                AppError::BackendUnavailable(message)
+            }
+            _ => AppError::Redis(message),
+        }
+    }
+}
+
+impl From<ClusterError> for AppError {
+    fn from(e: ClusterError) -> Self {
+        match e {
+            ClusterError::CrossSlot => AppError::RedisCrossSlot(e.to_string()),
+        }
+    }
+}
+
+/// Error text of a Redis failure as the server sent it, e.g. `WRONGTYPE Operation against ...`
+pub fn redis_error_message(e: &redis::RedisError) -> String {
+    match (e.code(), e.detail()) {
+        (Some(code), Some(detail)) => format!("{} {}", code, detail),
+        _ => e.to_string(),
     }
 }
 
@@ -109,3 +252,70 @@ pub fn validation_error(errors: validator::ValidationErrors) -> AppError {
     AppError::Validation(format!("{:?}", errors))
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reply_error(reply: &str) -> redis::RedisError {
+        redis::parse_redis_value(format!("-{}\r\n", reply).as_bytes()).unwrap_err()
+    }
+
+    #[test]
+    fn test_redis_errors_are_classified() {
+        let e = AppError::from(reply_error("WRONGTYPE Operation against a key holding the wrong kind of value"));
+        assert_eq!(e.code(), "WRONGTYPE");
+        assert_eq!(e.to_response_parts().0, StatusCode::BAD_REQUEST);
+
+        let e = AppError::from(reply_error("NOSCRIPT No matching script. Please use EVAL."));
+        assert_eq!(e.code(), "NOSCRIPT");
+        assert_eq!(e.to_response_parts().0, StatusCode::CONFLICT);
+
+        let e = AppError::from(reply_error("OOM command not allowed when used memory > 'maxmemory'."));
+        assert_eq!(e.code(), "OOM");
+        assert_eq!(e.to_response_parts().0, StatusCode::SERVICE_UNAVAILABLE);
+
+        let e = AppError::from(reply_error("READONLY You can't write against a read only replica."));
+        assert_eq!(e.code(), "READONLY");
+
+        let e = AppError::from(reply_error("CROSSSLOT Keys in request don't hash to the same slot"));
+        assert_eq!(e.code(), "CROSSSLOT");
+        assert_eq!(e.to_response_parts().0, StatusCode::BAD_REQUEST);
+        assert_eq!(AppError::from(ClusterError::CrossSlot).to_string(), e.to_string());
+
+        let e = AppError::from(reply_error("LOADING Redis is loading the dataset in memory"));
+        assert_eq!(e.code(), "BACKEND_UNAVAILABLE");
+
+        let e = AppError::from(reply_error("ERR wrong number of arguments for 'get' command"));
+        assert_eq!(e.code(), "COMMAND_ERROR");
+        assert_eq!(e.to_response_parts().0, StatusCode::BAD_REQUEST);
+
+        let e = AppError::from(reply_error("ERR value is not an integer or out of range"));
+        assert_eq!(e.code(), "COMMAND_ERROR");
+
+        let e = AppError::from(reply_error("NOAUTH Authentication required."));
+        assert_eq!(e.code(), "REDIS_ERROR");
+    }
+
+    #[test]
+    fn test_connection_failures_are_classified() {
+        let refused = std::io::Error::new(std::io::ErrorKind::ConnectionRefused, "refused");
+        let e = AppError::from(redis::RedisError::from(refused));
+        assert_eq!(e.code(), "BACKEND_UNAVAILABLE");
+        assert_eq!(e.to_response_parts().0, StatusCode::SERVICE_UNAVAILABLE);
+
+        let timed_out = std::io::Error::new(std::io::ErrorKind::TimedOut, "timed out");
+        let e = AppError::from(redis::RedisError::from(timed_out));
+        assert_eq!(e.code(), "TIMEOUT");
+        assert_eq!(e.to_response_parts().0, StatusCode::GATEWAY_TIMEOUT);
+    }
+
+    #[test]
+    fn test_envelope_carries_code() {
+        let (status, Json(body)) = AppError::RedisWrongType("WRONGTYPE Operation".to_string()).to_response_parts();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], "WRONGTYPE");
+        assert_eq!(body["error"], "WRONGTYPE Operation");
+        assert_eq!(body["success"], false);
+    }
+}
@@ -31,7 +31,7 @@ fn api_key_to_response(api_key: ApiKey) -> ApiKeyResponse {
         last_used_at: api_key.last_used_at,
         is_active: api_key.is_active.unwrap_or(true),
         expires_at: api_key.expires_at,
-        created_at: api_key.created_at.unwrap_or_else(|| Utc::now()),
+        created_at: api_key.created_at.unwrap_or_else(Utc::now),
     }
 }
 
@@ -415,6 +415,9 @@ pub async fn revoke_api_key(
         )
     })?;
 
+    // Stop accepting the key right away instead of waiting for the cache TTL
+    state.api_key_cache.revoke(key_id).await;
+
     Ok(Json(ApiResponse {
         success: true,
         data: None,
@@ -1,7 +1,7 @@
 // Authentication handlers (register, login)
 
 use axum::{extract::State, http::StatusCode, response::Json, Extension};
-use chrono::Utc;
+use chrono::{Duration, Utc};
 use std::sync::Arc;
 use uuid::Uuid;
 use validator::Validate;
@@ -14,11 +14,6 @@ use crate::models::User;
 
 type ErrorResponse = (StatusCode, Json<ApiResponse<()>>);
 
-// Helper function to create error responses
-fn error_response(status: StatusCode, message: String) -> ErrorResponse {
-    (status, Json(ApiResponse::<()>::error(message)))
-}
-
 // Helper function to convert User to UserResponse
 fn user_to_response(user: User) -> UserResponse {
     UserResponse {
@@ -29,7 +24,7 @@ fn user_to_response(user: User) -> UserResponse {
         last_name: user.last_name,
         is_active: user.is_active.unwrap_or(true),
         is_verified: user.is_verified.unwrap_or(false),
-        created_at: user.created_at.unwrap_or_else(|| Utc::now()),
+        created_at: user.created_at.unwrap_or_else(Utc::now),
     }
 }
 
@@ -233,6 +228,9 @@ pub async fn login(
         let api_key_id = Uuid::new_v4();
         let key_prefix = format!("rg_{}", &api_key_id.to_string().replace("-", "")[..12]);
 
+        let now = Utc::now();
+        let expires_at = now + Duration::days(state.api_key_expiry_days as i64);
+
         // Create API key claims with full permissions
         let api_key_claims = crate::auth::ApiKeyClaims::new(
             api_key_id,
@@ -240,7 +238,7 @@ pub async fn login(
             org_id,
             vec!["*".to_string()], // Full permissions
             key_prefix.clone(),
-            None, // No expiration
+            Some(expires_at),
         );
 
         // Generate JWT token for API key
@@ -253,11 +251,10 @@ pub async fn login(
         })?;
 
         // Save API key to database (store JWT token directly)
-        let now = Utc::now();
         sqlx::query!(
             r#"
-            INSERT INTO api_keys (id, name, key_token, key_prefix, user_id, organization_id, scopes, created_at, updated_at)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            INSERT INTO api_keys (id, name, key_token, key_prefix, user_id, organization_id, scopes, expires_at, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
             "#,
             api_key_id,
             format!("Auto-generated key for {}", user.email),
@@ -266,6 +263,7 @@ pub async fn login(
             user.id,
             org_id,
             &vec!["*".to_string()],
+            expires_at,
             now,
             now
         )
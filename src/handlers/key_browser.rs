@@ -0,0 +1,209 @@
+// Key browser over SCAN, for inspecting instances without blocking them with KEYS
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::handlers::redis::{
+    authenticate_request, authorize_instance_command, command_failed, get_redis_connection, is_cluster,
+    unexpected_reply, ErrorResponse, RedisResponse,
+};
+use crate::middleware::AppState;
+use crate::services::cluster;
+use crate::services::key_browser::{self, KeyInfo};
+use crate::services::redis_backend::BackendConnection;
+
+/// Default and largest SCAN COUNT hint
+const DEFAULT_COUNT: u64 = 50;
+const MAX_COUNT: u64 = 500;
+
+/// Browse keys: `GET /redis/:instance_id/keys?match=user:*&type=hash&cursor=...&count=50`
+///
+/// Returns one SCAN page. Like SCAN, a page may hold fewer keys than `count` (or none) while
+/// `cursor` is not null; pass `cursor` back to continue until it is null.
+///
+/// MEMORY USAGE is an admin command, so `memory_usage` is only filled in for keys that may
+/// run it. Previews are likewise left out for types whose read commands are not allowed.
+///
+/// On a cluster instance SCAN walks the node it reaches, so with several shards a page only
+/// covers that node's keys.
+pub async fn handle_browse_keys(
+    State(state): State<Arc<AppState>>,
+    Path(instance_id): Path<Uuid>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Result<Json<RedisResponse>, ErrorResponse> {
+    let bad_request = |message: String| (StatusCode::BAD_REQUEST, Json(json!({ "error": message })));
+
+    let pattern = query.get("match").cloned().unwrap_or_else(|| "*".to_string());
+    let key_type = query
+        .get("type")
+        .map(|key_type| key_browser::parse_type(key_type))
+        .transpose()
+        .map_err(|e| bad_request(e.to_string()))?;
+    let cursor = query
+        .get("cursor")
+        .map(|cursor| key_browser::decode_cursor(cursor))
+        .transpose()
+        .map_err(|e| bad_request(e.to_string()))?
+        .unwrap_or(0);
+    let count = match query.get("count") {
+        Some(count) => count
+            .parse::<u64>()
+            .ok()
+            .filter(|count| (1..=MAX_COUNT).contains(count))
+            .ok_or_else(|| bad_request(format!("count must be between 1 and {}", MAX_COUNT)))?,
+        None => DEFAULT_COUNT,
+    };
+
+    let (instance, claims) = authenticate_request(&state, &headers, query, instance_id).await?;
+    for command in ["SCAN", "TYPE", "PTTL"] {
+        authorize_instance_command(&state, &instance, &claims, command).await?;
+    }
+
+    // Optional commands: the page is still useful without them
+    let mut allowed = HashMap::new();
+    for command in ["MEMORY"] {
+        let ok = authorize_instance_command(&state, &instance, &claims, command).await.is_ok();
+        allowed.insert(command, ok);
+    }
+
+    let mut conn = get_redis_connection(&state, &instance).await?;
+    // Keys of a page are spread over slots, so on a cluster each round trip is split per slot
+    let per_slot = is_cluster(&instance);
+
+    let mut scan = redis::cmd("SCAN");
+    scan.arg(cursor).arg("MATCH").arg(&pattern).arg("COUNT").arg(count);
+    if let Some(key_type) = &key_type {
+        scan.arg("TYPE").arg(key_type);
+    }
+    let (next_cursor, keys): (u64, Vec<Vec<u8>>) = scan
+        .query_async(&mut conn)
+        .await
+        .map_err(|e| command_failed("SCAN", e))?;
+
+    if keys.is_empty() {
+        return Ok(Json(RedisResponse {
+            result: json!({ "keys": [], "cursor": key_browser::encode_cursor(next_cursor) }),
+        }));
+    }
+
+    // First round trip: type and TTL of every key
+    let mut pipe = redis::pipe();
+    for key in &keys {
+        pipe.cmd("TYPE").arg(key).cmd("PTTL").arg(key);
+    }
+    let replies = query_pipeline(&mut conn, &pipe, per_slot)
+        .await
+        .map_err(|e| command_failed("TYPE", e))?;
+    if replies.len() != keys.len() * 2 {
+        return Err(unexpected_reply());
+    }
+
+    let mut found = Vec::with_capacity(keys.len());
+    for (key, replies) in keys.iter().zip(replies.chunks(2)) {
+        let key_type = match &replies[0] {
+            redis::Value::Status(key_type) => key_type.clone(),
+            redis::Value::Data(bytes) => String::from_utf8_lossy(bytes).into_owned(),
+            _ => return Err(unexpected_reply()),
+        };
+        // Deleted or expired since SCAN returned it
+        if key_type == "none" {
+            continue;
+        }
+        found.push((key, key_type, key_browser::ttl_ms(&replies[1])));
+    }
+
+    for (_, key_type, _) in &found {
+        if let Some((length_command, preview_command)) = key_browser::preview_commands(key_type) {
+            for command in [length_command, preview_command] {
+                if !allowed.contains_key(command) {
+                    let ok = authorize_instance_command(&state, &instance, &claims, command).await.is_ok();
+                    allowed.insert(command, ok);
+                }
+            }
+        }
+    }
+
+    // Second round trip: size, length and preview of every key
+    let memory = allowed["MEMORY"];
+    let mut pipe = redis::pipe();
+    let mut layout = Vec::with_capacity(found.len());
+    for (key, key_type, _) in &found {
+        if memory {
+            pipe.cmd("MEMORY").arg("USAGE").arg(*key);
+        }
+        let commands = key_browser::preview_commands(key_type)
+            .filter(|(length, preview)| allowed[length] && allowed[preview]);
+        if let Some((length_command, preview_command)) = commands {
+            pipe.cmd(length_command).arg(*key);
+            pipe.cmd(preview_command).arg(*key).arg(key_browser::preview_args(key_type));
+        }
+        layout.push(commands.is_some());
+    }
+    let mut replies: std::vec::IntoIter<redis::Value> = if memory || layout.contains(&true) {
+        query_pipeline(&mut conn, &pipe, per_slot)
+            .await
+            .map_err(|e| command_failed("MEMORY USAGE", e))?
+            .into_iter()
+    } else {
+        Vec::new().into_iter()
+    };
+
+    let mut page = Vec::with_capacity(found.len());
+    for ((key, key_type, ttl_ms), has_preview) in found.into_iter().zip(layout) {
+        let memory_usage = if memory {
+            match replies.next().ok_or_else(unexpected_reply)? {
+                redis::Value::Int(bytes) => Some(bytes),
+                _ => None,
+            }
+        } else {
+            None
+        };
+
+        let (length, preview, truncated) = if has_preview {
+            let length = match replies.next().ok_or_else(unexpected_reply)? {
+                redis::Value::Int(length) => Some(length),
+                _ => None,
+            };
+            let reply = replies.next().ok_or_else(unexpected_reply)?;
+            let (preview, truncated) = key_browser::build_preview(&key_type, &reply, length);
+            (length, preview, truncated)
+        } else {
+            (None, serde_json::Value::Null, false)
+        };
+
+        page.push(KeyInfo {
+            key: String::from_utf8_lossy(key).into_owned(),
+            key_type,
+            ttl_ms,
+            memory_usage,
+            length,
+            preview,
+            truncated,
+        });
+    }
+
+    Ok(Json(RedisResponse {
+        result: json!({ "keys": page, "cursor": key_browser::encode_cursor(next_cursor) }),
+    }))
+}
+
+/// Run a pipeline, split per slot on cluster instances
+async fn query_pipeline(
+    conn: &mut BackendConnection,
+    pipe: &redis::Pipeline,
+    per_slot: bool,
+) -> redis::RedisResult<Vec<redis::Value>> {
+    if per_slot {
+        cluster::query_by_slot(conn, pipe).await
+    } else {
+        pipe.query_async(conn).await
+    }
+}
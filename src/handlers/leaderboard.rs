@@ -0,0 +1,95 @@
+// Leaderboard shortcuts over sorted sets, highest score first
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::handlers::redis::{
+    authenticate_request, authorize_instance_command, command_failed, get_redis_connection,
+    unexpected_reply, ErrorResponse, RedisResponse,
+};
+use crate::middleware::AppState;
+use crate::services::sorted_set::{self, RankedMember};
+
+/// Largest number of entries returned by the top-N route
+const MAX_LEADERBOARD_SIZE: u64 = 1000;
+
+/// Top `n` members of a leaderboard: `GET /redis/:instance_id/leaderboard/:key/top/:n`
+pub async fn handle_leaderboard_top(
+    State(state): State<Arc<AppState>>,
+    Path((instance_id, key, n)): Path<(Uuid, String, u64)>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Result<Json<RedisResponse>, ErrorResponse> {
+    if n == 0 || n > MAX_LEADERBOARD_SIZE {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": format!("n must be between 1 and {}", MAX_LEADERBOARD_SIZE)})),
+        ));
+    }
+
+    let (instance, claims) = authenticate_request(&state, &headers, query, instance_id).await?;
+    let command = authorize_instance_command(&state, &instance, &claims, "ZREVRANGE").await?;
+    let mut conn = get_redis_connection(&state, &instance).await?;
+
+    let reply: redis::Value = redis::cmd(&command)
+        .arg(&key)
+        .arg(0)
+        .arg(n - 1)
+        .arg("WITHSCORES")
+        .query_async(&mut conn)
+        .await
+        .map_err(|e| command_failed("ZREVRANGE", e))?;
+
+    let members = sorted_set::scored_members(&reply).ok_or_else(unexpected_reply)?;
+
+    Ok(Json(RedisResponse {
+        result: serde_json::to_value(sorted_set::ranked(members, 1)).unwrap_or(Value::Null),
+    }))
+}
+
+/// Rank and score of one member: `GET /redis/:instance_id/leaderboard/:key/rank/:member`
+///
+/// The result is `null` when the member is not on the leaderboard.
+pub async fn handle_leaderboard_rank(
+    State(state): State<Arc<AppState>>,
+    Path((instance_id, key, member)): Path<(Uuid, String, String)>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Result<Json<RedisResponse>, ErrorResponse> {
+    let (instance, claims) = authenticate_request(&state, &headers, query, instance_id).await?;
+    let rank_command = authorize_instance_command(&state, &instance, &claims, "ZREVRANK").await?;
+    let score_command = authorize_instance_command(&state, &instance, &claims, "ZSCORE").await?;
+    let mut conn = get_redis_connection(&state, &instance).await?;
+
+    // Both in one round trip
+    let (rank, score): (Option<u64>, redis::Value) = redis::pipe()
+        .cmd(&rank_command)
+        .arg(&key)
+        .arg(&member)
+        .cmd(&score_command)
+        .arg(&key)
+        .arg(&member)
+        .query_async(&mut conn)
+        .await
+        .map_err(|e| command_failed("ZREVRANK", e))?;
+
+    let entry = match rank {
+        Some(rank) => Some(RankedMember {
+            rank: rank + 1,
+            member,
+            score: sorted_set::parse_score(&score).ok_or_else(unexpected_reply)?,
+        }),
+        None => None,
+    };
+
+    Ok(Json(RedisResponse {
+        result: serde_json::to_value(entry).unwrap_or(Value::Null),
+    }))
+}
@@ -5,5 +5,12 @@ pub mod organizations;
 pub mod api_keys;
 pub mod redis_instances;
 pub mod redis;
+pub mod pubsub;
+pub mod websocket;
+pub mod resp_proxy;
 pub mod quota;
 pub mod monitoring;
+pub mod leaderboard;
+pub mod streams;
+pub mod scripts;
+pub mod key_browser;
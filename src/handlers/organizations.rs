@@ -31,8 +31,8 @@ fn organization_to_response(organization: Organization) -> OrganizationResponse
         plan: organization.plan.unwrap_or_else(|| "free".to_string()),
         max_redis_instances: organization.max_redis_instances.unwrap_or(3),
         max_api_keys: organization.max_api_keys.unwrap_or(10),
-        created_at: organization.created_at.unwrap_or_else(|| Utc::now()),
-        updated_at: organization.updated_at.unwrap_or_else(|| Utc::now()),
+        created_at: organization.created_at.unwrap_or_else(Utc::now),
+        updated_at: organization.updated_at.unwrap_or_else(Utc::now),
     }
 }
 
@@ -0,0 +1,206 @@
+// Redis Pub/Sub over Server-Sent Events
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::sse::{Event, KeepAlive, Sse},
+    Json,
+};
+use futures::{stream, Stream, StreamExt};
+use redis::aio::PubSub;
+use redis::Msg;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::error::{redis_error_message, AppError};
+use crate::handlers::redis::{
+    authenticate_and_get_instance, authorize_instance_command, extract_api_key, get_instance_endpoint, ErrorResponse,
+};
+use crate::middleware::AppState;
+use crate::models::RedisInstance;
+use crate::services::redis_backend::BackendConnection;
+use crate::services::redis_pool::RedisEndpoint;
+
+/// How often a heartbeat comment is sent to keep idle connections (and proxies) open
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Whether a subscription targets a single channel or a glob-style pattern
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum SubscriptionKind {
+    Channel,
+    Pattern,
+}
+
+impl SubscriptionKind {
+    pub(crate) fn command(&self) -> &'static str {
+        match self {
+            SubscriptionKind::Channel => "SUBSCRIBE",
+            SubscriptionKind::Pattern => "PSUBSCRIBE",
+        }
+    }
+
+    pub(crate) fn unsubscribe_command(&self) -> &'static str {
+        match self {
+            SubscriptionKind::Channel => "UNSUBSCRIBE",
+            SubscriptionKind::Pattern => "PUNSUBSCRIBE",
+        }
+    }
+}
+
+/// Logs when a subscription stream is dropped, i.e. when the client disconnects
+///
+/// Dropping the stream also drops the dedicated Redis connection, which makes Redis
+/// remove the subscription on its side.
+struct SubscriptionGuard {
+    instance_id: Uuid,
+    kind: SubscriptionKind,
+    target: String,
+}
+
+impl Drop for SubscriptionGuard {
+    fn drop(&mut self) {
+        info!(
+            "SSE client disconnected, ending {} {} on instance {}",
+            self.kind.command(),
+            self.target,
+            self.instance_id
+        );
+    }
+}
+
+/// Stream messages published to a channel as Server-Sent Events
+pub async fn handle_subscribe(
+    State(state): State<Arc<AppState>>,
+    Path((instance_id, channel)): Path<(Uuid, String)>,
+    headers: HeaderMap,
+    Query(query): Query<HashMap<String, String>>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ErrorResponse> {
+    subscribe(state, instance_id, channel, SubscriptionKind::Channel, headers, query).await
+}
+
+/// Stream messages published to channels matching a pattern as Server-Sent Events
+pub async fn handle_psubscribe(
+    State(state): State<Arc<AppState>>,
+    Path((instance_id, pattern)): Path<(Uuid, String)>,
+    headers: HeaderMap,
+    Query(query): Query<HashMap<String, String>>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ErrorResponse> {
+    subscribe(state, instance_id, pattern, SubscriptionKind::Pattern, headers, query).await
+}
+
+/// Open a dedicated Pub/Sub connection subscribed to a channel or pattern
+///
+/// Subscribed connections cannot run other commands, so every subscription gets its own.
+/// `target` is the channel or pattern as the client named it, without the instance's
+/// channel namespace.
+pub(crate) async fn open_subscription(
+    state: &AppState,
+    instance: &RedisInstance,
+    kind: SubscriptionKind,
+    target: &str,
+) -> Result<PubSub, ErrorResponse> {
+    let endpoint = get_instance_endpoint(state, instance).await?;
+    let mut pubsub = state.redis_backend.pubsub(&endpoint).await.map_err(|e| {
+        error!("Failed to open Pub/Sub connection for instance {}: {}", instance.id, e);
+        ErrorResponse::from(AppError::BackendUnavailable(format!("Redis instance unavailable: {}", e)))
+    })?;
+    let mut targets = [target.to_string()];
+    RedisEndpoint::namespace_channels(instance, kind.command(), &mut targets);
+    let subscribed = match kind {
+        SubscriptionKind::Channel => pubsub.subscribe(&targets[0]).await,
+        SubscriptionKind::Pattern => pubsub.psubscribe(&targets[0]).await,
+    };
+    subscribed.map_err(|e| {
+        error!("{} {} failed on instance {}: {}", kind.command(), target, instance.id, e);
+        ErrorResponse::from(AppError::BackendUnavailable(format!(
+            "Subscription failed: {}",
+            redis_error_message(&e)
+        )))
+    })?;
+
+    Ok(pubsub)
+}
+
+/// Open the connection a WebSocket session subscribes on
+///
+/// Unlike `open_subscription`, one connection serves all of the session's channels and
+/// patterns; see `RedisBackend::subscriber`.
+pub(crate) async fn open_subscriber(state: &AppState, instance: &RedisInstance) -> Result<BackendConnection, ErrorResponse> {
+    let endpoint = get_instance_endpoint(state, instance).await?;
+    state.redis_backend.subscriber(&endpoint).await.map_err(|e| {
+        error!("Failed to open Pub/Sub connection for instance {}: {}", instance.id, e);
+        ErrorResponse::from(AppError::BackendUnavailable(format!("Redis instance unavailable: {}", e)))
+    })
+}
+
+/// JSON representation of a published message, with the instance's channel namespace
+/// (see `RedisEndpoint::channel_namespace`) removed from its channel and pattern
+pub(crate) fn message_json(msg: &Msg, namespace: Option<&str>) -> Value {
+    let strip = |name: String| match namespace.and_then(|namespace| name.strip_prefix(namespace)) {
+        Some(name) => name.to_string(),
+        None => name,
+    };
+    let pattern = if msg.from_pattern() {
+        msg.get_pattern::<String>().ok().map(strip)
+    } else {
+        None
+    };
+
+    json!({
+        "channel": strip(msg.get_channel_name().to_string()),
+        "pattern": pattern,
+        "message": String::from_utf8_lossy(msg.get_payload_bytes()),
+    })
+}
+
+async fn subscribe(
+    state: Arc<AppState>,
+    instance_id: Uuid,
+    target: String,
+    kind: SubscriptionKind,
+    headers: HeaderMap,
+    query: HashMap<String, String>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ErrorResponse> {
+    let api_key = extract_api_key(&headers, &Query(query)).ok_or_else(|| {
+        (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({"error": "Missing API key"})),
+        )
+    })?;
+
+    let (instance, claims) = authenticate_and_get_instance(&state, &api_key, instance_id).await?;
+    authorize_instance_command(&state, &instance, &claims, kind.command()).await?;
+
+    let pubsub = open_subscription(&state, &instance, kind, &target).await?;
+
+    info!("SSE client subscribed ({} {}) on instance {}", kind.command(), target, instance_id);
+
+    let confirmation = Event::default()
+        .event("subscribe")
+        .data(json!({"kind": kind.command().to_lowercase(), "target": target}).to_string());
+
+    let guard = SubscriptionGuard {
+        instance_id,
+        kind,
+        target,
+    };
+    let namespace = RedisEndpoint::channel_namespace(&instance);
+    let messages = pubsub.into_on_message().map(move |msg| {
+        let _guard = &guard;
+        let data = message_json(&msg, namespace.as_deref());
+        Ok(Event::default().event("message").data(data.to_string()))
+    });
+
+    let events = stream::once(async move { Ok(confirmation) }).chain(messages);
+
+    Ok(Sse::new(events).keep_alive(
+        KeepAlive::new()
+            .interval(HEARTBEAT_INTERVAL)
+            .text("heartbeat"),
+    ))
+}
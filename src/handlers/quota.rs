@@ -137,7 +137,7 @@ pub async fn update_quota(
 
     // Validate values
     if let Some(val) = payload.max_instances {
-        if val < 1 || val > 1000 {
+        if !(1..=1000).contains(&val) {
             return Err((
                 StatusCode::BAD_REQUEST,
                 Json(ApiResponse::<()>::error("max_instances must be between 1 and 1000".to_string())),
@@ -146,7 +146,7 @@ pub async fn update_quota(
     }
 
     if let Some(val) = payload.max_memory_gb {
-        if val < 1 || val > 10000 {
+        if !(1..=10000).contains(&val) {
             return Err((
                 StatusCode::BAD_REQUEST,
                 Json(ApiResponse::<()>::error("max_memory_gb must be between 1 and 10000".to_string())),
@@ -155,7 +155,7 @@ pub async fn update_quota(
     }
 
     if let Some(val) = payload.max_api_keys {
-        if val < 1 || val > 1000 {
+        if !(1..=1000).contains(&val) {
             return Err((
                 StatusCode::BAD_REQUEST,
                 Json(ApiResponse::<()>::error("max_api_keys must be between 1 and 1000".to_string())),
@@ -1,37 +1,43 @@
 // Redis HTTP API handlers
 
 use axum::{
+    body::Bytes,
     extract::{Path, Query, State},
-    http::{StatusCode, HeaderMap},
+    http::{StatusCode, HeaderMap, Uri},
     response::Json,
 };
-use redis::{Commands, Connection, Client};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use redis::aio::ConnectionLike;
+use redis::AsyncCommands;
 use serde_json::{json, Value};
 use std::collections::HashMap;
 use std::sync::Arc;
 use uuid::Uuid;
-use tracing::{info, warn, error};
+use tracing::{debug, info, warn, error};
 
 use crate::middleware::AppState;
 use crate::models::RedisInstance;
 use crate::auth::ApiKeyClaims;
-
-type ErrorResponse = (StatusCode, Json<Value>);
+use crate::error::{redis_error_message, AppError};
+use crate::services::blocking;
+use crate::services::cluster::{self, ClusterError, Topology};
+use crate::services::command_scope::{classify_command, scopes_allow};
+use crate::services::path_command::parse_path_command;
+use crate::services::redis_backend::BackendConnection;
+use crate::services::redis_pool::RedisEndpoint;
+use crate::services::scripts::RegisteredScript;
+use crate::services::sorted_set;
+
+pub type ErrorResponse = (StatusCode, Json<Value>);
 
 /// Redis command response format
 #[derive(serde::Serialize)]
 pub struct RedisResponse {
-    result: Value,
-}
-
-/// Redis error response format
-#[derive(serde::Serialize)]
-struct RedisErrorResponse {
-    error: String,
+    pub result: Value,
 }
 
 /// Extract API key from headers or query parameters
-fn extract_api_key(headers: &HeaderMap, query: &Query<HashMap<String, String>>) -> Option<String> {
+pub(crate) fn extract_api_key(headers: &HeaderMap, query: &Query<HashMap<String, String>>) -> Option<String> {
     // First try Authorization header
     if let Some(auth_header) = headers.get("authorization") {
         if let Ok(auth_str) = auth_header.to_str() {
@@ -52,13 +58,12 @@ fn extract_api_key(headers: &HeaderMap, query: &Query<HashMap<String, String>>)
     None
 }
 
-/// Authenticate API key (JWT) and get Redis instance
-async fn authenticate_and_get_instance(
+/// Verify an API key (JWT) and make sure it has not been revoked
+pub(crate) async fn authenticate_api_key(
     state: &AppState,
     api_key_token: &str,
-    instance_id: Uuid,
-) -> Result<(RedisInstance, ApiKeyClaims), ErrorResponse> {
-    // Verify JWT token directly (no database lookup needed!)
+) -> Result<ApiKeyClaims, ErrorResponse> {
+    // Verify JWT signature and expiry
     let token_data = state.jwt_manager.verify_api_key_token(api_key_token)
         .map_err(|_| {
             warn!("Invalid or expired API key token");
@@ -69,15 +74,82 @@ async fn authenticate_and_get_instance(
         })?;
 
     let claims = token_data.claims;
+    ensure_api_key_active(state, &claims).await?;
+
     info!("Authenticated API key: {} for organization: {}", claims.key_prefix, claims.organization_id);
 
+    Ok(claims)
+}
+
+/// Reject an API key that has expired or been revoked since it was authenticated
+///
+/// Long-lived sessions (the RESP proxy, WebSockets) call this before every command, so a
+/// revoked key loses access within the status cache's TTL.
+pub(crate) async fn ensure_api_key_active(state: &AppState, claims: &ApiKeyClaims) -> Result<(), ErrorResponse> {
+    if claims.exp <= chrono::Utc::now().timestamp() {
+        warn!("Rejected expired API key: {}", claims.key_prefix);
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            Json(json!({"error": "API key has expired"})),
+        ));
+    }
+
+    // The JWT may still be valid after the key was revoked or deactivated
+    let revoked = state
+        .api_key_cache
+        .is_revoked(&state.db_pool, claims.api_key_id)
+        .await
+        .map_err(|e| {
+            error!("Database error checking API key status: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": "Internal server error"})),
+            )
+        })?;
+
+    if revoked {
+        warn!("Rejected revoked or expired API key: {}", claims.key_prefix);
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            Json(json!({"error": "API key has been revoked"})),
+        ));
+    }
+    Ok(())
+}
+
+/// Authenticate a request by the API key in its headers or query string and get the Redis instance
+pub(crate) async fn authenticate_request(
+    state: &AppState,
+    headers: &HeaderMap,
+    query: HashMap<String, String>,
+    instance_id: Uuid,
+) -> Result<(RedisInstance, ApiKeyClaims), ErrorResponse> {
+    let api_key = extract_api_key(headers, &Query(query)).ok_or_else(|| {
+        (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({"error": "Missing API key"})),
+        )
+    })?;
+
+    authenticate_and_get_instance(state, &api_key, instance_id).await
+}
+
+/// Authenticate API key (JWT) and get Redis instance
+pub(crate) async fn authenticate_and_get_instance(
+    state: &AppState,
+    api_key_token: &str,
+    instance_id: Uuid,
+) -> Result<(RedisInstance, ApiKeyClaims), ErrorResponse> {
+    let claims = authenticate_api_key(state, api_key_token).await?;
+
     // Get Redis instance and verify organization access
     let instance = sqlx::query_as!(
         RedisInstance,
         r#"
         SELECT id, name, slug, organization_id, api_key_id,
                port, private_ip_address, public_ip_address, domain,
-               max_memory, current_memory, password_hash, redis_version,
+               max_memory, current_memory, password_hash, redis_version, topology,
+               sentinel_master_name, sentinel_endpoints, deployment_mode,
                namespace, pod_name, service_name,
                status, last_health_check_at, health_status,
                cpu_usage_percent, memory_usage_percent, connections_count, max_connections,
@@ -110,134 +182,322 @@ async fn authenticate_and_get_instance(
     Ok((instance, claims))
 }
 
-/// Get Redis connection for an instance
-async fn get_redis_connection(instance: &RedisInstance) -> Result<Connection, ErrorResponse> {
-    // Build Redis connection URL from instance details
-    let host = if let Some(domain) = &instance.domain {
-        domain.clone()
-    } else if let Some(public_ip) = &instance.public_ip_address {
-        public_ip.ip().to_string()
-    } else if let Some(private_ip) = &instance.private_ip_address {
-        private_ip.ip().to_string()
-    } else if let Some(service_name) = &instance.service_name {
-        // Use Kubernetes service name
-        service_name.clone()
-    } else {
-        // Fallback to localhost for development
-        warn!("No connection info found for instance {}, falling back to localhost", instance.id);
-        "127.0.0.1".to_string()
-    };
+/// Resolve an instance's endpoint, decrypting its password
+pub(crate) async fn get_instance_endpoint(state: &AppState, instance: &RedisInstance) -> Result<RedisEndpoint, ErrorResponse> {
+    state.credentials.endpoint(instance).await.map_err(|e| {
+        error!("Failed to load credentials for instance {}: {}", instance.id, e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({"error": "Failed to load instance credentials"})),
+        )
+    })
+}
 
-    let port = instance.port.unwrap_or(6379);
+/// Get the pooled Redis connection for an instance
+pub(crate) async fn get_redis_connection(state: &AppState, instance: &RedisInstance) -> Result<BackendConnection, ErrorResponse> {
+    let endpoint = get_instance_endpoint(state, instance).await?;
+    shared_connection(state, instance, &endpoint).await
+}
 
-    // Build connection URL
-    let redis_url = if let Some(password) = &instance.password_hash {
-        // Note: In production, you might want to decrypt this if it's encrypted
-        format!("redis://:{}@{}:{}/", password, host, port)
-    } else {
-        format!("redis://{}:{}/", host, port)
-    };
+/// Shared connection to an endpoint that has already been resolved
+async fn shared_connection(
+    state: &AppState,
+    instance: &RedisInstance,
+    endpoint: &RedisEndpoint,
+) -> Result<BackendConnection, ErrorResponse> {
+    state.redis_backend.connection(instance.id, endpoint).await.map_err(|e| {
+        error!("Failed to get Redis connection for instance {}: {}", instance.id, e);
+        AppError::BackendUnavailable(format!("Redis instance unavailable: {}", e)).into()
+    })
+}
 
-    info!("Connecting to Redis instance {} at {}:{}", instance.id, host, port);
+/// Get a connection that is not shared with other requests (for WATCH/MULTI and similar)
+pub(crate) async fn get_dedicated_connection(state: &AppState, instance: &RedisInstance) -> Result<BackendConnection, ErrorResponse> {
+    let endpoint = get_instance_endpoint(state, instance).await?;
+    state.redis_backend.dedicated_connection(&endpoint).await.map_err(|e| {
+        error!("Failed to open dedicated Redis connection for instance {}: {}", instance.id, e);
+        AppError::BackendUnavailable(format!("Redis instance unavailable: {}", e)).into()
+    })
+}
 
-    let client = Client::open(redis_url.as_str()).map_err(|e| {
-        error!("Failed to create Redis client for instance {}: {}", instance.id, e);
+/// Apply the instance's command policy and the API key's scopes to a command
+///
+/// Returns the name to send the command to Redis under, since the policy can rename commands.
+pub(crate) async fn authorize_instance_command(
+    state: &AppState,
+    instance: &RedisInstance,
+    claims: &ApiKeyClaims,
+    command: &str,
+) -> Result<String, ErrorResponse> {
+    check_dev_isolation(instance, command)?;
+
+    let policy = state.command_policies.policy(instance.id).await.map_err(|e| {
+        error!("Failed to load command policy for instance {}: {}", instance.id, e);
         (
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({"error": format!("Failed to connect to Redis: {}", e)})),
+            Json(json!({"error": "Failed to load command policy"})),
         )
     })?;
 
-    let connection = client.get_connection().map_err(|e| {
-        error!("Failed to get Redis connection for instance {}: {}", instance.id, e);
+    let command = policy.resolve(command).map_err(|violation| {
+        warn!("Command policy of instance {} rejected {}: {}", instance.id, command, violation);
         (
-            StatusCode::SERVICE_UNAVAILABLE,
-            Json(json!({"error": format!("Redis instance unavailable: {}", e)})),
+            StatusCode::FORBIDDEN,
+            Json(json!({"error": violation.to_string()})),
         )
     })?;
 
-    info!("Successfully connected to Redis instance {}", instance.id);
-    Ok(connection)
+    authorize_command(claims, &command)?;
+    Ok(command)
 }
 
-/// Try to get Redis connection, return None if failed (for simulation mode fallback)
-async fn try_get_redis_connection(instance: &RedisInstance) -> Option<redis::Connection> {
-    let host = if let Some(domain) = &instance.domain {
-        domain.clone()
-    } else if let Some(service_name) = &instance.service_name {
-        service_name.clone()
-    } else if let Some(public_ip) = &instance.public_ip_address {
-        public_ip.ip().to_string()
-    } else if let Some(private_ip) = &instance.private_ip_address {
-        private_ip.ip().to_string()
-    } else {
-        // If no connection info at all, default to localhost for development
-        warn!("No connection info found for instance {}, defaulting to localhost", instance.id);
-        "localhost".to_string()
-    };
+/// Apply the instance's command policy and the API key's scopes to a registered script
+///
+/// The policy must allow every command the script calls, and the key's scopes must allow
+/// the script's class, i.e. its most privileged command. A read-only key can therefore run
+/// scripts that only read. EVALSHA itself is not checked: raw scripting is denied by
+/// default, and the registry is how scripts are meant to run.
+pub(crate) async fn authorize_script(
+    state: &AppState,
+    instance: &RedisInstance,
+    claims: &ApiKeyClaims,
+    script: &RegisteredScript,
+) -> Result<(), ErrorResponse> {
+    let policy = state.command_policies.policy(instance.id).await.map_err(|e| {
+        error!("Failed to load command policy for instance {}: {}", instance.id, e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({"error": "Failed to load command policy"})),
+        )
+    })?;
 
-    let port = instance.port.unwrap_or(6379);
+    // Commands computed at run time could leave a development instance's database
+    if script.commands.dynamic && RedisEndpoint::is_local_development(instance) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(json!({
+                "error": format!("Script {} calls commands chosen at run time, which development instances do not allow", script.name)
+            })),
+        ));
+    }
 
-    // For development instances (dev-* or localhost* domains), connect to actual localhost
-    let is_dev = host.starts_with("dev-") || host.starts_with("localhost") || host == "127.0.0.1" || host.contains("service");
-    let actual_host = if is_dev {
-        "127.0.0.1".to_string()
-    } else {
-        host.clone()
-    };
+    for command in &script.commands.commands {
+        check_dev_isolation(instance, command)?;
+        // The gateway cannot put a script's channels in the instance's channel namespace
+        if RedisEndpoint::is_local_development(instance) && RedisEndpoint::uses_channels(command) {
+            return Err((
+                StatusCode::FORBIDDEN,
+                Json(json!({
+                    "error": format!("Script {} uses Pub/Sub ({}), which development instances do not allow in scripts", script.name, command)
+                })),
+            ));
+        }
+        policy.permits(command).map_err(|violation| {
+            warn!(
+                "Command policy of instance {} rejected script {}: {}",
+                instance.id, script.name, violation
+            );
+            (
+                StatusCode::FORBIDDEN,
+                Json(json!({"error": format!("Script {} cannot run: {}", script.name, violation)})),
+            )
+        })?;
+    }
 
-    // For development instances, don't use password
-    let redis_url = if is_dev {
-        // Development mode - connect to local Redis without password
-        info!("Using localhost Redis (development mode) for instance {} at {}:{}", instance.id, actual_host, port);
-        format!("redis://{}:{}/", actual_host, port)
-    } else if let Some(password) = &instance.password_hash {
-        // Production mode - use password
-        info!("Using Redis with auth for instance {} at {}:{}", instance.id, actual_host, port);
-        format!("redis://:{}@{}:{}/", password, actual_host, port)
-    } else {
-        info!("Using Redis without auth for instance {} at {}:{}", instance.id, actual_host, port);
-        format!("redis://{}:{}/", actual_host, port)
-    };
+    let class = script.commands.class();
+    if scopes_allow(&claims.scopes, class) {
+        return Ok(());
+    }
 
-    info!("Attempting Redis connection for instance {}", instance.id);
-
-    match Client::open(redis_url.as_str()) {
-        Ok(client) => {
-            match client.get_connection() {
-                Ok(conn) => {
-                    info!("✓ Successfully connected to Redis instance {}", instance.id);
-                    Some(conn)
-                },
-                Err(e) => {
-                    error!("✗ Failed to connect to Redis instance {}: {} - using simulation mode", instance.id, e);
-                    None
-                }
-            }
-        },
-        Err(e) => {
-            error!("✗ Failed to create Redis client for instance {}: {} - using simulation mode", instance.id, e);
-            None
-        }
+    warn!(
+        "API key {} with scopes {:?} denied {} script {}",
+        claims.key_prefix, claims.scopes, class, script.name
+    );
+    Err((
+        StatusCode::FORBIDDEN,
+        Json(json!({
+            "error": format!("API key is not allowed to run script {} ({} script)", script.name, class)
+        })),
+    ))
+}
+
+/// Reject commands that would leave a development instance's database of the shared local Redis
+fn check_dev_isolation(instance: &RedisInstance, command: &str) -> Result<(), ErrorResponse> {
+    if RedisEndpoint::is_local_development(instance) && RedisEndpoint::crosses_databases(command) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(json!({
+                "error": format!("Command {} is not available on development instances", command.to_uppercase())
+            })),
+        ));
+    }
+    Ok(())
+}
+
+/// Whether an instance is a Redis Cluster, whose multi-key requests must stay in one hash slot
+pub(crate) fn is_cluster(instance: &RedisInstance) -> bool {
+    Topology::from_column(&instance.topology) == Topology::Cluster
+}
+
+/// Reject a command whose keys hash to different slots of a cluster instance
+///
+/// Redis would refuse it with CROSSSLOT anyway; checking first keeps it from reaching a node.
+pub(crate) fn check_same_slot(command: &str, args: &[Vec<u8>]) -> Result<(), ErrorResponse> {
+    cluster::check_same_slot(command, args).map_err(|e| {
+        debug!("Rejected {} spanning hash slots", command.to_uppercase());
+        AppError::from(e).into()
+    })
+}
+
+/// Reject commands that the API key's scopes do not allow
+fn authorize_command(claims: &ApiKeyClaims, command: &str) -> Result<(), ErrorResponse> {
+    let class = classify_command(command);
+    if scopes_allow(&claims.scopes, class) {
+        return Ok(());
+    }
+
+    warn!(
+        "API key {} with scopes {:?} denied {} command {}",
+        claims.key_prefix, claims.scopes, class, command
+    );
+    Err((
+        StatusCode::FORBIDDEN,
+        Json(json!({
+            "error": format!("API key is not allowed to run {} ({} command)", command.to_uppercase(), class)
+        })),
+    ))
+}
+
+/// Parse a JSON command array (`["COMMAND", "arg1", ...]`) into the command name and its arguments
+pub(crate) fn parse_command(payload: &[Value]) -> Result<(String, Vec<String>), ErrorResponse> {
+    if payload.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": "Empty command"})),
+        ));
+    }
+
+    let command = payload[0].as_str().ok_or_else(|| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": "Invalid command format"})),
+        )
+    })?;
+
+    let args: Vec<String> = payload[1..].iter().map(value_to_arg).collect();
+
+    Ok((command.to_string(), args))
+}
+
+/// Convert a JSON value to a command argument (strings as-is, anything else as JSON text)
+pub(crate) fn value_to_arg(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Number(n) => n.to_string(),
+        _ => value.to_string(),
+    }
+}
+
+/// Extract the message from an error response, for protocols that report errors as text
+pub(crate) fn error_message((_, Json(body)): ErrorResponse) -> String {
+    body["error"].as_str().unwrap_or("Unknown error").to_string()
+}
+
+/// Error response for a failed Redis command, with the status and `code` of its kind of failure
+pub(crate) fn command_failed(command: &str, e: redis::RedisError) -> ErrorResponse {
+    debug!("Redis {} failed: {}", command, e);
+    AppError::from(e).into()
+}
+
+/// Entry of a pipeline command that failed: the `error` and `code` of its error response
+fn pipeline_error(e: AppError) -> Value {
+    let (_, Json(body)) = ErrorResponse::from(e);
+    json!({"error": body["error"], "code": body["code"]})
+}
+
+/// Error response for a reply that does not have the shape the command documents
+pub(crate) fn unexpected_reply() -> ErrorResponse {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(json!({"error": "Unexpected reply from Redis"})),
+    )
+}
+
+/// How values are represented in command arguments and replies
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueEncoding {
+    /// Plain strings; invalid UTF-8 in replies is replaced with U+FFFD
+    Plain,
+    /// Arguments are base64-decoded before sending and bulk replies are base64-encoded,
+    /// so binary values round-trip exactly
+    Base64,
+}
+
+/// Read the value encoding from the `encoding` query parameter or the `Upstash-Encoding` header
+fn value_encoding(headers: &HeaderMap, query: &HashMap<String, String>) -> Result<ValueEncoding, ErrorResponse> {
+    let requested = query
+        .get("encoding")
+        .map(String::as_str)
+        .or_else(|| headers.get("upstash-encoding").and_then(|v| v.to_str().ok()));
+
+    match requested {
+        None => Ok(ValueEncoding::Plain),
+        Some(encoding) if encoding.eq_ignore_ascii_case("base64") => Ok(ValueEncoding::Base64),
+        Some(encoding) => Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": format!("Unsupported encoding: {}", encoding)})),
+        )),
     }
 }
 
+/// Number of arguments read by the typed branch of `execute_command`, if it has one
+fn typed_arg_count(command: &str) -> Option<usize> {
+    let count = match command {
+        "PING" => 0,
+        "GET" | "DEL" | "INCR" | "DECR" | "EXISTS" | "TTL" | "LPOP" | "RPOP" | "LLEN"
+        | "HGETALL" | "HKEYS" | "HVALS" | "SMEMBERS" | "SCARD" | "STRLEN" => 1,
+        "SET" | "EXPIRE" | "LPUSH" | "RPUSH" | "HGET" | "HDEL" | "HEXISTS" | "SADD" | "SREM"
+        | "SISMEMBER" | "APPEND" => 2,
+        "LRANGE" | "HSET" => 3,
+        _ => return None,
+    };
+    Some(count)
+}
+
+/// Decode base64 command arguments into raw bytes (numbers are sent as their decimal text)
+fn decode_base64_args(args: &[Value]) -> Result<Vec<Vec<u8>>, ErrorResponse> {
+    args.iter()
+        .map(|v| match v {
+            Value::String(s) => BASE64.decode(s).map_err(|_| {
+                (
+                    StatusCode::BAD_REQUEST,
+                    Json(json!({"error": format!("Invalid base64 argument: {}", s)})),
+                )
+            }),
+            Value::Number(n) => Ok(n.to_string().into_bytes()),
+            _ => Ok(v.to_string().into_bytes()),
+        })
+        .collect()
+}
+
 /// Convert Redis value to JSON
-fn redis_value_to_json(value: redis::Value) -> Value {
+pub(crate) fn redis_value_to_json(value: redis::Value) -> Value {
+    encode_redis_value(value, ValueEncoding::Plain)
+}
+
+/// Convert Redis value to JSON using the requested encoding for bulk replies
+fn encode_redis_value(value: redis::Value, encoding: ValueEncoding) -> Value {
     match value {
         redis::Value::Nil => Value::Null,
         redis::Value::Int(i) => Value::Number(serde_json::Number::from(i)),
-        redis::Value::Data(bytes) => {
-            if let Ok(s) = String::from_utf8(bytes) {
-                Value::String(s)
-            } else {
-                Value::Null
-            }
-        }
+        redis::Value::Data(bytes) => match encoding {
+            ValueEncoding::Plain => Value::String(String::from_utf8_lossy(&bytes).into_owned()),
+            ValueEncoding::Base64 => Value::String(BASE64.encode(bytes)),
+        },
         redis::Value::Bulk(values) => {
             let json_values: Vec<Value> = values
                 .into_iter()
-                .map(redis_value_to_json)
+                .map(|value| encode_redis_value(value, encoding))
                 .collect();
             Value::Array(json_values)
         }
@@ -262,32 +522,16 @@ pub async fn handle_ping(
         )
     })?;
 
-    let (instance, _claims) = authenticate_and_get_instance(&state, &api_key, instance_id).await?;
+    let (instance, claims) = authenticate_and_get_instance(&state, &api_key, instance_id).await?;
+    authorize_instance_command(&state, &instance, &claims, "PING").await?;
 
-    // Try to connect to Redis, fallback to simulation mode if fails
-    match try_get_redis_connection(&instance).await {
-        Some(mut conn) => {
-            // Real Redis connection
-            let result: String = redis::cmd("PING").query(&mut conn).map_err(|e| {
-                error!("Redis PING command failed: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": "Redis command failed"})),
-                )
-            })?;
+    let mut conn = get_redis_connection(&state, &instance).await?;
 
-            Ok(Json(RedisResponse {
-                result: Value::String(result),
-            }))
-        },
-        None => {
-            // Connection failed - use simulation mode
-            info!("Using simulation mode for PING on instance {}", instance_id);
-            Ok(Json(RedisResponse {
-                result: Value::String("PONG (simulation mode - Redis not available)".to_string()),
-            }))
-        }
-    }
+    let result: String = redis::cmd("PING").query_async(&mut conn).await.map_err(|e| command_failed("PING", e))?;
+
+    Ok(Json(RedisResponse {
+        result: Value::String(result),
+    }))
 }
 
 /// Handle SET command
@@ -304,43 +548,28 @@ pub async fn handle_set(
         )
     })?;
 
-    let (instance, _claims) = authenticate_and_get_instance(&state, &api_key, instance_id).await?;
+    let (instance, claims) = authenticate_and_get_instance(&state, &api_key, instance_id).await?;
+    authorize_instance_command(&state, &instance, &claims, "SET").await?;
 
-    // Try to connect to Redis, fallback to simulation mode if fails
-    match try_get_redis_connection(&instance).await {
-        Some(mut conn) => {
-            // Real Redis connection
-            let result = if let Some(ex) = query.get("EX") {
-                let expire_seconds: u64 = ex.parse().map_err(|_| {
-                    (
-                        StatusCode::BAD_REQUEST,
-                        Json(json!({"error": "Invalid EX parameter"})),
-                    )
-                })?;
-                conn.set_ex(&key, &value, expire_seconds)
-            } else {
-                conn.set(&key, &value)
-            }
-            .map_err(|e| {
-                error!("Redis SET command failed: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": "Redis command failed"})),
-                )
-            })?;
+    let mut conn = get_redis_connection(&state, &instance).await?;
 
-            Ok(Json(RedisResponse {
-                result: redis_value_to_json(result),
-            }))
-        },
-        None => {
-            // Connection failed - use simulation mode
-            info!("Using simulation mode for SET on instance {}", instance_id);
-            Ok(Json(RedisResponse {
-                result: Value::String("OK (simulation mode)".to_string()),
-            }))
-        }
+    let result = if let Some(ex) = query.get("EX") {
+        let expire_seconds: u64 = ex.parse().map_err(|_| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(json!({"error": "Invalid EX parameter"})),
+            )
+        })?;
+        conn.set_ex(&key, &value, expire_seconds)
+    } else {
+        conn.set(&key, &value)
     }
+    .await
+    .map_err(|e| command_failed("SET", e))?;
+
+    Ok(Json(RedisResponse {
+        result: redis_value_to_json(result),
+    }))
 }
 
 /// Handle GET command
@@ -357,32 +586,16 @@ pub async fn handle_get(
         )
     })?;
 
-    let (instance, _claims) = authenticate_and_get_instance(&state, &api_key, instance_id).await?;
+    let (instance, claims) = authenticate_and_get_instance(&state, &api_key, instance_id).await?;
+    authorize_instance_command(&state, &instance, &claims, "GET").await?;
 
-    // Try to connect to Redis, fallback to simulation mode if fails
-    match try_get_redis_connection(&instance).await {
-        Some(mut conn) => {
-            // Real Redis connection
-            let result: redis::Value = conn.get(&key).map_err(|e| {
-                error!("Redis GET command failed: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": "Redis command failed"})),
-                )
-            })?;
+    let mut conn = get_redis_connection(&state, &instance).await?;
 
-            Ok(Json(RedisResponse {
-                result: redis_value_to_json(result),
-            }))
-        },
-        None => {
-            // Connection failed - use simulation mode
-            info!("Using simulation mode for GET on instance {}", instance_id);
-            Ok(Json(RedisResponse {
-                result: Value::String(format!("mock-value-for-{} (simulation mode)", key)),
-            }))
-        }
-    }
+    let result: redis::Value = conn.get(&key).await.map_err(|e| command_failed("GET", e))?;
+
+    Ok(Json(RedisResponse {
+        result: redis_value_to_json(result),
+    }))
 }
 
 /// Handle DEL command
@@ -399,16 +612,11 @@ pub async fn handle_del(
         )
     })?;
 
-    let (instance, _claims) = authenticate_and_get_instance(&state, &api_key, instance_id).await?;
-    let mut conn = get_redis_connection(&instance).await?;
+    let (instance, claims) = authenticate_and_get_instance(&state, &api_key, instance_id).await?;
+    authorize_instance_command(&state, &instance, &claims, "DEL").await?;
+    let mut conn = get_redis_connection(&state, &instance).await?;
 
-    let result: i32 = conn.del(&key).map_err(|e| {
-        error!("Redis DEL failed: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({"error": "Redis command failed"})),
-        )
-    })?;
+    let result: i32 = conn.del(&key).await.map_err(|e| command_failed("DEL", e))?;
 
     Ok(Json(RedisResponse {
         result: Value::Number(serde_json::Number::from(result)),
@@ -423,6 +631,7 @@ pub async fn handle_generic_command(
     headers: HeaderMap,
     Json(payload): Json<Vec<Value>>,
 ) -> Result<Json<RedisResponse>, ErrorResponse> {
+    let encoding = value_encoding(&headers, &query)?;
     let api_key = extract_api_key(&headers, &Query(query)).ok_or_else(|| {
         (
             StatusCode::UNAUTHORIZED,
@@ -430,44 +639,73 @@ pub async fn handle_generic_command(
         )
     })?;
 
-    let (instance, _claims) = authenticate_and_get_instance(&state, &api_key, instance_id).await?;
-    let mut conn = get_redis_connection(&instance).await?;
+    let (instance, claims) = authenticate_and_get_instance(&state, &api_key, instance_id).await?;
+    execute_command(&state, &instance, &claims, &payload, encoding).await
+}
 
-    if payload.is_empty() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(json!({"error": "Empty command"})),
-        ));
+/// Run one command given as a JSON array, after the instance's policy and the key's scopes allow it
+pub async fn execute_command(
+    state: &AppState,
+    instance: &RedisInstance,
+    claims: &ApiKeyClaims,
+    payload: &[Value],
+    encoding: ValueEncoding,
+) -> Result<Json<RedisResponse>, ErrorResponse> {
+    // Extract command and arguments
+    let (command, mut args) = parse_command(payload)?;
+    let command = authorize_instance_command(state, instance, claims, &command).await?;
+    let command = command.as_str();
+    RedisEndpoint::namespace_channels(instance, command, &mut args);
+
+    if is_cluster(instance) {
+        let raw_args = match encoding {
+            ValueEncoding::Base64 => decode_base64_args(&payload[1..])?,
+            ValueEncoding::Plain => args.iter().map(|arg| arg.as_bytes().to_vec()).collect(),
+        };
+        check_same_slot(command, &raw_args)?;
     }
 
-    // Extract command and arguments
-    let command = payload[0].as_str().ok_or_else(|| {
-        (
-            StatusCode::BAD_REQUEST,
-            Json(json!({"error": "Invalid command format"})),
-        )
-    })?;
+    // Blocking pops would hold up the shared connection, so they get one of their own
+    if blocking::is_blocking(&command.to_uppercase()) {
+        let raw_args = match encoding {
+            ValueEncoding::Base64 => decode_base64_args(&payload[1..])?,
+            ValueEncoding::Plain => args.into_iter().map(String::into_bytes).collect(),
+        };
+        return execute_blocking(state, instance, command, raw_args, encoding).await;
+    }
 
-    let args: Vec<String> = payload[1..]
-        .iter()
-        .map(|v| match v {
-            Value::String(s) => s.clone(),
-            Value::Number(n) => n.to_string(),
-            _ => v.to_string(),
-        })
-        .collect();
+    let mut conn = get_redis_connection(state, instance).await?;
+
+    // Binary-safe mode sends the decoded bytes as-is instead of going through the typed helpers
+    if encoding == ValueEncoding::Base64 {
+        let mut binary_args = decode_base64_args(&payload[1..])?;
+        RedisEndpoint::namespace_channels(instance, command, &mut binary_args);
+        info!("Executing Redis command: {} with {} base64 arguments", command, binary_args.len());
+
+        let mut cmd = redis::cmd(command);
+        for arg in &binary_args {
+            cmd.arg(arg.as_slice());
+        }
+        let result: redis::Value = cmd.query_async(&mut conn).await.map_err(|e| command_failed(command, e))?;
+
+        return Ok(Json(RedisResponse {
+            result: encode_redis_value(result, encoding),
+        }));
+    }
 
     info!("Executing Redis command: {} with args: {:?}", command, args);
 
-    let result = match command.to_uppercase().as_str() {
+    // The typed branches only read a fixed number of arguments, so anything with extra
+    // arguments (SET k v EX 100, DEL k1 k2) goes to the generic branch instead
+    let upper = command.to_uppercase();
+    let dispatch = match typed_arg_count(&upper) {
+        Some(count) if args.len() > count => "",
+        _ => upper.as_str(),
+    };
+
+    let result = match dispatch {
         "PING" => {
-            let result: String = redis::cmd("PING").query(&mut conn).map_err(|e| {
-                error!("Redis PING failed: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": "Redis command failed"})),
-                )
-            })?;
+            let result: String = redis::cmd("PING").query_async(&mut conn).await.map_err(|e| command_failed("PING", e))?;
             redis::Value::Status(result)
         }
         "SET" => {
@@ -477,13 +715,7 @@ pub async fn handle_generic_command(
                     Json(json!({"error": "SET requires key and value"})),
                 ));
             }
-            conn.set(&args[0], &args[1]).map_err(|e| {
-                error!("Redis SET failed: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": "Redis command failed"})),
-                )
-            })?
+            conn.set(&args[0], &args[1]).await.map_err(|e| command_failed("SET", e))?
         }
         "GET" => {
             if args.is_empty() {
@@ -492,13 +724,7 @@ pub async fn handle_generic_command(
                     Json(json!({"error": "GET requires key"})),
                 ));
             }
-            conn.get(&args[0]).map_err(|e| {
-                error!("Redis GET failed: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": "Redis command failed"})),
-                )
-            })?
+            conn.get(&args[0]).await.map_err(|e| command_failed("GET", e))?
         }
         "DEL" => {
             if args.is_empty() {
@@ -507,13 +733,7 @@ pub async fn handle_generic_command(
                     Json(json!({"error": "DEL requires key"})),
                 ));
             }
-            let count: i32 = conn.del(&args[0]).map_err(|e| {
-                error!("Redis DEL failed: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": "Redis command failed"})),
-                )
-            })?;
+            let count: i32 = conn.del(&args[0]).await.map_err(|e| command_failed("DEL", e))?;
             redis::Value::Int(count as i64)
         }
         // String operations
@@ -524,13 +744,7 @@ pub async fn handle_generic_command(
                     Json(json!({"error": "INCR requires key"})),
                 ));
             }
-            let result: i64 = conn.incr(&args[0], 1).map_err(|e| {
-                error!("Redis INCR failed: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": "Redis command failed"})),
-                )
-            })?;
+            let result: i64 = conn.incr(&args[0], 1).await.map_err(|e| command_failed("INCR", e))?;
             redis::Value::Int(result)
         }
         "DECR" => {
@@ -540,13 +754,7 @@ pub async fn handle_generic_command(
                     Json(json!({"error": "DECR requires key"})),
                 ));
             }
-            let result: i64 = conn.decr(&args[0], 1).map_err(|e| {
-                error!("Redis DECR failed: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": "Redis command failed"})),
-                )
-            })?;
+            let result: i64 = conn.decr(&args[0], 1).await.map_err(|e| command_failed("DECR", e))?;
             redis::Value::Int(result)
         }
         "EXISTS" => {
@@ -556,13 +764,7 @@ pub async fn handle_generic_command(
                     Json(json!({"error": "EXISTS requires key"})),
                 ));
             }
-            let result: bool = conn.exists(&args[0]).map_err(|e| {
-                error!("Redis EXISTS failed: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": "Redis command failed"})),
-                )
-            })?;
+            let result: bool = conn.exists(&args[0]).await.map_err(|e| command_failed("EXISTS", e))?;
             redis::Value::Int(if result { 1 } else { 0 })
         }
         "EXPIRE" => {
@@ -578,13 +780,7 @@ pub async fn handle_generic_command(
                     Json(json!({"error": "Invalid expire time"})),
                 )
             })?;
-            let result: bool = conn.expire(&args[0], seconds).map_err(|e| {
-                error!("Redis EXPIRE failed: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": "Redis command failed"})),
-                )
-            })?;
+            let result: bool = conn.expire(&args[0], seconds).await.map_err(|e| command_failed("EXPIRE", e))?;
             redis::Value::Int(if result { 1 } else { 0 })
         }
         "TTL" => {
@@ -594,13 +790,7 @@ pub async fn handle_generic_command(
                     Json(json!({"error": "TTL requires key"})),
                 ));
             }
-            let result: i64 = conn.ttl(&args[0]).map_err(|e| {
-                error!("Redis TTL failed: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": "Redis command failed"})),
-                )
-            })?;
+            let result: i64 = conn.ttl(&args[0]).await.map_err(|e| command_failed("TTL", e))?;
             redis::Value::Int(result)
         }
         // List operations
@@ -611,13 +801,7 @@ pub async fn handle_generic_command(
                     Json(json!({"error": "LPUSH requires key and value"})),
                 ));
             }
-            let result: i32 = conn.lpush(&args[0], &args[1]).map_err(|e| {
-                error!("Redis LPUSH failed: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": "Redis command failed"})),
-                )
-            })?;
+            let result: i32 = conn.lpush(&args[0], &args[1]).await.map_err(|e| command_failed("LPUSH", e))?;
             redis::Value::Int(result as i64)
         }
         "RPUSH" => {
@@ -627,13 +811,7 @@ pub async fn handle_generic_command(
                     Json(json!({"error": "RPUSH requires key and value"})),
                 ));
             }
-            let result: i32 = conn.rpush(&args[0], &args[1]).map_err(|e| {
-                error!("Redis RPUSH failed: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": "Redis command failed"})),
-                )
-            })?;
+            let result: i32 = conn.rpush(&args[0], &args[1]).await.map_err(|e| command_failed("RPUSH", e))?;
             redis::Value::Int(result as i64)
         }
         "LPOP" => {
@@ -643,13 +821,7 @@ pub async fn handle_generic_command(
                     Json(json!({"error": "LPOP requires key"})),
                 ));
             }
-            conn.lpop(&args[0], None).map_err(|e| {
-                error!("Redis LPOP failed: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": "Redis command failed"})),
-                )
-            })?
+            conn.lpop(&args[0], None).await.map_err(|e| command_failed("LPOP", e))?
         }
         "RPOP" => {
             if args.is_empty() {
@@ -658,13 +830,7 @@ pub async fn handle_generic_command(
                     Json(json!({"error": "RPOP requires key"})),
                 ));
             }
-            conn.rpop(&args[0], None).map_err(|e| {
-                error!("Redis RPOP failed: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": "Redis command failed"})),
-                )
-            })?
+            conn.rpop(&args[0], None).await.map_err(|e| command_failed("RPOP", e))?
         }
         "LLEN" => {
             if args.is_empty() {
@@ -673,13 +839,7 @@ pub async fn handle_generic_command(
                     Json(json!({"error": "LLEN requires key"})),
                 ));
             }
-            let result: i32 = conn.llen(&args[0]).map_err(|e| {
-                error!("Redis LLEN failed: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": "Redis command failed"})),
-                )
-            })?;
+            let result: i32 = conn.llen(&args[0]).await.map_err(|e| command_failed("LLEN", e))?;
             redis::Value::Int(result as i64)
         }
         "LRANGE" => {
@@ -701,13 +861,7 @@ pub async fn handle_generic_command(
                     Json(json!({"error": "Invalid stop index"})),
                 )
             })?;
-            conn.lrange(&args[0], start, stop).map_err(|e| {
-                error!("Redis LRANGE failed: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": "Redis command failed"})),
-                )
-            })?
+            conn.lrange(&args[0], start, stop).await.map_err(|e| command_failed("LRANGE", e))?
         }
         // Hash operations
         "HSET" => {
@@ -717,13 +871,7 @@ pub async fn handle_generic_command(
                     Json(json!({"error": "HSET requires key, field, and value"})),
                 ));
             }
-            let result: i32 = conn.hset(&args[0], &args[1], &args[2]).map_err(|e| {
-                error!("Redis HSET failed: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": "Redis command failed"})),
-                )
-            })?;
+            let result: i32 = conn.hset(&args[0], &args[1], &args[2]).await.map_err(|e| command_failed("HSET", e))?;
             redis::Value::Int(result as i64)
         }
         "HGET" => {
@@ -733,13 +881,7 @@ pub async fn handle_generic_command(
                     Json(json!({"error": "HGET requires key and field"})),
                 ));
             }
-            conn.hget(&args[0], &args[1]).map_err(|e| {
-                error!("Redis HGET failed: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": "Redis command failed"})),
-                )
-            })?
+            conn.hget(&args[0], &args[1]).await.map_err(|e| command_failed("HGET", e))?
         }
         "HDEL" => {
             if args.len() < 2 {
@@ -748,13 +890,7 @@ pub async fn handle_generic_command(
                     Json(json!({"error": "HDEL requires key and field"})),
                 ));
             }
-            let result: i32 = conn.hdel(&args[0], &args[1]).map_err(|e| {
-                error!("Redis HDEL failed: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": "Redis command failed"})),
-                )
-            })?;
+            let result: i32 = conn.hdel(&args[0], &args[1]).await.map_err(|e| command_failed("HDEL", e))?;
             redis::Value::Int(result as i64)
         }
         "HEXISTS" => {
@@ -764,13 +900,7 @@ pub async fn handle_generic_command(
                     Json(json!({"error": "HEXISTS requires key and field"})),
                 ));
             }
-            let result: bool = conn.hexists(&args[0], &args[1]).map_err(|e| {
-                error!("Redis HEXISTS failed: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": "Redis command failed"})),
-                )
-            })?;
+            let result: bool = conn.hexists(&args[0], &args[1]).await.map_err(|e| command_failed("HEXISTS", e))?;
             redis::Value::Int(if result { 1 } else { 0 })
         }
         "HGETALL" => {
@@ -780,13 +910,7 @@ pub async fn handle_generic_command(
                     Json(json!({"error": "HGETALL requires key"})),
                 ));
             }
-            conn.hgetall(&args[0]).map_err(|e| {
-                error!("Redis HGETALL failed: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": "Redis command failed"})),
-                )
-            })?
+            conn.hgetall(&args[0]).await.map_err(|e| command_failed("HGETALL", e))?
         }
         "HKEYS" => {
             if args.is_empty() {
@@ -795,13 +919,7 @@ pub async fn handle_generic_command(
                     Json(json!({"error": "HKEYS requires key"})),
                 ));
             }
-            conn.hkeys(&args[0]).map_err(|e| {
-                error!("Redis HKEYS failed: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": "Redis command failed"})),
-                )
-            })?
+            conn.hkeys(&args[0]).await.map_err(|e| command_failed("HKEYS", e))?
         }
         "HVALS" => {
             if args.is_empty() {
@@ -810,13 +928,7 @@ pub async fn handle_generic_command(
                     Json(json!({"error": "HVALS requires key"})),
                 ));
             }
-            conn.hvals(&args[0]).map_err(|e| {
-                error!("Redis HVALS failed: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": "Redis command failed"})),
-                )
-            })?
+            conn.hvals(&args[0]).await.map_err(|e| command_failed("HVALS", e))?
         }
         // Set operations
         "SADD" => {
@@ -826,13 +938,7 @@ pub async fn handle_generic_command(
                     Json(json!({"error": "SADD requires key and member"})),
                 ));
             }
-            let result: i32 = conn.sadd(&args[0], &args[1]).map_err(|e| {
-                error!("Redis SADD failed: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": "Redis command failed"})),
-                )
-            })?;
+            let result: i32 = conn.sadd(&args[0], &args[1]).await.map_err(|e| command_failed("SADD", e))?;
             redis::Value::Int(result as i64)
         }
         "SREM" => {
@@ -842,13 +948,7 @@ pub async fn handle_generic_command(
                     Json(json!({"error": "SREM requires key and member"})),
                 ));
             }
-            let result: i32 = conn.srem(&args[0], &args[1]).map_err(|e| {
-                error!("Redis SREM failed: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": "Redis command failed"})),
-                )
-            })?;
+            let result: i32 = conn.srem(&args[0], &args[1]).await.map_err(|e| command_failed("SREM", e))?;
             redis::Value::Int(result as i64)
         }
         "SISMEMBER" => {
@@ -858,13 +958,7 @@ pub async fn handle_generic_command(
                     Json(json!({"error": "SISMEMBER requires key and member"})),
                 ));
             }
-            let result: bool = conn.sismember(&args[0], &args[1]).map_err(|e| {
-                error!("Redis SISMEMBER failed: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": "Redis command failed"})),
-                )
-            })?;
+            let result: bool = conn.sismember(&args[0], &args[1]).await.map_err(|e| command_failed("SISMEMBER", e))?;
             redis::Value::Int(if result { 1 } else { 0 })
         }
         "SMEMBERS" => {
@@ -874,13 +968,7 @@ pub async fn handle_generic_command(
                     Json(json!({"error": "SMEMBERS requires key"})),
                 ));
             }
-            conn.smembers(&args[0]).map_err(|e| {
-                error!("Redis SMEMBERS failed: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": "Redis command failed"})),
-                )
-            })?
+            conn.smembers(&args[0]).await.map_err(|e| command_failed("SMEMBERS", e))?
         }
         "SCARD" => {
             if args.is_empty() {
@@ -889,13 +977,7 @@ pub async fn handle_generic_command(
                     Json(json!({"error": "SCARD requires key"})),
                 ));
             }
-            let result: i32 = conn.scard(&args[0]).map_err(|e| {
-                error!("Redis SCARD failed: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": "Redis command failed"})),
-                )
-            })?;
+            let result: i32 = conn.scard(&args[0]).await.map_err(|e| command_failed("SCARD", e))?;
             redis::Value::Int(result as i64)
         }
         // Additional string operations
@@ -906,13 +988,7 @@ pub async fn handle_generic_command(
                     Json(json!({"error": "APPEND requires key and value"})),
                 ));
             }
-            let result: i32 = conn.append(&args[0], &args[1]).map_err(|e| {
-                error!("Redis APPEND failed: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": "Redis command failed"})),
-                )
-            })?;
+            let result: i32 = conn.append(&args[0], &args[1]).await.map_err(|e| command_failed("APPEND", e))?;
             redis::Value::Int(result as i64)
         }
         "STRLEN" => {
@@ -922,15 +998,29 @@ pub async fn handle_generic_command(
                     Json(json!({"error": "STRLEN requires key"})),
                 ));
             }
-            let result: i32 = conn.strlen(&args[0]).map_err(|e| {
-                error!("Redis STRLEN failed: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": "Redis command failed"})),
-                )
-            })?;
+            let result: i32 = conn.strlen(&args[0]).await.map_err(|e| command_failed("STRLEN", e))?;
             redis::Value::Int(result as i64)
         }
+        // Sorted sets reply with `{member, score}` objects instead of flat arrays
+        "ZADD" | "ZINCRBY" | "ZRANGE" | "ZRANGEBYSCORE" | "ZRANK" | "ZREM" => {
+            if let Some((required, usage)) = sorted_set::required_args(dispatch) {
+                if args.len() < required {
+                    return Err((
+                        StatusCode::BAD_REQUEST,
+                        Json(json!({"error": format!("{} requires {}", dispatch, usage)})),
+                    ));
+                }
+            }
+            let mut cmd = redis::cmd(command);
+            for arg in &args {
+                cmd.arg(arg);
+            }
+            let result: redis::Value = cmd.query_async(&mut conn).await.map_err(|e| command_failed(dispatch, e))?;
+            if let Some(structured) = sorted_set::structured_reply(dispatch, &args, &result) {
+                return Ok(Json(RedisResponse { result: structured }));
+            }
+            result
+        }
         // Generic command execution using cmd 
         _ => {
             // For any other command, build it dynamically
@@ -938,13 +1028,7 @@ pub async fn handle_generic_command(
             for arg in &args {
                 cmd.arg(arg);
             }
-            cmd.query(&mut conn).map_err(|e| {
-                error!("Redis command {} failed: {}", command, e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": format!("Redis command failed: {}", e)})),
-                )
-            })?
+            cmd.query_async(&mut conn).await.map_err(|e| command_failed(command, e))?
         }
     };
 
@@ -953,27 +1037,387 @@ pub async fn handle_generic_command(
     }))
 }
 
-/// Debug handler to see what requests are coming in
-pub async fn handle_debug_request(
-    State(_state): State<Arc<AppState>>,
-    Path((instance_id, path)): Path<(Uuid, String)>,
+/// Run a blocking list command (BLPOP, BRPOP, BLMOVE, ...) on a dedicated connection
+///
+/// The command's timeout is capped just below the request timeout, and 0 ("forever") becomes
+/// the cap. If the client goes away, the handler future is dropped together with the
+/// connection, and Redis abandons the blocked command once the connection closes.
+async fn execute_blocking(
+    state: &AppState,
+    instance: &RedisInstance,
+    command: &str,
+    mut args: Vec<Vec<u8>>,
+    encoding: ValueEncoding,
+) -> Result<Json<RedisResponse>, ErrorResponse> {
+    let max_block = blocking::max_block(state.request_timeout);
+    let block = blocking::cap_timeout(&command.to_uppercase(), &mut args, max_block)
+        .map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(json!({"error": e.to_string()})),
+            )
+        })?
+        .unwrap_or(max_block);
+
+    let mut conn = get_dedicated_connection(state, instance).await?;
+
+    info!("Executing blocking Redis command: {} for up to {:?}", command, block);
+
+    let mut cmd = redis::cmd(command);
+    for arg in &args {
+        cmd.arg(arg.as_slice());
+    }
+
+    let result = tokio::time::timeout(state.request_timeout, cmd.query_async::<_, redis::Value>(&mut conn))
+        .await
+        .map_err(|_| {
+            error!("Blocking command {} on instance {} did not return in time", command, instance.id);
+            ErrorResponse::from(AppError::Timeout("Redis did not respond in time".to_string()))
+        })?
+        .map_err(|e| command_failed(command, e))?;
+
+    Ok(Json(RedisResponse {
+        result: encode_redis_value(result, encoding),
+    }))
+}
+
+/// Handle a batch of commands sent as a single pipeline (POST /redis/:instance_id/pipeline)
+///
+/// Body is an array of command arrays, e.g. `[["SET","a","1"],["INCR","a"]]`.
+/// Every command gets its own entry in the response, either `{"result": ...}` or
+/// `{"error": "..."}`, so one failing command does not discard the others.
+pub async fn handle_pipeline(
+    State(state): State<Arc<AppState>>,
+    Path(instance_id): Path<Uuid>,
     Query(query): Query<HashMap<String, String>>,
     headers: HeaderMap,
-    method: axum::http::Method,
-) -> Result<Json<Value>, ErrorResponse> {
-    info!("DEBUG: {} request to /redis/{}/{} with query: {:?}", method, instance_id, path, query);
-    
-    // Log authorization header
-    if let Some(auth_header) = headers.get("authorization") {
-        if let Ok(auth_str) = auth_header.to_str() {
-            info!("DEBUG: Authorization header: {}", auth_str);
+    Json(payload): Json<Vec<Vec<Value>>>,
+) -> Result<Json<Vec<Value>>, ErrorResponse> {
+    let api_key = extract_api_key(&headers, &Query(query)).ok_or_else(|| {
+        (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({"error": "Missing API key"})),
+        )
+    })?;
+
+    let (instance, claims) = authenticate_and_get_instance(&state, &api_key, instance_id).await?;
+    execute_pipeline(&state, &instance, &claims, &payload).await
+}
+
+/// Run a batch of commands, each with its own result or error, after all of them are allowed
+pub async fn execute_pipeline(
+    state: &AppState,
+    instance: &RedisInstance,
+    claims: &ApiKeyClaims,
+    payload: &[Vec<Value>],
+) -> Result<Json<Vec<Value>>, ErrorResponse> {
+    if payload.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": "Empty pipeline"})),
+        ));
+    }
+
+    let mut commands = payload
+        .iter()
+        .map(|command| parse_command(command))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Every command must be allowed before any of them is sent
+    for (command, args) in &mut commands {
+        *command = authorize_instance_command(state, instance, claims, command).await?;
+        RedisEndpoint::namespace_channels(instance, command, args);
+    }
+
+    // A blocking pop here would hold up the replies of the rest of the batch
+    if let Some((command, _)) = commands.iter().find(|(command, _)| blocking::is_blocking(&command.to_uppercase())) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": format!("{} is not supported in pipelines, send it on its own", command)})),
+        ));
+    }
+
+    info!("Executing Redis pipeline with {} commands", commands.len());
+
+    if is_cluster(instance) {
+        return execute_cluster_pipeline(state, instance, &commands).await;
+    }
+
+    // The commands go out together as a non-atomic redis::pipe(), in one write and one round
+    // trip. Their replies are then read one at a time, since redis-rs would collapse them
+    // into the first error; that needs a connection no other request reads from.
+    let mut conn = get_dedicated_connection(state, instance).await?;
+    let mut pipe = redis::pipe();
+    for (command, args) in &commands {
+        pipe.cmd(command).arg(args);
+    }
+    conn.req_packed_commands(&pipe, 0, 0)
+        .await
+        .map_err(|e| command_failed("pipeline", e))?;
+
+    let next_reply = redis::pipe();
+    let mut results = Vec::with_capacity(commands.len());
+    for (command, _) in &commands {
+        let result = match conn.req_packed_commands(&next_reply, 0, 1).await {
+            Ok(mut values) => json!({"result": redis_value_to_json(values.pop().unwrap_or(redis::Value::Nil))}),
+            Err(e) if e.is_io_error() || e.is_connection_dropped() => return Err(command_failed("pipeline", e)),
+            Err(e) => {
+                warn!("Redis pipeline command {} failed: {}", command, e);
+                pipeline_error(AppError::from(e))
+            }
+        };
+        results.push(result);
+    }
+
+    Ok(Json(results))
+}
+
+/// Run a pipeline on a cluster instance, where its commands may belong to different nodes
+///
+/// Each command is sent in order to the node owning its slot, and one whose keys span
+/// slots fails on its own.
+async fn execute_cluster_pipeline(
+    state: &AppState,
+    instance: &RedisInstance,
+    commands: &[(String, Vec<String>)],
+) -> Result<Json<Vec<Value>>, ErrorResponse> {
+    let mut conn = get_redis_connection(state, instance).await?;
+
+    let mut results = Vec::with_capacity(commands.len());
+    for (command, args) in commands {
+        let raw_args: Vec<Vec<u8>> = args.iter().map(|arg| arg.as_bytes().to_vec()).collect();
+        if let Err(e) = cluster::check_same_slot(command, &raw_args) {
+            results.push(pipeline_error(AppError::from(e)));
+            continue;
         }
+        let result = match redis::cmd(command).arg(args).query_async::<_, redis::Value>(&mut conn).await {
+            Ok(value) => json!({"result": redis_value_to_json(value)}),
+            Err(e) if e.is_io_error() => return Err(command_failed("pipeline", e)),
+            Err(e) => {
+                warn!("Redis pipeline command {} failed: {}", command, e);
+                pipeline_error(AppError::from(e))
+            }
+        };
+        results.push(result);
     }
-    
-    Err((
-        StatusCode::NOT_IMPLEMENTED,
-        Json(json!({"error": format!("Debug: {} to /redis/{}/{} not implemented", method, instance_id, path)})),
-    ))
+
+    Ok(Json(results))
+}
+
+/// Request body for the MULTI/EXEC endpoint
+///
+/// Accepts either a bare array of commands or an object with optional WATCH keys.
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+pub enum TransactionRequest {
+    Commands(Vec<Vec<Value>>),
+    WithWatch {
+        commands: Vec<Vec<Value>>,
+        #[serde(default)]
+        watch: Vec<String>,
+    },
+}
+
+/// Handle an atomic transaction (POST /redis/:instance_id/multi-exec)
+///
+/// Commands are wrapped in MULTI/EXEC on a single connection. When `watch` keys are
+/// given they are WATCHed first, and the transaction is reported as aborted with
+/// `409 Conflict` if any of them changed before EXEC.
+pub async fn handle_multi_exec(
+    State(state): State<Arc<AppState>>,
+    Path(instance_id): Path<Uuid>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    Json(payload): Json<TransactionRequest>,
+) -> Result<Json<Vec<Value>>, ErrorResponse> {
+    let api_key = extract_api_key(&headers, &Query(query)).ok_or_else(|| {
+        (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({"error": "Missing API key"})),
+        )
+    })?;
+
+    let (instance, claims) = authenticate_and_get_instance(&state, &api_key, instance_id).await?;
+    execute_transaction(&state, &instance, &claims, payload).await
+}
+
+/// Run commands atomically in MULTI/EXEC on a dedicated connection, after all of them are allowed
+pub async fn execute_transaction(
+    state: &AppState,
+    instance: &RedisInstance,
+    claims: &ApiKeyClaims,
+    payload: TransactionRequest,
+) -> Result<Json<Vec<Value>>, ErrorResponse> {
+    let (payload, watch) = match payload {
+        TransactionRequest::Commands(commands) => (commands, Vec::new()),
+        TransactionRequest::WithWatch { commands, watch } => (commands, watch),
+    };
+
+    if payload.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": "Empty transaction"})),
+        ));
+    }
+
+    let mut commands = payload
+        .iter()
+        .map(|command| parse_command(command))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Every command must be allowed before any of them is sent
+    for (command, args) in &mut commands {
+        *command = authorize_instance_command(state, instance, claims, command).await?;
+        RedisEndpoint::namespace_channels(instance, command, args);
+    }
+
+    if let Some((command, _)) = commands.iter().find(|(command, _)| {
+        matches!(
+            command.to_uppercase().as_str(),
+            "MULTI" | "EXEC" | "DISCARD" | "WATCH" | "UNWATCH"
+        )
+    }) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": format!("{} is not allowed inside a transaction", command)})),
+        ));
+    }
+
+    // A cluster runs a transaction on one node, so all its keys must share a slot
+    if is_cluster(instance) {
+        let mut keyed: Vec<(String, Vec<Vec<u8>>)> = commands
+            .iter()
+            .map(|(command, args)| (command.clone(), args.iter().map(|arg| arg.as_bytes().to_vec()).collect()))
+            .collect();
+        keyed.push(("WATCH".to_string(), watch.iter().map(|key| key.as_bytes().to_vec()).collect()));
+        let groups = cluster::split_by_slot(&keyed).map_err(|e| ErrorResponse::from(AppError::from(e)))?;
+        if groups.len() > 1 {
+            return Err(AppError::from(ClusterError::CrossSlot).into());
+        }
+    }
+
+    // WATCH and MULTI are connection state, so the transaction needs its own connection
+    let mut conn = get_dedicated_connection(state, instance).await?;
+
+    if !watch.is_empty() {
+        redis::cmd("WATCH")
+            .arg(&watch)
+            .query_async::<_, ()>(&mut conn)
+            .await
+            .map_err(|e| command_failed("WATCH", e))?;
+    }
+
+    info!(
+        "Executing Redis transaction with {} commands ({} watched keys)",
+        commands.len(),
+        watch.len()
+    );
+
+    // Queue the commands before EXEC, so that one Redis rejects while queueing (an unknown
+    // command, a wrong number of arguments) is known to have aborted the whole transaction
+    let mut queue = redis::pipe();
+    queue.cmd("MULTI").ignore();
+    for (command, args) in &commands {
+        queue.cmd(command).arg(args).ignore();
+    }
+    queue
+        .query_async::<_, ()>(&mut conn)
+        .await
+        .map_err(|e| command_failed("MULTI", e))?;
+
+    match redis::cmd("EXEC").query_async::<_, Option<Vec<redis::Value>>>(&mut conn).await {
+        Ok(None) => {
+            warn!("Redis transaction aborted: watched key modified");
+            let (status, Json(mut body)) = ErrorResponse::from(AppError::Conflict(
+                "Transaction aborted: a watched key was modified".to_string(),
+            ));
+            body["aborted"] = json!(true);
+            Err((status, Json(body)))
+        }
+        Ok(Some(values)) => Ok(Json(
+            values
+                .into_iter()
+                .map(|value| json!({"result": redis_value_to_json(value)}))
+                .collect(),
+        )),
+        // A command failed while running. Redis still ran and committed the others, but
+        // redis-rs only reports the first error of the EXEC reply
+        Err(e) if !e.is_io_error() => {
+            warn!("Redis transaction partially executed: {}", redis_error_message(&e));
+            let (status, Json(mut body)) = command_failed("EXEC", e);
+            body["partial"] = json!(true);
+            Err((status, Json(body)))
+        }
+        Err(e) => Err(command_failed("EXEC", e)),
+    }
+}
+
+/// Query parameters that configure the request rather than being command options
+const RESERVED_QUERY_PARAMS: &[&str] = &["_token", "encoding"];
+
+/// Handle an Upstash-style command in the URL path (GET or POST /redis/:instance_id/*path)
+///
+/// `/set/foo/bar/EX/100` runs `SET foo bar EX 100`. A POST body is appended after the
+/// path arguments, and query parameters other than `_token` and `encoding` are appended
+/// as options (`?EX=100`, or `?NX` for a flag).
+pub async fn handle_path_command(
+    State(state): State<Arc<AppState>>,
+    Path(params): Path<HashMap<String, String>>,
+    Query(query): Query<Vec<(String, String)>>,
+    headers: HeaderMap,
+    uri: Uri,
+    body: Bytes,
+) -> Result<Json<RedisResponse>, ErrorResponse> {
+    let instance_id = params
+        .get("instance_id")
+        .and_then(|id| Uuid::parse_str(id).ok())
+        .ok_or_else(|| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(json!({"error": "Invalid instance id"})),
+            )
+        })?;
+
+    let (reserved, options): (Vec<_>, Vec<_>) = query
+        .into_iter()
+        .partition(|(name, _)| RESERVED_QUERY_PARAMS.contains(&name.as_str()));
+    let reserved: HashMap<String, String> = reserved.into_iter().collect();
+
+    let encoding = value_encoding(&headers, &reserved)?;
+    let api_key = extract_api_key(&headers, &Query(reserved)).ok_or_else(|| {
+        (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({"error": "Missing API key"})),
+        )
+    })?;
+
+    // Segments are split from the raw path so that escaped slashes stay inside their argument
+    let command_path = uri.path().splitn(4, '/').nth(3).unwrap_or("");
+    let args = parse_path_command(command_path, &options, &body).map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": e.to_string()})),
+        )
+    })?;
+
+    // In base64 mode, arguments (but not the command name) are re-encoded so that binary
+    // values are sent unchanged
+    let payload = args
+        .into_iter()
+        .enumerate()
+        .map(|(i, arg)| match encoding {
+            ValueEncoding::Base64 if i > 0 => Ok(Value::String(BASE64.encode(arg))),
+            _ => String::from_utf8(arg).map(Value::String).map_err(|_| {
+                (
+                    StatusCode::BAD_REQUEST,
+                    Json(json!({"error": "Arguments must be valid UTF-8, use base64 encoding for binary values"})),
+                )
+            }),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let (instance, claims) = authenticate_and_get_instance(&state, &api_key, instance_id).await?;
+    execute_command(&state, &instance, &claims, &payload, encoding).await
 }
 
 /// Handle INCR command via GET route
@@ -990,29 +1434,16 @@ pub async fn handle_incr(
         )
     })?;
 
-    let (instance, _claims) = authenticate_and_get_instance(&state, &api_key, instance_id).await?;
-    match try_get_redis_connection(&instance).await {
-            Some(mut conn) => {
-                let result: i64 = conn.incr(&key, 1).map_err(|e| {
-                    error!("Redis INCR failed: {}", e);
-                    (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(json!({"error": "Redis command failed"})),
-                    )
-                })?;
-
-                Ok(Json(RedisResponse {
-                    result: Value::Number(serde_json::Number::from(result)),
-                }))
-            },
-            None => {
-                info!("Using simulation mode for INCR on instance {}", instance_id);
-                Ok(Json(RedisResponse {
-                    result: Value::Number(serde_json::Number::from(1)),
-                }))
-            }
-        }
-    }
+    let (instance, claims) = authenticate_and_get_instance(&state, &api_key, instance_id).await?;
+    authorize_instance_command(&state, &instance, &claims, "INCR").await?;
+    let mut conn = get_redis_connection(&state, &instance).await?;
+
+    let result: i64 = conn.incr(&key, 1).await.map_err(|e| command_failed("INCR", e))?;
+
+    Ok(Json(RedisResponse {
+        result: Value::Number(serde_json::Number::from(result)),
+    }))
+}
 
 /// Handle HSET command via GET route
 pub async fn handle_hset(
@@ -1028,29 +1459,16 @@ pub async fn handle_hset(
         )
     })?;
 
-    let (instance, _claims) = authenticate_and_get_instance(&state, &api_key, instance_id).await?;
-    match try_get_redis_connection(&instance).await {
-            Some(mut conn) => {
-                let result: i32 = conn.hset(&key, &field, &value).map_err(|e| {
-                    error!("Redis HSET failed: {}", e);
-                    (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(json!({"error": "Redis command failed"})),
-                    )
-                })?;
-
-                Ok(Json(RedisResponse {
-                    result: Value::Number(serde_json::Number::from(result)),
-                }))
-            },
-            None => {
-                info!("Using simulation mode for HSET on instance {}", instance_id);
-                Ok(Json(RedisResponse {
-                    result: Value::Number(serde_json::Number::from(1)),
-                }))
-            }
-        }
-    }
+    let (instance, claims) = authenticate_and_get_instance(&state, &api_key, instance_id).await?;
+    authorize_instance_command(&state, &instance, &claims, "HSET").await?;
+    let mut conn = get_redis_connection(&state, &instance).await?;
+
+    let result: i32 = conn.hset(&key, &field, &value).await.map_err(|e| command_failed("HSET", e))?;
+
+    Ok(Json(RedisResponse {
+        result: Value::Number(serde_json::Number::from(result)),
+    }))
+}
 
 /// Handle HGET command via GET route
 pub async fn handle_hget(
@@ -1066,29 +1484,16 @@ pub async fn handle_hget(
         )
     })?;
 
-    let (instance, _claims) = authenticate_and_get_instance(&state, &api_key, instance_id).await?;
-    match try_get_redis_connection(&instance).await {
-            Some(mut conn) => {
-                let result: redis::Value = conn.hget(&key, &field).map_err(|e| {
-                    error!("Redis HGET failed: {}", e);
-                    (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(json!({"error": "Redis command failed"})),
-                    )
-                })?;
-
-                Ok(Json(RedisResponse {
-                    result: redis_value_to_json(result),
-                }))
-            },
-            None => {
-                info!("Using simulation mode for HGET on instance {}", instance_id);
-                Ok(Json(RedisResponse {
-                    result: Value::String(format!("mock-{}-{} (simulation mode)", field, key)),
-                }))
-            }
-        }
-    }
+    let (instance, claims) = authenticate_and_get_instance(&state, &api_key, instance_id).await?;
+    authorize_instance_command(&state, &instance, &claims, "HGET").await?;
+    let mut conn = get_redis_connection(&state, &instance).await?;
+
+    let result: redis::Value = conn.hget(&key, &field).await.map_err(|e| command_failed("HGET", e))?;
+
+    Ok(Json(RedisResponse {
+        result: redis_value_to_json(result),
+    }))
+}
 
 /// Handle LPUSH command via GET route  
 pub async fn handle_lpush(
@@ -1104,31 +1509,16 @@ pub async fn handle_lpush(
         )
     })?;
 
-    let (instance, _claims) = authenticate_and_get_instance(&state, &api_key, instance_id).await?;
+    let (instance, claims) = authenticate_and_get_instance(&state, &api_key, instance_id).await?;
+    authorize_instance_command(&state, &instance, &claims, "LPUSH").await?;
 
-    match try_get_redis_connection(&instance).await {
-        Some(mut conn) => {
-            // Real Redis connection
-            let result: i32 = conn.lpush(&key, &value).map_err(|e| {
-                error!("Redis LPUSH failed: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": "Redis command failed"})),
-                )
-            })?;
+    let mut conn = get_redis_connection(&state, &instance).await?;
 
-            Ok(Json(RedisResponse {
-                result: Value::Number(serde_json::Number::from(result)),
-            }))
-        },
-        None => {
-            // Connection failed - use simulation mode
-            info!("Using simulation mode for LPUSH on instance {}", instance_id);
-            Ok(Json(RedisResponse {
-                result: Value::Number(serde_json::Number::from(1)), // Mocked response
-            }))
-        }
-    }
+    let result: i32 = conn.lpush(&key, &value).await.map_err(|e| command_failed("LPUSH", e))?;
+
+    Ok(Json(RedisResponse {
+        result: Value::Number(serde_json::Number::from(result)),
+    }))
 }
 
 /// Handle LPOP command via GET route
@@ -1145,29 +1535,16 @@ pub async fn handle_lpop(
         )
     })?;
 
-    let (instance, _claims) = authenticate_and_get_instance(&state, &api_key, instance_id).await?;
+    let (instance, claims) = authenticate_and_get_instance(&state, &api_key, instance_id).await?;
+    authorize_instance_command(&state, &instance, &claims, "LPOP").await?;
 
-    match try_get_redis_connection(&instance).await {
-        Some(mut conn) => {
-            let result: redis::Value = conn.lpop(&key, None).map_err(|e| {
-                error!("Redis LPOP failed: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": "Redis command failed"})),
-                )
-            })?;
+    let mut conn = get_redis_connection(&state, &instance).await?;
 
-            Ok(Json(RedisResponse {
-                result: redis_value_to_json(result),
-            }))
-        },
-        None => {
-            info!("Using simulation mode for LPOP on instance {}", instance_id);
-            Ok(Json(RedisResponse {
-                result: Value::String(format!("mock-value-for-{} (simulation mode)", key)),
-            }))
-        }
-    }
+    let result: redis::Value = conn.lpop(&key, None).await.map_err(|e| command_failed("LPOP", e))?;
+
+    Ok(Json(RedisResponse {
+        result: redis_value_to_json(result),
+    }))
 }
 
 /// Handle EXPIRE command - Set key expiration time in seconds
@@ -1184,33 +1561,21 @@ pub async fn handle_expire(
         )
     })?;
 
-    let (instance, _claims) = authenticate_and_get_instance(&state, &api_key, instance_id).await?;
-
-    match try_get_redis_connection(&instance).await {
-        Some(mut conn) => {
-            let result: i32 = redis::cmd("EXPIRE")
-                .arg(&key)
-                .arg(seconds)
-                .query(&mut conn)
-                .map_err(|e| {
-                    error!("Redis EXPIRE failed: {}", e);
-                    (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(json!({"error": "Redis command failed"})),
-                    )
-                })?;
-
-            Ok(Json(RedisResponse {
-                result: Value::Number(serde_json::Number::from(result)),
-            }))
-        },
-        None => {
-            info!("Using simulation mode for EXPIRE on instance {}", instance_id);
-            Ok(Json(RedisResponse {
-                result: Value::Number(serde_json::Number::from(1)),
-            }))
-        }
-    }
+    let (instance, claims) = authenticate_and_get_instance(&state, &api_key, instance_id).await?;
+    authorize_instance_command(&state, &instance, &claims, "EXPIRE").await?;
+
+    let mut conn = get_redis_connection(&state, &instance).await?;
+
+    let result: i32 = redis::cmd("EXPIRE")
+        .arg(&key)
+        .arg(seconds)
+        .query_async(&mut conn)
+        .await
+        .map_err(|e| command_failed("EXPIRE", e))?;
+
+    Ok(Json(RedisResponse {
+        result: Value::Number(serde_json::Number::from(result)),
+    }))
 }
 
 /// Handle TTL command - Get time to live for key
@@ -1227,32 +1592,20 @@ pub async fn handle_ttl(
         )
     })?;
 
-    let (instance, _claims) = authenticate_and_get_instance(&state, &api_key, instance_id).await?;
-
-    match try_get_redis_connection(&instance).await {
-        Some(mut conn) => {
-            let result: i64 = redis::cmd("TTL")
-                .arg(&key)
-                .query(&mut conn)
-                .map_err(|e| {
-                    error!("Redis TTL failed: {}", e);
-                    (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(json!({"error": "Redis command failed"})),
-                    )
-                })?;
-
-            Ok(Json(RedisResponse {
-                result: Value::Number(serde_json::Number::from(result)),
-            }))
-        },
-        None => {
-            info!("Using simulation mode for TTL on instance {}", instance_id);
-            Ok(Json(RedisResponse {
-                result: Value::Number(serde_json::Number::from(-1)), // -1 means no expiration
-            }))
-        }
-    }
+    let (instance, claims) = authenticate_and_get_instance(&state, &api_key, instance_id).await?;
+    authorize_instance_command(&state, &instance, &claims, "TTL").await?;
+
+    let mut conn = get_redis_connection(&state, &instance).await?;
+
+    let result: i64 = redis::cmd("TTL")
+        .arg(&key)
+        .query_async(&mut conn)
+        .await
+        .map_err(|e| command_failed("TTL", e))?;
+
+    Ok(Json(RedisResponse {
+        result: Value::Number(serde_json::Number::from(result)),
+    }))
 }
 
 /// Handle EXISTS command - Check if key exists
@@ -1269,32 +1622,20 @@ pub async fn handle_exists(
         )
     })?;
 
-    let (instance, _claims) = authenticate_and_get_instance(&state, &api_key, instance_id).await?;
-
-    match try_get_redis_connection(&instance).await {
-        Some(mut conn) => {
-            let result: i32 = redis::cmd("EXISTS")
-                .arg(&key)
-                .query(&mut conn)
-                .map_err(|e| {
-                    error!("Redis EXISTS failed: {}", e);
-                    (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(json!({"error": "Redis command failed"})),
-                    )
-                })?;
-
-            Ok(Json(RedisResponse {
-                result: Value::Number(serde_json::Number::from(result)),
-            }))
-        },
-        None => {
-            info!("Using simulation mode for EXISTS on instance {}", instance_id);
-            Ok(Json(RedisResponse {
-                result: Value::Number(serde_json::Number::from(0)),
-            }))
-        }
-    }
+    let (instance, claims) = authenticate_and_get_instance(&state, &api_key, instance_id).await?;
+    authorize_instance_command(&state, &instance, &claims, "EXISTS").await?;
+
+    let mut conn = get_redis_connection(&state, &instance).await?;
+
+    let result: i32 = redis::cmd("EXISTS")
+        .arg(&key)
+        .query_async(&mut conn)
+        .await
+        .map_err(|e| command_failed("EXISTS", e))?;
+
+    Ok(Json(RedisResponse {
+        result: Value::Number(serde_json::Number::from(result)),
+    }))
 }
 
 /// Handle DECR command - Decrement integer value
@@ -1311,32 +1652,20 @@ pub async fn handle_decr(
         )
     })?;
 
-    let (instance, _claims) = authenticate_and_get_instance(&state, &api_key, instance_id).await?;
-
-    match try_get_redis_connection(&instance).await {
-        Some(mut conn) => {
-            let result: i64 = redis::cmd("DECR")
-                .arg(&key)
-                .query(&mut conn)
-                .map_err(|e| {
-                    error!("Redis DECR failed: {}", e);
-                    (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(json!({"error": "Redis command failed"})),
-                    )
-                })?;
-
-            Ok(Json(RedisResponse {
-                result: Value::Number(serde_json::Number::from(result)),
-            }))
-        },
-        None => {
-            info!("Using simulation mode for DECR on instance {}", instance_id);
-            Ok(Json(RedisResponse {
-                result: Value::Number(serde_json::Number::from(-1)),
-            }))
-        }
-    }
+    let (instance, claims) = authenticate_and_get_instance(&state, &api_key, instance_id).await?;
+    authorize_instance_command(&state, &instance, &claims, "DECR").await?;
+
+    let mut conn = get_redis_connection(&state, &instance).await?;
+
+    let result: i64 = redis::cmd("DECR")
+        .arg(&key)
+        .query_async(&mut conn)
+        .await
+        .map_err(|e| command_failed("DECR", e))?;
+
+    Ok(Json(RedisResponse {
+        result: Value::Number(serde_json::Number::from(result)),
+    }))
 }
 
 /// Handle SADD command - Add member to set
@@ -1353,33 +1682,21 @@ pub async fn handle_sadd(
         )
     })?;
 
-    let (instance, _claims) = authenticate_and_get_instance(&state, &api_key, instance_id).await?;
-
-    match try_get_redis_connection(&instance).await {
-        Some(mut conn) => {
-            let result: i32 = redis::cmd("SADD")
-                .arg(&key)
-                .arg(&member)
-                .query(&mut conn)
-                .map_err(|e| {
-                    error!("Redis SADD failed: {}", e);
-                    (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(json!({"error": "Redis command failed"})),
-                    )
-                })?;
-
-            Ok(Json(RedisResponse {
-                result: Value::Number(serde_json::Number::from(result)),
-            }))
-        },
-        None => {
-            info!("Using simulation mode for SADD on instance {}", instance_id);
-            Ok(Json(RedisResponse {
-                result: Value::Number(serde_json::Number::from(1)),
-            }))
-        }
-    }
+    let (instance, claims) = authenticate_and_get_instance(&state, &api_key, instance_id).await?;
+    authorize_instance_command(&state, &instance, &claims, "SADD").await?;
+
+    let mut conn = get_redis_connection(&state, &instance).await?;
+
+    let result: i32 = redis::cmd("SADD")
+        .arg(&key)
+        .arg(&member)
+        .query_async(&mut conn)
+        .await
+        .map_err(|e| command_failed("SADD", e))?;
+
+    Ok(Json(RedisResponse {
+        result: Value::Number(serde_json::Number::from(result)),
+    }))
 }
 
 /// Handle SMEMBERS command - Get all members of set
@@ -1396,32 +1713,20 @@ pub async fn handle_smembers(
         )
     })?;
 
-    let (instance, _claims) = authenticate_and_get_instance(&state, &api_key, instance_id).await?;
-
-    match try_get_redis_connection(&instance).await {
-        Some(mut conn) => {
-            let result: redis::Value = redis::cmd("SMEMBERS")
-                .arg(&key)
-                .query(&mut conn)
-                .map_err(|e| {
-                    error!("Redis SMEMBERS failed: {}", e);
-                    (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(json!({"error": "Redis command failed"})),
-                    )
-                })?;
-
-            Ok(Json(RedisResponse {
-                result: redis_value_to_json(result),
-            }))
-        },
-        None => {
-            info!("Using simulation mode for SMEMBERS on instance {}", instance_id);
-            Ok(Json(RedisResponse {
-                result: Value::Array(vec![]),
-            }))
-        }
-    }
+    let (instance, claims) = authenticate_and_get_instance(&state, &api_key, instance_id).await?;
+    authorize_instance_command(&state, &instance, &claims, "SMEMBERS").await?;
+
+    let mut conn = get_redis_connection(&state, &instance).await?;
+
+    let result: redis::Value = redis::cmd("SMEMBERS")
+        .arg(&key)
+        .query_async(&mut conn)
+        .await
+        .map_err(|e| command_failed("SMEMBERS", e))?;
+
+    Ok(Json(RedisResponse {
+        result: redis_value_to_json(result),
+    }))
 }
 
 /// Handle SISMEMBER command - Check if member exists in set
@@ -1438,33 +1743,21 @@ pub async fn handle_sismember(
         )
     })?;
 
-    let (instance, _claims) = authenticate_and_get_instance(&state, &api_key, instance_id).await?;
-
-    match try_get_redis_connection(&instance).await {
-        Some(mut conn) => {
-            let result: i32 = redis::cmd("SISMEMBER")
-                .arg(&key)
-                .arg(&member)
-                .query(&mut conn)
-                .map_err(|e| {
-                    error!("Redis SISMEMBER failed: {}", e);
-                    (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(json!({"error": "Redis command failed"})),
-                    )
-                })?;
-
-            Ok(Json(RedisResponse {
-                result: Value::Number(serde_json::Number::from(result)),
-            }))
-        },
-        None => {
-            info!("Using simulation mode for SISMEMBER on instance {}", instance_id);
-            Ok(Json(RedisResponse {
-                result: Value::Number(serde_json::Number::from(0)),
-            }))
-        }
-    }
+    let (instance, claims) = authenticate_and_get_instance(&state, &api_key, instance_id).await?;
+    authorize_instance_command(&state, &instance, &claims, "SISMEMBER").await?;
+
+    let mut conn = get_redis_connection(&state, &instance).await?;
+
+    let result: i32 = redis::cmd("SISMEMBER")
+        .arg(&key)
+        .arg(&member)
+        .query_async(&mut conn)
+        .await
+        .map_err(|e| command_failed("SISMEMBER", e))?;
+
+    Ok(Json(RedisResponse {
+        result: Value::Number(serde_json::Number::from(result)),
+    }))
 }
 
 /// Handle SREM command - Remove member from set
@@ -1481,31 +1774,19 @@ pub async fn handle_srem(
         )
     })?;
 
-    let (instance, _claims) = authenticate_and_get_instance(&state, &api_key, instance_id).await?;
-
-    match try_get_redis_connection(&instance).await {
-        Some(mut conn) => {
-            let result: i32 = redis::cmd("SREM")
-                .arg(&key)
-                .arg(&member)
-                .query(&mut conn)
-                .map_err(|e| {
-                    error!("Redis SREM failed: {}", e);
-                    (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(json!({"error": "Redis command failed"})),
-                    )
-                })?;
-
-            Ok(Json(RedisResponse {
-                result: Value::Number(serde_json::Number::from(result)),
-            }))
-        },
-        None => {
-            info!("Using simulation mode for SREM on instance {}", instance_id);
-            Ok(Json(RedisResponse {
-                result: Value::Number(serde_json::Number::from(1)),
-            }))
-        }
-    }
+    let (instance, claims) = authenticate_and_get_instance(&state, &api_key, instance_id).await?;
+    authorize_instance_command(&state, &instance, &claims, "SREM").await?;
+
+    let mut conn = get_redis_connection(&state, &instance).await?;
+
+    let result: i32 = redis::cmd("SREM")
+        .arg(&key)
+        .arg(&member)
+        .query_async(&mut conn)
+        .await
+        .map_err(|e| command_failed("SREM", e))?;
+
+    Ok(Json(RedisResponse {
+        result: Value::Number(serde_json::Number::from(result)),
+    }))
 }
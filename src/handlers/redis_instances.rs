@@ -2,24 +2,35 @@
 
 use axum::{
     extract::{Extension, Path, Query, State},
-    http::StatusCode,
+    http::{
+        header::{HeaderName, CACHE_CONTROL, USER_AGENT},
+        HeaderMap, StatusCode,
+    },
     response::Json,
 };
 use chrono::Utc;
+use serde_json::{json, Value};
 use sqlx::{Row, types::BigDecimal};
 use std::sync::Arc;
 use uuid::Uuid;
 use validator::Validate;
-use tracing::{info, warn, error, debug, instrument};
+use tracing::{info, warn, error};
 
 use crate::api_models::{
-    ApiResponse, CreateRedisInstanceRequest, PaginatedResponse, PaginationParams,
-    RedisInstanceResponse,
+    ApiResponse, CommandPolicyResponse, CreateRedisInstanceRequest, PaginatedResponse,
+    PaginationParams, RedisCredentialsResponse, RedisInstanceResponse, RotatePasswordResponse,
+    ScriptResponse, UpdateCommandPolicyRequest, UpdateScriptRequest, UpdateSentinelRequest,
 };
-use crate::auth::hash_password;
 use crate::k8s_service::K8sRedisService;
 use crate::middleware::{AppState, CurrentUser};
 use crate::models::RedisInstance;
+use crate::services::audit::{self, AuditEvent};
+use crate::services::cluster::Topology;
+use crate::services::command_policy::{CommandPolicy, PolicyError};
+use crate::services::redis_backend::BackendConnection;
+use crate::services::redis_pool::{RedisEndpoint, DEVELOPMENT_MODE, KUBERNETES_MODE};
+use crate::services::scripts::{RegisteredScript, ScriptError};
+use crate::services::sentinel::{self, SentinelConfig};
 
 type ErrorResponse = (StatusCode, Json<ApiResponse<()>>);
 
@@ -36,6 +47,10 @@ fn redis_instance_to_response(redis_instance: RedisInstance) -> RedisInstanceRes
         max_memory: redis_instance.max_memory.unwrap_or(0),
         current_memory: redis_instance.current_memory.unwrap_or(0),
         redis_version: redis_instance.redis_version.unwrap_or_else(|| "7.0".to_string()),
+        topology: redis_instance.topology,
+        sentinel_master_name: redis_instance.sentinel_master_name,
+        sentinel_endpoints: redis_instance.sentinel_endpoints,
+        current_master: None,
         namespace: redis_instance.namespace.unwrap_or_else(|| "default".to_string()),
         status: redis_instance.status.unwrap_or_else(|| "unknown".to_string()),
         health_status: redis_instance.health_status.unwrap_or_else(|| "unknown".to_string()),
@@ -50,8 +65,8 @@ fn redis_instance_to_response(redis_instance: RedisInstance) -> RedisInstanceRes
         persistence_enabled: redis_instance.persistence_enabled.unwrap_or(false),
         backup_enabled: redis_instance.backup_enabled.unwrap_or(false),
         last_backup_at: redis_instance.last_backup_at,
-        created_at: redis_instance.created_at.unwrap_or_else(|| Utc::now()),
-        updated_at: redis_instance.updated_at.unwrap_or_else(|| Utc::now()),
+        created_at: redis_instance.created_at.unwrap_or_else(Utc::now),
+        updated_at: redis_instance.updated_at.unwrap_or_else(Utc::now),
     }
 }
 
@@ -155,18 +170,13 @@ pub async fn create_redis_instance(
     let _instance_id = Uuid::new_v4();
     let now = Utc::now();
     
-    // Generate Redis password and hash it
+    // Generate Redis password (stored encrypted once the instance row exists)
     let redis_password = generate_redis_password();
-    let redis_password_hash = hash_password(&redis_password).map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiResponse::<()>::error(format!("Password hashing error: {}", e))),
-        )
-    })?;
 
     // Create Redis instance
     let instance_id = Uuid::new_v4();
     let redis_version = payload.redis_version.unwrap_or_else(|| "7.2".to_string());
+    let topology = payload.topology.unwrap_or_default();
     let persistence_enabled = payload.persistence_enabled.unwrap_or(true);
     let backup_enabled = payload.backup_enabled.unwrap_or(false);
     let namespace = format!("redis-{}", payload.organization_id.simple());
@@ -175,9 +185,6 @@ pub async fn create_redis_instance(
     // For production K8s, each instance gets its own port
     let port = 6379;
 
-    // Use slug as domain to ensure uniqueness in development mode
-    let domain = format!("localhost-{}", payload.slug);
-
     // Try to deploy to Kubernetes if available (silent fallback to simulation mode)
     let k8s_deployment_result = match crate::k8s_service::K8sRedisService::new().await {
         Ok(k8s_service) => {
@@ -191,6 +198,7 @@ pub async fn create_redis_instance(
                 max_memory: payload.max_memory,
                 redis_password: redis_password.clone(),
                 port,
+                topology,
             };
             
             match k8s_service.create_redis_instance(config).await {
@@ -211,8 +219,18 @@ pub async fn create_redis_instance(
         }
     };
 
+    // The shared local Redis is standalone, so a cluster needs a Kubernetes deployment
+    if topology == Topology::Cluster && k8s_deployment_result.is_none() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::<()>::error(
+                "Cluster instances require Kubernetes; development instances are standalone".to_string(),
+            )),
+        ));
+    }
+
     // Use deployment result or mock data for database record
-    let (actual_port, actual_domain, actual_namespace, deployment_name, service_name, status) = 
+    let (actual_port, actual_domain, actual_namespace, deployment_name, service_name, status, deployment_mode) =
         if let Some(ref result) = k8s_deployment_result {
             (
                 result.port,
@@ -220,7 +238,8 @@ pub async fn create_redis_instance(
                 result.namespace.clone(),
                 result.deployment_name.clone(),
                 result.service_name.clone(),
-                "pending" // K8s deployment is pending
+                "pending", // K8s deployment is pending
+                KUBERNETES_MODE,
             )
         } else {
             // Development mode - use localhost Redis with NULL domain (avoids unique constraint)
@@ -232,20 +251,29 @@ pub async fn create_redis_instance(
                 namespace.clone(),
                 format!("redis-{}", payload.slug),
                 format!("redis-{}-service", payload.slug),
-                "development" // Development mode with local Redis
+                "development", // Development mode with local Redis
+                DEVELOPMENT_MODE,
             )
         };
 
-    sqlx::query(
+    // Instance row and its encrypted password are written together
+    let mut tx = state.db_pool.begin().await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::<()>::error(format!("Database error: {}", e))),
+        )
+    })?;
+
+    let insert_result = sqlx::query(
         r#"
         INSERT INTO redis_instances (
             id, name, slug, organization_id, port, domain,
-            max_memory, current_memory, password_hash, redis_version, namespace,
+            max_memory, current_memory, redis_version, namespace,
             pod_name, service_name, status, health_status, cpu_usage_percent, memory_usage_percent,
             connections_count, max_connections, persistence_enabled, backup_enabled,
-            created_at, updated_at
+            created_at, updated_at, topology, deployment_mode
         )
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24)
         "#,
     )
     .bind(instance_id)
@@ -256,7 +284,6 @@ pub async fn create_redis_instance(
     .bind(&actual_domain)
     .bind(payload.max_memory)
     .bind(0i64) // current_memory starts at 0
-    .bind(&redis_password_hash)
     .bind(&redis_version)
     .bind(&actual_namespace)
     .bind(&deployment_name) // pod_name (using deployment name)
@@ -271,9 +298,28 @@ pub async fn create_redis_instance(
     .bind(backup_enabled)
     .bind(now)
     .bind(now)
-    .execute(&state.db_pool)
+    .bind(topology.as_str())
+    .bind(deployment_mode)
+    .execute(&mut *tx)
     .await
-    .map_err(|e| {
+    .map_err(|e| format!("Failed to create Redis instance: {}", e));
+
+    let saved = match insert_result {
+        Ok(_) => match state
+            .credentials
+            .store_instance_password(&mut *tx, instance_id, &redis_password)
+            .await
+        {
+            Ok(()) => tx
+                .commit()
+                .await
+                .map_err(|e| format!("Failed to create Redis instance: {}", e)),
+            Err(e) => Err(format!("Failed to store Redis instance password: {}", e)),
+        },
+        Err(message) => Err(message),
+    };
+
+    if let Err(message) = saved {
         // If database insert fails, we should clean up K8s resources if they were created
         if let Some(result) = k8s_deployment_result {
             tokio::spawn(async move {
@@ -282,12 +328,12 @@ pub async fn create_redis_instance(
                 }
             });
         }
-        
-        (
+
+        return Err((
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiResponse::<()>::error(format!("Failed to create Redis instance: {}", e))),
-        )
-    })?;
+            Json(ApiResponse::<()>::error(message)),
+        ));
+    }
 
     // Fetch created instance
     let redis_instance = sqlx::query_as!(
@@ -460,36 +506,7 @@ pub async fn delete_redis_instance(
     Extension(current_user): Extension<CurrentUser>,
     Path((org_id, instance_id)): Path<(Uuid, Uuid)>,
 ) -> Result<Json<ApiResponse<()>>, ErrorResponse> {
-    // Check if user has admin access to the organization
-    let org_membership = sqlx::query!(
-        r#"
-        SELECT role FROM organization_memberships 
-        WHERE organization_id = $1 AND user_id = $2 AND is_active = true
-        "#,
-        org_id,
-        current_user.id
-    )
-    .fetch_optional(&state.db_pool)
-    .await
-    .map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiResponse::<()>::error(format!("Database error: {}", e))),
-        )
-    })?
-    .ok_or_else(|| {
-        (
-            StatusCode::NOT_FOUND,
-            Json(ApiResponse::<()>::error("Organization not found or access denied".to_string())),
-        )
-    })?;
-
-    if !["admin", "owner"].contains(&org_membership.role.as_str()) {
-        return Err((
-            StatusCode::FORBIDDEN,
-            Json(ApiResponse::<()>::error("Insufficient permissions to delete Redis instances".to_string())),
-        ));
-    }
+    require_org_admin(&state, org_id, current_user.id, "delete Redis instances").await?;
 
     // Check if Redis instance exists and get its details
     let redis_instance = sqlx::query!(
@@ -561,8 +578,15 @@ pub async fn delete_redis_instance(
                 Json(ApiResponse::<()>::error(format!("Failed to deactivate API key: {}", e))),
             )
         })?;
+        state.api_key_cache.revoke(key_id).await;
     }
 
+    // Drop the pooled connection and cached policy of the deleted instance
+    state.redis_pool.remove_instance(&instance_id.to_string()).await;
+    state.command_policies.invalidate(instance_id).await;
+
+    release_dev_database(&state, instance_id).await;
+
     Ok(Json(ApiResponse {
         success: true,
         data: None,
@@ -571,6 +595,782 @@ pub async fn delete_redis_instance(
     }))
 }
 
+pub async fn rotate_redis_instance_password(
+    State(state): State<Arc<AppState>>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path((org_id, instance_id)): Path<(Uuid, Uuid)>,
+    headers: HeaderMap,
+) -> Result<Json<ApiResponse<RotatePasswordResponse>>, ErrorResponse> {
+    require_org_admin(&state, org_id, current_user.id, "rotate Redis instance passwords").await?;
+
+    let redis_instance = sqlx::query_as!(
+        RedisInstance,
+        "SELECT * FROM redis_instances WHERE id = $1 AND organization_id = $2 AND deleted_at IS NULL",
+        instance_id,
+        org_id
+    )
+    .fetch_optional(&state.db_pool)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::<()>::error(format!("Database error: {}", e))),
+        )
+    })?
+    .ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ApiResponse::<()>::error("Redis instance not found".to_string())),
+        )
+    })?;
+
+
+    match rotate_password(&state, &redis_instance).await {
+        Ok(change) => {
+            let details = json!({
+                "applied_live": change.is_some(),
+                "method": change.map(LivePasswordChange::as_str),
+            });
+            record_instance_audit(
+                &state,
+                &headers,
+                current_user.id,
+                org_id,
+                instance_id,
+                "rotate_password",
+                Ok(details),
+            )
+            .await;
+            info!("Rotated password of Redis instance {}", instance_id);
+
+            Ok(Json(ApiResponse {
+                success: true,
+                data: Some(RotatePasswordResponse {
+                    instance_id,
+                    applied_live: change.is_some(),
+                    rotated_at: Utc::now(),
+                }),
+                message: Some("Redis instance password rotated successfully".to_string()),
+                timestamp: Utc::now(),
+            }))
+        }
+        Err(e) => {
+            let error = e.1.message.clone().unwrap_or_default();
+            record_instance_audit(
+                &state,
+                &headers,
+                current_user.id,
+                org_id,
+                instance_id,
+                "rotate_password",
+                Err(error),
+            )
+            .await;
+            Err(e)
+        }
+    }
+}
+
+pub async fn get_redis_instance_credentials(
+    State(state): State<Arc<AppState>>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path((org_id, instance_id)): Path<(Uuid, Uuid)>,
+    headers: HeaderMap,
+) -> Result<([(HeaderName, &'static str); 1], Json<ApiResponse<RedisCredentialsResponse>>), ErrorResponse> {
+    require_org_admin(&state, org_id, current_user.id, "view Redis instance credentials").await?;
+
+    let redis_instance = sqlx::query_as!(
+        RedisInstance,
+        "SELECT * FROM redis_instances WHERE id = $1 AND organization_id = $2 AND deleted_at IS NULL",
+        instance_id,
+        org_id
+    )
+    .fetch_optional(&state.db_pool)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::<()>::error(format!("Database error: {}", e))),
+        )
+    })?
+    .ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ApiResponse::<()>::error("Redis instance not found".to_string())),
+        )
+    })?;
+
+    // Development instances share the local Redis, which has no password, each in its own database
+    let endpoint = if RedisEndpoint::is_local_development(&redis_instance) {
+        state.credentials.endpoint(&redis_instance).await.map_err(|e| {
+            error!("Failed to resolve endpoint of instance {}: {}", instance_id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::<()>::error(format!("Failed to resolve instance endpoint: {}", e))),
+            )
+        })
+    } else {
+        current_instance_password(&state, &redis_instance)
+            .await
+            .map(|password| RedisEndpoint::for_instance(&redis_instance, password))
+    };
+    let endpoint = match endpoint {
+        Ok(endpoint) => endpoint,
+        Err(e) => {
+            let error = e.1.message.clone().unwrap_or_default();
+            record_instance_audit(
+                &state,
+                &headers,
+                current_user.id,
+                org_id,
+                instance_id,
+                "view_credentials",
+                Err(error),
+            )
+            .await;
+            return Err(e);
+        }
+    };
+
+    record_instance_audit(
+        &state,
+        &headers,
+        current_user.id,
+        org_id,
+        instance_id,
+        "view_credentials",
+        Ok(Value::Null),
+    )
+    .await;
+    info!("User {} viewed credentials of Redis instance {}", current_user.id, instance_id);
+
+    let credentials = RedisCredentialsResponse {
+        instance_id,
+        redis_url: if state.instance_tls { endpoint.tls_url() } else { endpoint.url() },
+        host: endpoint.host,
+        port: endpoint.port,
+        password: endpoint.password,
+        tls: state.instance_tls,
+        rest_url: format!("{}/redis/{}", state.public_url, instance_id),
+    };
+
+    // Keep the password out of browser and proxy caches
+    Ok((
+        [(CACHE_CONTROL, "no-store")],
+        Json(ApiResponse::success(credentials)),
+    ))
+}
+
+pub async fn get_redis_instance_command_policy(
+    State(state): State<Arc<AppState>>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path((org_id, instance_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<ApiResponse<CommandPolicyResponse>>, ErrorResponse> {
+    // Check if user has access to the organization
+    let _org_membership = sqlx::query!(
+        r#"
+        SELECT role FROM organization_memberships 
+        WHERE organization_id = $1 AND user_id = $2 AND is_active = true
+        "#,
+        org_id,
+        current_user.id
+    )
+    .fetch_optional(&state.db_pool)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::<()>::error(format!("Database error: {}", e))),
+        )
+    })?
+    .ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ApiResponse::<()>::error("Organization not found or access denied".to_string())),
+        )
+    })?;
+
+    ensure_instance_exists(&state, org_id, instance_id).await?;
+
+    let policy = state.command_policies.policy(instance_id).await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::<()>::error(format!("Database error: {}", e))),
+        )
+    })?;
+
+    Ok(Json(ApiResponse::success(command_policy_to_response(instance_id, &policy))))
+}
+
+pub async fn update_redis_instance_command_policy(
+    State(state): State<Arc<AppState>>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path((org_id, instance_id)): Path<(Uuid, Uuid)>,
+    headers: HeaderMap,
+    Json(payload): Json<UpdateCommandPolicyRequest>,
+) -> Result<Json<ApiResponse<CommandPolicyResponse>>, ErrorResponse> {
+    require_org_admin(&state, org_id, current_user.id, "change command policies").await?;
+
+    ensure_instance_exists(&state, org_id, instance_id).await?;
+
+    let policy = CommandPolicy {
+        allowed_commands: payload.allowed_commands,
+        denied_commands: payload
+            .denied_commands
+            .unwrap_or_else(|| CommandPolicy::default().denied_commands),
+        renamed_commands: payload.renamed_commands,
+    };
+
+    let policy = state
+        .command_policies
+        .set_policy(instance_id, policy, current_user.id)
+        .await
+        .map_err(|e| match e {
+            PolicyError::Database(e) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::<()>::error(format!("Failed to update command policy: {}", e))),
+            ),
+            e => (
+                StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<()>::error(e.to_string())),
+            ),
+        })?;
+
+    let response = command_policy_to_response(instance_id, &policy);
+
+    record_instance_audit(
+        &state,
+        &headers,
+        current_user.id,
+        org_id,
+        instance_id,
+        "update_command_policy",
+        Ok(json!({
+        "allowed_commands": response.allowed_commands,
+        "denied_commands": response.denied_commands,
+        "renamed_commands": response.renamed_commands.keys().collect::<Vec<_>>(),
+    })),
+    )
+    .await;
+
+    info!("Command policy of Redis instance {} updated by user {}", instance_id, current_user.id);
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(response),
+        message: Some("Command policy updated successfully".to_string()),
+        timestamp: Utc::now(),
+    }))
+}
+
+pub async fn list_redis_instance_scripts(
+    State(state): State<Arc<AppState>>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path((org_id, instance_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<ApiResponse<Vec<ScriptResponse>>>, ErrorResponse> {
+    // Check if user has access to the organization
+    let _org_membership = sqlx::query!(
+        r#"
+        SELECT role FROM organization_memberships 
+        WHERE organization_id = $1 AND user_id = $2 AND is_active = true
+        "#,
+        org_id,
+        current_user.id
+    )
+    .fetch_optional(&state.db_pool)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::<()>::error(format!("Database error: {}", e))),
+        )
+    })?
+    .ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ApiResponse::<()>::error("Organization not found or access denied".to_string())),
+        )
+    })?;
+
+    ensure_instance_exists(&state, org_id, instance_id).await?;
+
+    let scripts = state.scripts.list(instance_id).await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::<()>::error(format!("Database error: {}", e))),
+        )
+    })?;
+
+    Ok(Json(ApiResponse::success(scripts.iter().map(script_to_response).collect())))
+}
+
+pub async fn put_redis_instance_script(
+    State(state): State<Arc<AppState>>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path((org_id, instance_id, name)): Path<(Uuid, Uuid, String)>,
+    headers: HeaderMap,
+    Json(payload): Json<UpdateScriptRequest>,
+) -> Result<Json<ApiResponse<ScriptResponse>>, ErrorResponse> {
+    require_org_admin(&state, org_id, current_user.id, "manage scripts").await?;
+
+    ensure_instance_exists(&state, org_id, instance_id).await?;
+
+    let script = state
+        .scripts
+        .put(instance_id, &name, payload.source, current_user.id)
+        .await
+        .map_err(|e| match e {
+            ScriptError::Database(e) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::<()>::error(format!("Failed to store script: {}", e))),
+            ),
+            e => (
+                StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<()>::error(e.to_string())),
+            ),
+        })?;
+
+    let response = script_to_response(&script);
+
+    record_instance_audit(
+        &state,
+        &headers,
+        current_user.id,
+        org_id,
+        instance_id,
+        "put_script",
+        Ok(json!({
+        "name": response.name,
+        "sha1": response.sha1,
+        "access_class": response.access_class,
+    })),
+    )
+    .await;
+
+    info!("Script {} of Redis instance {} stored by user {}", name, instance_id, current_user.id);
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(response),
+        message: Some("Script stored successfully".to_string()),
+        timestamp: Utc::now(),
+    }))
+}
+
+pub async fn delete_redis_instance_script(
+    State(state): State<Arc<AppState>>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path((org_id, instance_id, name)): Path<(Uuid, Uuid, String)>,
+    headers: HeaderMap,
+) -> Result<Json<ApiResponse<()>>, ErrorResponse> {
+    require_org_admin(&state, org_id, current_user.id, "manage scripts").await?;
+
+    ensure_instance_exists(&state, org_id, instance_id).await?;
+
+    let deleted = state.scripts.delete(instance_id, &name).await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::<()>::error(format!("Database error: {}", e))),
+        )
+    })?;
+
+    if !deleted {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(ApiResponse::<()>::error("Script not found".to_string())),
+        ));
+    }
+
+    record_instance_audit(
+        &state,
+        &headers,
+        current_user.id,
+        org_id,
+        instance_id,
+        "delete_script",
+        Ok(json!({ "name": name })),
+    )
+    .await;
+
+    info!("Script {} of Redis instance {} deleted by user {}", name, instance_id, current_user.id);
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: None,
+        message: Some("Script deleted successfully".to_string()),
+        timestamp: Utc::now(),
+    }))
+}
+
+fn script_to_response(script: &RegisteredScript) -> ScriptResponse {
+    ScriptResponse {
+        instance_id: script.instance_id,
+        name: script.name.clone(),
+        source: script.source.clone(),
+        sha1: script.sha1.clone(),
+        commands: script.commands.commands.clone(),
+        dynamic_commands: script.commands.dynamic,
+        access_class: script.commands.class().to_string(),
+        updated_at: script.updated_at,
+    }
+}
+
+fn command_policy_to_response(instance_id: Uuid, policy: &CommandPolicy) -> CommandPolicyResponse {
+    CommandPolicyResponse {
+        instance_id,
+        allowed_commands: policy.allowed_commands.clone(),
+        denied_commands: policy.denied_commands.clone(),
+        renamed_commands: policy.renamed_commands.clone(),
+    }
+}
+
+/// Reject users who are not an admin or owner of the organization
+///
+/// `action` completes the error message, e.g. "manage scripts".
+async fn require_org_admin(state: &AppState, org_id: Uuid, user_id: Uuid, action: &str) -> Result<(), ErrorResponse> {
+    let org_membership = sqlx::query!(
+        r#"
+        SELECT role FROM organization_memberships 
+        WHERE organization_id = $1 AND user_id = $2 AND is_active = true
+        "#,
+        org_id,
+        user_id
+    )
+    .fetch_optional(&state.db_pool)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::<()>::error(format!("Database error: {}", e))),
+        )
+    })?
+    .ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ApiResponse::<()>::error("Organization not found or access denied".to_string())),
+        )
+    })?;
+
+    if !["admin", "owner"].contains(&org_membership.role.as_str()) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(ApiResponse::<()>::error(format!("Insufficient permissions to {}", action))),
+        ));
+    }
+    Ok(())
+}
+
+/// Write the audit entry of an action on a Redis instance: its details, or why it failed
+async fn record_instance_audit(
+    state: &AppState,
+    headers: &HeaderMap,
+    user_id: Uuid,
+    org_id: Uuid,
+    instance_id: Uuid,
+    action: &'static str,
+    outcome: Result<Value, String>,
+) {
+    let mut event = AuditEvent::new(action, "redis_instance", instance_id);
+    event.user_id = Some(user_id);
+    event.organization_id = Some(org_id);
+    event.user_agent = headers
+        .get(USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    match outcome {
+        Ok(details) => event.details = details,
+        Err(error) => event.error_message = Some(error),
+    }
+    audit::record(&state.db_pool, &event).await;
+}
+
+async fn ensure_instance_exists(state: &AppState, org_id: Uuid, instance_id: Uuid) -> Result<(), ErrorResponse> {
+    sqlx::query("SELECT id FROM redis_instances WHERE id = $1 AND organization_id = $2 AND deleted_at IS NULL")
+        .bind(instance_id)
+        .bind(org_id)
+        .fetch_optional(&state.db_pool)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::<()>::error(format!("Database error: {}", e))),
+            )
+        })?
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ApiResponse::<()>::error("Redis instance not found".to_string())),
+            )
+        })?;
+    Ok(())
+}
+
+/// How a new password was applied to a running Redis server
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LivePasswordChange {
+    /// Added with `ACL SETUSER`; the old password keeps working until it is retired
+    Acl,
+    /// Set with `CONFIG SET requirepass` (Redis < 6); the old password stops working at once
+    RequirePass,
+}
+
+impl LivePasswordChange {
+    fn as_str(self) -> &'static str {
+        match self {
+            LivePasswordChange::Acl => "acl",
+            LivePasswordChange::RequirePass => "requirepass",
+        }
+    }
+}
+
+/// Empty and free the local Redis database of a deleted development instance
+///
+/// If the database cannot be emptied it stays assigned, so the next development instance
+/// never starts with another instance's keys.
+async fn release_dev_database(state: &AppState, instance_id: Uuid) {
+    let endpoint = match state.credentials.assigned_dev_endpoint(instance_id).await {
+        Ok(Some(endpoint)) => endpoint,
+        Ok(None) => return,
+        Err(e) => {
+            warn!("Failed to look up the development database of instance {}: {}", instance_id, e);
+            return;
+        }
+    };
+
+    let flushed = match state.redis_backend.dedicated_connection(&endpoint).await {
+        Ok(mut conn) => redis::cmd("FLUSHDB")
+            .query_async::<_, ()>(&mut conn)
+            .await
+            .map_err(|e| e.to_string()),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = flushed {
+        warn!(
+            "Failed to empty database {} of deleted instance {}, it stays reserved: {}",
+            endpoint.database, instance_id, e
+        );
+        return;
+    }
+
+    match state.credentials.release_dev_database(instance_id).await {
+        Ok(()) => info!("Released database {} of deleted instance {}", endpoint.database, instance_id),
+        Err(e) => warn!("Failed to release the development database of instance {}: {}", instance_id, e),
+    }
+}
+
+/// Replace an instance's password everywhere it is kept
+///
+/// The new password is accepted by the server before anything starts using it, and the
+/// old one is only retired once the secret and database have been updated, so clients
+/// never see authentication failures. Returns `None` for development instances, which
+/// share the local Redis and only get their stored password replaced.
+async fn rotate_password(
+    state: &AppState,
+    instance: &RedisInstance,
+) -> Result<Option<LivePasswordChange>, ErrorResponse> {
+    let new_password = generate_redis_password();
+
+    if RedisEndpoint::is_local_development(instance) {
+        store_password(state, instance.id, &new_password).await?;
+        return Ok(None);
+    }
+
+    // The password is applied on one server only. On a cluster, ACL SETUSER reaches a single
+    // node; with Sentinel, replicas and their masterauth would keep the old password and
+    // the next failover (or retiring the old password) would lock the gateway out.
+    let unsupported = if Topology::from_column(&instance.topology) == Topology::Cluster {
+        Some("Password rotation is not supported for cluster instances yet")
+    } else if SentinelConfig::from_instance(instance).is_some() {
+        Some("Password rotation is not supported for Sentinel-managed instances yet")
+    } else {
+        None
+    };
+    if let Some(message) = unsupported {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::<()>::error(message.to_string())),
+        ));
+    }
+
+    let namespace = instance.namespace.clone().unwrap_or_else(|| "default".to_string());
+    let k8s_service = match K8sRedisService::new().await {
+        Ok(k8s_service) => Some(k8s_service),
+        Err(e) => {
+            warn!("Kubernetes not available: {}. Skipping secret update.", e);
+            None
+        }
+    };
+
+    let current_password = current_instance_password(state, instance).await?;
+
+    let endpoint = RedisEndpoint::for_instance(instance, current_password.clone());
+    let mut conn = state.redis_backend.dedicated_connection(&endpoint).await.map_err(|e| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ApiResponse::<()>::error(format!("Failed to connect to Redis instance: {}", e))),
+        )
+    })?;
+
+    let change = add_live_password(&mut conn, &new_password).await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::<()>::error(format!("Failed to apply new password: {}", e))),
+        )
+    })?;
+
+    // Pods restarted from now on must come up with the new password
+    if let Some(k8s_service) = &k8s_service {
+        if let Err(e) = k8s_service
+            .update_redis_password(&namespace, &instance.slug, &new_password)
+            .await
+        {
+            revert_live_password(&mut conn, change, current_password.as_deref(), &new_password).await;
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::<()>::error(format!("Failed to update instance secret: {}", e))),
+            ));
+        }
+    }
+
+    if let Err(e) = store_password(state, instance.id, &new_password).await {
+        revert_live_password(&mut conn, change, current_password.as_deref(), &new_password).await;
+        if let (Some(k8s_service), Some(old_password)) = (&k8s_service, &current_password) {
+            if let Err(e) = k8s_service
+                .update_redis_password(&namespace, &instance.slug, old_password)
+                .await
+            {
+                error!("Failed to restore secret of instance {}: {}", instance.id, e);
+            }
+        }
+        return Err(e);
+    }
+
+    // New gateway connections pick up the new password; connections that are already
+    // authenticated stay valid after the old password is retired
+    state.redis_pool.remove_instance(&instance.id.to_string()).await;
+
+    if let (LivePasswordChange::Acl, Some(old_password)) = (change, &current_password) {
+        let retired: redis::RedisResult<()> = redis::cmd("ACL")
+            .arg("SETUSER")
+            .arg("default")
+            .arg(format!("<{}", old_password))
+            .query_async(&mut conn)
+            .await;
+        if let Err(e) = retired {
+            warn!("Failed to retire old password of instance {}: {}", instance.id, e);
+        }
+    }
+
+    Ok(Some(change))
+}
+
+/// Password an instance currently accepts
+///
+/// Instances created before credentials were stored only have their password in the
+/// Kubernetes secret; it is read from there and stored encrypted for next time.
+async fn current_instance_password(
+    state: &AppState,
+    instance: &RedisInstance,
+) -> Result<Option<String>, ErrorResponse> {
+    let stored_password = state.credentials.instance_password(instance.id).await.map_err(|e| {
+        error!("Failed to load credentials for instance {}: {}", instance.id, e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::<()>::error("Failed to load instance credentials".to_string())),
+        )
+    })?;
+    if stored_password.is_some() {
+        return Ok(stored_password);
+    }
+
+    let namespace = instance.namespace.as_deref().unwrap_or("default");
+    let secret_password = match K8sRedisService::new().await {
+        Ok(k8s_service) => k8s_service
+            .get_redis_password(namespace, &instance.slug)
+            .await
+            .unwrap_or_else(|e| {
+                warn!("Failed to read secret of instance {}: {}", instance.id, e);
+                None
+            }),
+        Err(e) => {
+            warn!("Kubernetes not available: {}. Instance {} has no stored password.", e, instance.id);
+            None
+        }
+    };
+
+    if let Some(password) = &secret_password {
+        store_password(state, instance.id, password).await?;
+    }
+    Ok(secret_password)
+}
+
+/// Make a running server accept a new password
+async fn add_live_password(
+    conn: &mut BackendConnection,
+    password: &str,
+) -> redis::RedisResult<LivePasswordChange> {
+    let added: redis::RedisResult<()> = redis::cmd("ACL")
+        .arg("SETUSER")
+        .arg("default")
+        .arg(format!(">{}", password))
+        .query_async(conn)
+        .await;
+
+    match added {
+        Ok(()) => Ok(LivePasswordChange::Acl),
+        // Servers older than Redis 6 have no ACLs
+        Err(e) if e.to_string().to_lowercase().contains("unknown command") => {
+            redis::cmd("CONFIG")
+                .arg("SET")
+                .arg("requirepass")
+                .arg(password)
+                .query_async::<_, ()>(conn)
+                .await?;
+            Ok(LivePasswordChange::RequirePass)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Undo `add_live_password` after a later step of the rotation failed
+async fn revert_live_password(
+    conn: &mut BackendConnection,
+    change: LivePasswordChange,
+    old_password: Option<&str>,
+    new_password: &str,
+) {
+    let cmd = match change {
+        LivePasswordChange::Acl => {
+            let mut cmd = redis::cmd("ACL");
+            cmd.arg("SETUSER").arg("default").arg(format!("<{}", new_password));
+            cmd
+        }
+        LivePasswordChange::RequirePass => {
+            let mut cmd = redis::cmd("CONFIG");
+            cmd.arg("SET").arg("requirepass").arg(old_password.unwrap_or(""));
+            cmd
+        }
+    };
+
+    if let Err(e) = cmd.query_async::<_, ()>(conn).await {
+        error!("Failed to revert password change, the instance accepts both passwords: {}", e);
+    }
+}
+
+async fn store_password(state: &AppState, instance_id: Uuid, password: &str) -> Result<(), ErrorResponse> {
+    state
+        .credentials
+        .store_instance_password(&state.db_pool, instance_id, password)
+        .await
+        .map_err(|e| {
+            error!("Failed to store credentials for instance {}: {}", instance_id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::<()>::error("Failed to store instance credentials".to_string())),
+            )
+        })
+}
+
 pub async fn update_redis_instance_status(
     State(state): State<Arc<AppState>>,
     Extension(current_user): Extension<CurrentUser>,
@@ -682,7 +1482,129 @@ pub async fn update_redis_instance_status(
         )
     })?;
 
-    let instance_response = redis_instance_to_response(updated_instance);
+    // Sentinel-managed instances also report where their master is right now
+    let sentinel_config = SentinelConfig::from_instance(&updated_instance);
+    let mut instance_response = redis_instance_to_response(updated_instance);
+    if let Some(config) = sentinel_config {
+        match state.redis_pool.refresh_master(&config).await {
+            Ok((host, port)) => instance_response.current_master = Some(sentinel::format_address(&host, port)),
+            Err(e) => warn!("Failed to resolve the master of Redis instance {}: {}", instance_id, e),
+        }
+    }
 
     Ok(Json(ApiResponse::success(instance_response)))
 }
+
+/// Point an instance at the sentinels watching its master, or stop using Sentinel
+///
+/// With Sentinel settings, the gateway connects to whichever server Sentinel reports as
+/// master instead of the instance's own address, and follows failovers.
+pub async fn update_redis_instance_sentinel(
+    State(state): State<Arc<AppState>>,
+    Extension(current_user): Extension<CurrentUser>,
+    Path((org_id, instance_id)): Path<(Uuid, Uuid)>,
+    headers: HeaderMap,
+    Json(payload): Json<UpdateSentinelRequest>,
+) -> Result<Json<ApiResponse<RedisInstanceResponse>>, ErrorResponse> {
+    require_org_admin(&state, org_id, current_user.id, "change Sentinel settings").await?;
+
+    let mut redis_instance = sqlx::query_as!(
+        RedisInstance,
+        "SELECT * FROM redis_instances WHERE id = $1 AND organization_id = $2 AND deleted_at IS NULL",
+        instance_id,
+        org_id
+    )
+    .fetch_optional(&state.db_pool)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::<()>::error(format!("Database error: {}", e))),
+        )
+    })?
+    .ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ApiResponse::<()>::error("Redis instance not found".to_string())),
+        )
+    })?;
+
+    let bad_request = |message: &str| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::<()>::error(message.to_string())),
+        )
+    };
+    if RedisEndpoint::is_local_development(&redis_instance) {
+        return Err(bad_request("Development instances use the shared local Redis and cannot use Sentinel"));
+    }
+    if Topology::from_column(&redis_instance.topology) == Topology::Cluster {
+        return Err(bad_request("Cluster instances fail over on their own and cannot use Sentinel"));
+    }
+
+    let config = payload
+        .master_name
+        .as_deref()
+        .map(|master_name| SentinelConfig::new(master_name, &payload.endpoints))
+        .transpose()
+        .map_err(|e| bad_request(&e.to_string()))?;
+    let (master_name, endpoints) = match &config {
+        Some(config) => (Some(config.master_name.clone()), config.endpoints()),
+        None => (None, Vec::new()),
+    };
+
+    sqlx::query(
+        "UPDATE redis_instances SET sentinel_master_name = $1, sentinel_endpoints = $2, updated_at = $3 WHERE id = $4",
+    )
+    .bind(&master_name)
+    .bind(&endpoints)
+    .bind(Utc::now())
+    .bind(instance_id)
+    .execute(&state.db_pool)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::<()>::error(format!("Failed to update Sentinel settings: {}", e))),
+        )
+    })?;
+
+    // Connections were opened for the old address; the next request connects anew
+    if let Some(previous) = SentinelConfig::from_instance(&redis_instance) {
+        state.redis_pool.forget_master(&previous).await;
+    }
+    state.redis_pool.remove_instance(&instance_id.to_string()).await;
+
+    record_instance_audit(
+        &state,
+        &headers,
+        current_user.id,
+        org_id,
+        instance_id,
+        "update_sentinel",
+        Ok(json!({
+        "master_name": master_name,
+        "endpoints": endpoints,
+    })),
+    )
+    .await;
+
+    info!("Sentinel settings of Redis instance {} updated by user {}", instance_id, current_user.id);
+
+    redis_instance.sentinel_master_name = master_name;
+    redis_instance.sentinel_endpoints = endpoints;
+    let mut instance_response = redis_instance_to_response(redis_instance);
+    if let Some(config) = config {
+        match state.redis_pool.refresh_master(&config).await {
+            Ok((host, port)) => instance_response.current_master = Some(sentinel::format_address(&host, port)),
+            Err(e) => warn!("Failed to resolve the master of Redis instance {}: {}", instance_id, e),
+        }
+    }
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(instance_response),
+        message: Some("Sentinel settings updated successfully".to_string()),
+        timestamp: Utc::now(),
+    }))
+}
@@ -0,0 +1,473 @@
+// Native RESP proxy so standard Redis clients can connect through the gateway
+//
+// Clients authenticate with `AUTH <api-key>` (or `AUTH <instance> <api-key>`), pick an
+// instance of the key's organization with `SELECT <instance-id|slug>` when it was not
+// given as the username, and then talk to that instance as if it were a plain Redis
+// server. Every command goes through the same command policy, scope checks, rate limits
+// and metrics as the HTTP API.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Semaphore;
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
+
+use crate::auth::ApiKeyClaims;
+use crate::config::ProxyConfig;
+use crate::error::redis_error_message;
+use crate::handlers::redis::{
+    authenticate_api_key, ensure_api_key_active, error_message, get_dedicated_connection,
+};
+use crate::middleware::AppState;
+use crate::models::RedisInstance;
+use crate::services::command_policy::PolicyViolation;
+use crate::services::command_scope::{classify_command, scopes_allow};
+use crate::services::metrics::MetricsService;
+use crate::services::redis_backend::BackendConnection;
+use crate::services::redis_pool::RedisEndpoint;
+use crate::services::resp::{self, encode_error, encode_status, encode_value, Limits};
+
+/// Size of each socket read
+const READ_CHUNK_SIZE: usize = 16 * 1024;
+
+/// Commands that need a connection mode the proxy cannot forward
+const UNSUPPORTED_COMMANDS: &[&str] = &[
+    "SUBSCRIBE", "PSUBSCRIBE", "SSUBSCRIBE", "UNSUBSCRIBE", "PUNSUBSCRIBE", "SUNSUBSCRIBE",
+    "MONITOR", "SYNC", "PSYNC",
+];
+
+/// Accept RESP clients until the listener fails
+pub async fn serve(state: Arc<AppState>, config: ProxyConfig) -> std::io::Result<()> {
+    let listener = TcpListener::bind((config.host.as_str(), config.port)).await?;
+    let slots = Arc::new(Semaphore::new(config.max_clients));
+
+    info!("🔌 RESP proxy listening on {}:{}", config.host, config.port);
+
+    loop {
+        let (mut socket, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                warn!("Failed to accept RESP client: {}", e);
+                continue;
+            }
+        };
+
+        let Ok(slot) = slots.clone().try_acquire_owned() else {
+            warn!("Rejecting RESP client {}: max clients reached", peer);
+            let mut out = Vec::new();
+            encode_error("ERR max number of clients reached", &mut out);
+            let _ = socket.write_all(&out).await;
+            continue;
+        };
+
+        let state = state.clone();
+        tokio::spawn(async move {
+            let _slot = slot;
+            ProxySession::new(state, peer).run(socket).await;
+        });
+    }
+}
+
+/// What to do with the client connection after a command
+enum Flow {
+    Continue,
+    Close,
+}
+
+/// State of one client connection
+struct ProxySession {
+    state: Arc<AppState>,
+    peer: SocketAddr,
+    claims: Option<ApiKeyClaims>,
+    instance: Option<RedisInstance>,
+    /// Connection to the selected instance, owned by this client so that MULTI/EXEC,
+    /// WATCH, SELECT and blocking commands behave as they would against Redis itself
+    backend: Option<BackendConnection>,
+}
+
+impl ProxySession {
+    fn new(state: Arc<AppState>, peer: SocketAddr) -> Self {
+        Self {
+            state,
+            peer,
+            claims: None,
+            instance: None,
+            backend: None,
+        }
+    }
+
+    async fn run(mut self, mut socket: TcpStream) {
+        debug!("RESP client connected: {}", self.peer);
+
+        let mut buf = Vec::new();
+        let mut chunk = vec![0u8; READ_CHUNK_SIZE];
+        let mut out = Vec::new();
+
+        loop {
+            let n = match socket.read(&mut chunk).await {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) => {
+                    debug!("RESP client {} read error: {}", self.peer, e);
+                    break;
+                }
+            };
+            buf.extend_from_slice(&chunk[..n]);
+
+            // Answer every complete command in the buffer, then flush once (pipelining)
+            let mut close = false;
+            let mut consumed = 0;
+            loop {
+                // Anonymous clients get Redis' small pre-AUTH limits
+                let limits = if self.claims.is_some() {
+                    Limits::AUTHENTICATED
+                } else {
+                    Limits::UNAUTHENTICATED
+                };
+                match resp::parse_command(&buf[consumed..], limits) {
+                    Ok(Some((args, len))) => {
+                        consumed += len;
+                        if args.is_empty() {
+                            continue;
+                        }
+                        if let Flow::Close = self.handle(args, &mut out).await {
+                            close = true;
+                            break;
+                        }
+                    }
+                    Ok(None) => break,
+                    Err(e) => {
+                        encode_error(&format!("ERR {}", e), &mut out);
+                        close = true;
+                        break;
+                    }
+                }
+            }
+            buf.drain(..consumed);
+
+            if !out.is_empty() {
+                if let Err(e) = socket.write_all(&out).await {
+                    debug!("RESP client {} write error: {}", self.peer, e);
+                    break;
+                }
+                out.clear();
+            }
+            if close {
+                break;
+            }
+        }
+
+        debug!("RESP client disconnected: {}", self.peer);
+    }
+
+    /// Handle one command and append its reply to `out`
+    async fn handle(&mut self, args: Vec<Vec<u8>>, out: &mut Vec<u8>) -> Flow {
+        let command = String::from_utf8_lossy(&args[0]).to_uppercase();
+
+        match command.as_str() {
+            "QUIT" => {
+                encode_status("OK", out);
+                return Flow::Close;
+            }
+            "AUTH" => {
+                self.auth(&args, out).await;
+                return Flow::Continue;
+            }
+            // Only RESP2 is spoken; clients fall back to AUTH on this error
+            "HELLO" => {
+                encode_error("NOPROTO this gateway only supports RESP2, use AUTH", out);
+                return Flow::Continue;
+            }
+            _ => {}
+        }
+
+        if self.claims.is_none() {
+            encode_error("NOAUTH Authentication required.", out);
+            return Flow::Continue;
+        }
+
+        if command == "SELECT" && args.len() == 2 {
+            let selector = String::from_utf8_lossy(&args[1]).to_string();
+            // Before an instance is chosen (or with a non-numeric argument) SELECT picks the
+            // instance; afterwards a database index is passed through to Redis
+            if self.instance.is_none() || selector.parse::<u32>().is_err() {
+                self.select_instance(&selector, out).await;
+                return Flow::Continue;
+            }
+        }
+
+        if self.instance.is_none() {
+            encode_error(
+                "ERR no instance selected, use SELECT <instance-id|slug> or AUTH <instance> <api-key>",
+                out,
+            );
+            return Flow::Continue;
+        }
+
+        // Connection housekeeping only affects this client's own backend connection
+        let connection_command = command == "SELECT"
+            || (command == "CLIENT"
+                && args.get(1).is_some_and(|sub| {
+                    sub.eq_ignore_ascii_case(b"SETNAME") || sub.eq_ignore_ascii_case(b"SETINFO")
+                }));
+
+        let command = match self.authorize(&command, connection_command).await {
+            Ok(command) => command,
+            Err((message, flow)) => {
+                encode_error(&message, out);
+                return flow;
+            }
+        };
+
+        if UNSUPPORTED_COMMANDS.contains(&command.as_str()) {
+            encode_error(
+                &format!(
+                    "ERR {} is not supported through the proxy, use the SSE or WebSocket endpoints",
+                    command
+                ),
+                out,
+            );
+            return Flow::Continue;
+        }
+
+        self.forward(&command, &args, out).await
+    }
+
+    /// Apply the instance's command policy, the API key's scopes and its rate limit to a
+    /// command, returning the name to send it to Redis under
+    ///
+    /// The key is checked again first: one that expired or was revoked since `AUTH` ends
+    /// the session.
+    async fn authorize(&self, command: &str, connection_command: bool) -> Result<String, (String, Flow)> {
+        let (Some(claims), Some(instance)) = (self.claims.as_ref(), self.instance.as_ref()) else {
+            return Err(("NOAUTH Authentication required.".to_string(), Flow::Continue));
+        };
+
+        if let Err(e) = ensure_api_key_active(&self.state, claims).await {
+            info!("Closing RESP session of client {}: API key {} is no longer valid", self.peer, claims.key_prefix);
+            return Err((format!("WRONGPASS {}", error_message(e)), Flow::Close));
+        }
+        self.authorize_command(claims, instance, command, connection_command)
+            .await
+            .map_err(|message| (message, Flow::Continue))
+    }
+
+    async fn authorize_command(
+        &self,
+        claims: &ApiKeyClaims,
+        instance: &RedisInstance,
+        command: &str,
+        connection_command: bool,
+    ) -> Result<String, String> {
+
+        // Development instances share the local Redis, one database each
+        if RedisEndpoint::is_local_development(instance) && RedisEndpoint::crosses_databases(command) {
+            return Err(format!("NOPERM {} is not available on development instances", command));
+        }
+
+        let mut command = command.to_string();
+        if !connection_command {
+            let policy = self.state.command_policies.policy(instance.id).await.map_err(|e| {
+                error!("Failed to load command policy for instance {}: {}", instance.id, e);
+                "ERR internal error".to_string()
+            })?;
+            command = policy.resolve(&command).map_err(|violation| match violation {
+                PolicyViolation::Renamed(name) => {
+                    format!("ERR unknown command '{}'", name.to_lowercase())
+                }
+                violation => format!("NOPERM {}", violation),
+            })?;
+
+            let class = classify_command(&command);
+            if !scopes_allow(&claims.scopes, class) {
+                warn!(
+                    "API key {} with scopes {:?} denied {} command {} over RESP",
+                    claims.key_prefix, claims.scopes, class, command
+                );
+                return Err(format!(
+                    "NOPERM API key is not allowed to run {} ({} command)",
+                    command, class
+                ));
+            }
+        }
+
+        if self.state.rate_limit_enabled {
+            let decision = self
+                .state
+                .rate_limiter
+                .check_api_key_detailed(&claims.api_key_id.to_string(), None)
+                .await;
+            if !decision.allowed {
+                return Err(format!(
+                    "ERR rate limit exceeded, retry in {} seconds",
+                    decision.retry_after_secs()
+                ));
+            }
+        }
+
+        Ok(command)
+    }
+
+    /// `AUTH <api-key>` or `AUTH <instance-id|slug> <api-key>`
+    async fn auth(&mut self, args: &[Vec<u8>], out: &mut Vec<u8>) {
+        let (selector, token) = match args {
+            [_, token] => (None, token),
+            [_, username, token] => {
+                let username = String::from_utf8_lossy(username).to_string();
+                // Clients send "default" when only a password is configured
+                (Some(username).filter(|u| u != "default"), token)
+            }
+            _ => {
+                encode_error("ERR wrong number of arguments for 'auth' command", out);
+                return;
+            }
+        };
+
+        // Re-authenticating starts over, even if the new key is rejected
+        self.claims = None;
+        self.instance = None;
+        self.backend = None;
+
+        let token = String::from_utf8_lossy(token);
+        let claims = match authenticate_api_key(&self.state, &token).await {
+            Ok(claims) => claims,
+            Err(e) => {
+                self.state.metrics.inc_auth_failures();
+                encode_error(&format!("WRONGPASS {}", error_message(e)), out);
+                return;
+            }
+        };
+
+        info!("RESP client {} authenticated with API key {}", self.peer, claims.key_prefix);
+        self.claims = Some(claims);
+
+        match selector {
+            Some(selector) => self.select_instance(&selector, out).await,
+            None => encode_status("OK", out),
+        }
+    }
+
+    /// Point this session at one of the organization's instances and connect to it
+    async fn select_instance(&mut self, selector: &str, out: &mut Vec<u8>) {
+        let Some(organization_id) = self.claims.as_ref().map(|c| c.organization_id) else {
+            encode_error("NOAUTH Authentication required.", out);
+            return;
+        };
+
+        let instance = match find_instance(&self.state, organization_id, selector).await {
+            Ok(Some(instance)) => instance,
+            Ok(None) => {
+                encode_error(&format!("ERR instance not found: {}", selector), out);
+                return;
+            }
+            Err(e) => {
+                error!("Database error resolving instance {}: {}", selector, e);
+                encode_error("ERR internal error", out);
+                return;
+            }
+        };
+
+        let backend = match get_dedicated_connection(&self.state, &instance).await {
+            Ok(conn) => conn,
+            Err(e) => {
+                self.state.metrics.inc_redis_connection_errors();
+                encode_error(&format!("ERR {}", error_message(e)), out);
+                return;
+            }
+        };
+
+        info!("RESP client {} selected instance {} ({})", self.peer, instance.slug, instance.id);
+        self.instance = Some(instance);
+        self.backend = Some(backend);
+        encode_status("OK", out);
+    }
+
+    /// Send a command to the selected instance and relay its reply
+    async fn forward(&mut self, command: &str, args: &[Vec<u8>], out: &mut Vec<u8>) -> Flow {
+        let Some(backend) = self.backend.as_mut() else {
+            encode_error("ERR no instance selected", out);
+            return Flow::Continue;
+        };
+
+        // Only Pub/Sub arguments are rewritten, so the rest are not copied
+        let namespaced;
+        let args = match &self.instance {
+            Some(instance) if RedisEndpoint::uses_channels(command) => {
+                let mut channels = args[1..].to_vec();
+                RedisEndpoint::namespace_channels(instance, command, &mut channels);
+                namespaced = channels;
+                &namespaced[..]
+            }
+            _ => &args[1..],
+        };
+        let mut cmd = redis::cmd(command);
+        for arg in args {
+            cmd.arg(arg.as_slice());
+        }
+
+        self.state.metrics.inc_requests();
+        self.state.metrics.inc_redis_commands();
+        let start = Instant::now();
+        let result = cmd.query_async::<_, redis::Value>(backend).await;
+        let duration = start.elapsed().as_secs_f64();
+
+        match result {
+            Ok(value) => {
+                self.state.metrics.inc_success();
+                MetricsService::record_redis_command(command, true, duration);
+                encode_value(&value, out);
+                Flow::Continue
+            }
+            Err(e) if e.is_io_error() || e.is_connection_dropped() => {
+                self.state.metrics.inc_errors();
+                self.state.metrics.inc_redis_connection_errors();
+                MetricsService::record_redis_command(command, false, duration);
+                warn!("RESP proxy lost backend connection for client {}: {}", self.peer, e);
+                encode_error("ERR connection to the Redis instance was lost", out);
+                Flow::Close
+            }
+            Err(e) => {
+                self.state.metrics.inc_errors();
+                MetricsService::record_redis_command(command, false, duration);
+                // Errors that did not come from Redis itself still need an error code
+                let message = match e.code() {
+                    Some(_) => redis_error_message(&e),
+                    None => format!("ERR {}", e),
+                };
+                encode_error(&message, out);
+                Flow::Continue
+            }
+        }
+    }
+}
+
+/// Look up an instance of the organization by id or slug
+async fn find_instance(
+    state: &AppState,
+    organization_id: Uuid,
+    selector: &str,
+) -> Result<Option<RedisInstance>, sqlx::Error> {
+    sqlx::query_as::<_, RedisInstance>(
+        r#"
+        SELECT id, name, slug, organization_id, api_key_id,
+               port, private_ip_address, public_ip_address, domain,
+               max_memory, current_memory, password_hash, redis_version, topology,
+               sentinel_master_name, sentinel_endpoints, deployment_mode,
+               namespace, pod_name, service_name,
+               status, last_health_check_at, health_status,
+               cpu_usage_percent, memory_usage_percent, connections_count, max_connections,
+               persistence_enabled, backup_enabled, last_backup_at,
+               created_at, updated_at, deleted_at
+        FROM redis_instances
+        WHERE organization_id = $1 AND (id = $2 OR slug = $3) AND deleted_at IS NULL
+        "#,
+    )
+    .bind(organization_id)
+    .bind(Uuid::parse_str(selector).ok())
+    .bind(selector)
+    .fetch_optional(&state.db_pool)
+    .await
+}
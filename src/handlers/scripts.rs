@@ -0,0 +1,132 @@
+// Invoking registered Lua scripts by name
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::handlers::redis::{
+    authenticate_request, authorize_script, command_failed, get_redis_connection, is_cluster, redis_value_to_json,
+    value_to_arg, ErrorResponse, RedisResponse,
+};
+use crate::middleware::AppState;
+use crate::services::cluster;
+use crate::services::redis_backend::BackendConnection;
+use crate::services::scripts::RegisteredScript;
+
+/// Script invocation request body
+#[derive(Debug, Deserialize)]
+pub struct InvokeScriptRequest {
+    /// Available to the script as `KEYS`
+    #[serde(default)]
+    pub keys: Vec<Value>,
+    /// Available to the script as `ARGV`
+    #[serde(default)]
+    pub args: Vec<Value>,
+}
+
+/// Run a registered script: `POST /redis/:instance_id/scripts/:name`
+///
+/// Runs the script with EVALSHA. If Redis does not have it cached (after a restart or
+/// SCRIPT FLUSH), it is loaded with SCRIPT LOAD and run again.
+pub async fn handle_invoke_script(
+    State(state): State<Arc<AppState>>,
+    Path((instance_id, name)): Path<(Uuid, String)>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    Json(request): Json<InvokeScriptRequest>,
+) -> Result<Json<RedisResponse>, ErrorResponse> {
+    let (instance, claims) = authenticate_request(&state, &headers, query, instance_id).await?;
+
+    let script = state
+        .scripts
+        .script(instance_id, &name)
+        .await
+        .map_err(|e| {
+            error!("Failed to load script {} of instance {}: {}", name, instance_id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": "Failed to load script"})),
+            )
+        })?
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(json!({"error": format!("Script {} not found", name)})),
+            )
+        })?;
+
+    authorize_script(&state, &instance, &claims, &script).await?;
+
+    let keys: Vec<String> = request.keys.iter().map(value_to_arg).collect();
+    let args: Vec<String> = request.args.iter().map(value_to_arg).collect();
+
+    // A script runs on one node of a cluster, so its keys must share a slot
+    if is_cluster(&instance) {
+        cluster::same_slot(keys.iter().map(|key| key.as_bytes()))
+            .map_err(|e| ErrorResponse::from(AppError::from(e)))?;
+    }
+
+    let mut conn = get_redis_connection(&state, &instance).await?;
+
+    let result = match evalsha(&mut conn, &script.sha1, &keys, &args).await {
+        Err(e) if e.kind() == redis::ErrorKind::NoScriptError => {
+            load(&mut conn, &script).await?;
+            evalsha(&mut conn, &script.sha1, &keys, &args).await
+        }
+        result => result,
+    }
+    .map_err(|e| command_failed("EVALSHA", e))?;
+
+    Ok(Json(RedisResponse {
+        result: redis_value_to_json(result),
+    }))
+}
+
+async fn evalsha(
+    conn: &mut BackendConnection,
+    sha1: &str,
+    keys: &[String],
+    args: &[String],
+) -> redis::RedisResult<redis::Value> {
+    redis::cmd("EVALSHA")
+        .arg(sha1)
+        .arg(keys.len())
+        .arg(keys)
+        .arg(args)
+        .query_async(conn)
+        .await
+}
+
+/// Load a script into Redis' script cache
+async fn load(conn: &mut BackendConnection, script: &RegisteredScript) -> Result<(), ErrorResponse> {
+    info!("Loading script {} into instance {}", script.name, script.instance_id);
+
+    let sha1: String = redis::cmd("SCRIPT")
+        .arg("LOAD")
+        .arg(&script.source)
+        .query_async(conn)
+        .await
+        .map_err(|e| command_failed("SCRIPT LOAD", e))?;
+
+    // Only possible if the stored hash does not match the stored source
+    if sha1 != script.sha1 {
+        error!(
+            "Script {} of instance {} loaded as {} but is stored as {}",
+            script.name, script.instance_id, sha1, script.sha1
+        );
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({"error": "Script hash mismatch"})),
+        ));
+    }
+    Ok(())
+}
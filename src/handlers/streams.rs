@@ -0,0 +1,348 @@
+// Redis Streams over HTTP, including consumer groups with long-poll reads
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use tokio::time::timeout;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::error::{redis_error_message, AppError};
+use crate::handlers::redis::{
+    authenticate_request, authorize_instance_command, command_failed, get_dedicated_connection,
+    get_redis_connection, redis_value_to_json, unexpected_reply, ErrorResponse,
+    RedisResponse,
+};
+use crate::middleware::AppState;
+use crate::services::{blocking, stream};
+
+/// Default and largest number of entries returned by a single read
+const DEFAULT_COUNT: u64 = 100;
+const MAX_COUNT: u64 = 1000;
+
+/// Append request body
+#[derive(Debug, Deserialize)]
+pub struct AppendRequest {
+    pub fields: BTreeMap<String, String>,
+    /// Entry id, generated by Redis when omitted
+    #[serde(default = "auto_id")]
+    pub id: String,
+    /// Trim the stream to about this many entries
+    pub maxlen: Option<u64>,
+}
+
+/// Consumer group creation request body
+#[derive(Debug, Deserialize)]
+pub struct CreateGroupRequest {
+    pub group: String,
+    /// Deliver entries after this id; `$` means only entries added from now on
+    #[serde(default = "last_id")]
+    pub start_id: String,
+    /// Create the stream if it does not exist yet
+    #[serde(default = "default_true")]
+    pub mkstream: bool,
+}
+
+/// Consumer group read request body
+#[derive(Debug, Deserialize)]
+pub struct GroupReadRequest {
+    pub consumer: String,
+    #[serde(default = "default_count")]
+    pub count: u64,
+    /// Wait up to this long for new entries (capped below the request timeout); 0 returns immediately
+    #[serde(default)]
+    pub block_ms: u64,
+    /// `>` for new entries, or an id to re-read this consumer's pending entries after it
+    #[serde(default = "new_entries_id")]
+    pub id: String,
+}
+
+/// Acknowledge request body
+#[derive(Debug, Deserialize)]
+pub struct AckRequest {
+    pub ids: Vec<String>,
+}
+
+/// Claim request body
+#[derive(Debug, Deserialize)]
+pub struct ClaimRequest {
+    pub consumer: String,
+    /// Only claim entries that have been pending for at least this long
+    pub min_idle_ms: u64,
+    /// Where to start scanning the pending list; pass back `next_id` to continue
+    #[serde(default = "first_id")]
+    pub start: String,
+    #[serde(default = "default_count")]
+    pub count: u64,
+}
+
+fn auto_id() -> String {
+    "*".to_string()
+}
+
+fn last_id() -> String {
+    "$".to_string()
+}
+
+fn new_entries_id() -> String {
+    ">".to_string()
+}
+
+fn first_id() -> String {
+    "0-0".to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_count() -> u64 {
+    DEFAULT_COUNT
+}
+
+fn check_count(count: u64) -> Result<u64, ErrorResponse> {
+    if count == 0 || count > MAX_COUNT {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": format!("count must be between 1 and {}", MAX_COUNT)})),
+        ));
+    }
+    Ok(count)
+}
+
+/// Map consumer group errors to client errors, anything else to a failed command
+fn stream_error(command: &str, e: redis::RedisError) -> ErrorResponse {
+    match e.code() {
+        Some("BUSYGROUP") => AppError::Conflict(redis_error_message(&e)).into(),
+        Some("NOGROUP") => AppError::NotFound(redis_error_message(&e)).into(),
+        _ => command_failed(command, e),
+    }
+}
+
+/// Append an entry: `POST /redis/:instance_id/streams/:key`
+///
+/// Returns the id of the new entry.
+pub async fn handle_stream_append(
+    State(state): State<Arc<AppState>>,
+    Path((instance_id, key)): Path<(Uuid, String)>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    Json(request): Json<AppendRequest>,
+) -> Result<Json<RedisResponse>, ErrorResponse> {
+    if request.fields.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": "At least one field is required"})),
+        ));
+    }
+
+    let (instance, claims) = authenticate_request(&state, &headers, query, instance_id).await?;
+    let command = authorize_instance_command(&state, &instance, &claims, "XADD").await?;
+    let mut conn = get_redis_connection(&state, &instance).await?;
+
+    let mut cmd = redis::cmd(&command);
+    cmd.arg(&key);
+    if let Some(maxlen) = request.maxlen {
+        cmd.arg("MAXLEN").arg("~").arg(maxlen);
+    }
+    cmd.arg(&request.id);
+    for (field, value) in &request.fields {
+        cmd.arg(field).arg(value);
+    }
+
+    let id: String = cmd
+        .query_async(&mut conn)
+        .await
+        .map_err(|e| command_failed("XADD", e))?;
+
+    Ok(Json(RedisResponse { result: json!({ "id": id }) }))
+}
+
+/// Read a range of entries: `GET /redis/:instance_id/streams/:key?start=-&end=+&count=100`
+pub async fn handle_stream_range(
+    State(state): State<Arc<AppState>>,
+    Path((instance_id, key)): Path<(Uuid, String)>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Result<Json<RedisResponse>, ErrorResponse> {
+    let start = query.get("start").cloned().unwrap_or_else(|| "-".to_string());
+    let end = query.get("end").cloned().unwrap_or_else(|| "+".to_string());
+    let count = match query.get("count") {
+        Some(count) => check_count(count.parse().map_err(|_| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(json!({"error": "Invalid count parameter"})),
+            )
+        })?)?,
+        None => DEFAULT_COUNT,
+    };
+
+    let (instance, claims) = authenticate_request(&state, &headers, query, instance_id).await?;
+    let command = authorize_instance_command(&state, &instance, &claims, "XRANGE").await?;
+    let mut conn = get_redis_connection(&state, &instance).await?;
+
+    let reply: redis::Value = redis::cmd(&command)
+        .arg(&key)
+        .arg(&start)
+        .arg(&end)
+        .arg("COUNT")
+        .arg(count)
+        .query_async(&mut conn)
+        .await
+        .map_err(|e| command_failed("XRANGE", e))?;
+
+    let entries = stream::entries(&reply).ok_or_else(unexpected_reply)?;
+
+    Ok(Json(RedisResponse {
+        result: serde_json::to_value(entries).unwrap_or(Value::Null),
+    }))
+}
+
+/// Create a consumer group: `POST /redis/:instance_id/streams/:key/groups`
+///
+/// Returns 409 if the group already exists.
+pub async fn handle_create_group(
+    State(state): State<Arc<AppState>>,
+    Path((instance_id, key)): Path<(Uuid, String)>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    Json(request): Json<CreateGroupRequest>,
+) -> Result<Json<RedisResponse>, ErrorResponse> {
+    let (instance, claims) = authenticate_request(&state, &headers, query, instance_id).await?;
+    let command = authorize_instance_command(&state, &instance, &claims, "XGROUP").await?;
+    let mut conn = get_redis_connection(&state, &instance).await?;
+
+    let mut cmd = redis::cmd(&command);
+    cmd.arg("CREATE").arg(&key).arg(&request.group).arg(&request.start_id);
+    if request.mkstream {
+        cmd.arg("MKSTREAM");
+    }
+
+    let reply: redis::Value = cmd
+        .query_async(&mut conn)
+        .await
+        .map_err(|e| stream_error("XGROUP CREATE", e))?;
+
+    Ok(Json(RedisResponse {
+        result: redis_value_to_json(reply),
+    }))
+}
+
+/// Read as a consumer of a group: `POST /redis/:instance_id/streams/:key/groups/:group/read`
+///
+/// With `block_ms` the request is held open until entries arrive or the time runs out, in
+/// which case the result is an empty list. Blocking reads use a dedicated connection so they
+/// don't stall other requests multiplexed on the instance's shared connection.
+pub async fn handle_group_read(
+    State(state): State<Arc<AppState>>,
+    Path((instance_id, key, group)): Path<(Uuid, String, String)>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    Json(request): Json<GroupReadRequest>,
+) -> Result<Json<RedisResponse>, ErrorResponse> {
+    let count = check_count(request.count)?;
+    let block = stream::clamp_block(request.block_ms, blocking::max_block(state.request_timeout));
+
+    let (instance, claims) = authenticate_request(&state, &headers, query, instance_id).await?;
+    let command = authorize_instance_command(&state, &instance, &claims, "XREADGROUP").await?;
+
+    let mut cmd = redis::cmd(&command);
+    cmd.arg("GROUP").arg(&group).arg(&request.consumer).arg("COUNT").arg(count);
+    if !block.is_zero() {
+        cmd.arg("BLOCK").arg(block.as_millis() as u64);
+    }
+    cmd.arg("STREAMS").arg(&key).arg(&request.id);
+
+    let reply: redis::Value = if block.is_zero() {
+        let mut conn = get_redis_connection(&state, &instance).await?;
+        cmd.query_async(&mut conn)
+            .await
+            .map_err(|e| stream_error("XREADGROUP", e))?
+    } else {
+        let mut conn = get_dedicated_connection(&state, &instance).await?;
+        timeout(state.request_timeout, cmd.query_async::<_, redis::Value>(&mut conn))
+            .await
+            .map_err(|_| {
+                error!("XREADGROUP on instance {} did not return in time", instance_id);
+                ErrorResponse::from(AppError::Timeout("Redis did not respond in time".to_string()))
+            })?
+            .map_err(|e| stream_error("XREADGROUP", e))?
+    };
+
+    let entries = stream::read_entries(&reply).ok_or_else(unexpected_reply)?;
+
+    Ok(Json(RedisResponse {
+        result: serde_json::to_value(entries).unwrap_or(Value::Null),
+    }))
+}
+
+/// Acknowledge entries: `POST /redis/:instance_id/streams/:key/groups/:group/ack`
+///
+/// Returns the number of entries that were pending and are now acknowledged.
+pub async fn handle_group_ack(
+    State(state): State<Arc<AppState>>,
+    Path((instance_id, key, group)): Path<(Uuid, String, String)>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    Json(request): Json<AckRequest>,
+) -> Result<Json<RedisResponse>, ErrorResponse> {
+    if request.ids.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": "At least one id is required"})),
+        ));
+    }
+
+    let (instance, claims) = authenticate_request(&state, &headers, query, instance_id).await?;
+    let command = authorize_instance_command(&state, &instance, &claims, "XACK").await?;
+    let mut conn = get_redis_connection(&state, &instance).await?;
+
+    let acknowledged: i64 = redis::cmd(&command)
+        .arg(&key)
+        .arg(&group)
+        .arg(&request.ids)
+        .query_async(&mut conn)
+        .await
+        .map_err(|e| stream_error("XACK", e))?;
+
+    Ok(Json(RedisResponse { result: json!(acknowledged) }))
+}
+
+/// Claim entries other consumers left pending: `POST /redis/:instance_id/streams/:key/groups/:group/claim`
+pub async fn handle_group_claim(
+    State(state): State<Arc<AppState>>,
+    Path((instance_id, key, group)): Path<(Uuid, String, String)>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    Json(request): Json<ClaimRequest>,
+) -> Result<Json<RedisResponse>, ErrorResponse> {
+    let count = check_count(request.count)?;
+
+    let (instance, claims) = authenticate_request(&state, &headers, query, instance_id).await?;
+    let command = authorize_instance_command(&state, &instance, &claims, "XAUTOCLAIM").await?;
+    let mut conn = get_redis_connection(&state, &instance).await?;
+
+    let reply: redis::Value = redis::cmd(&command)
+        .arg(&key)
+        .arg(&group)
+        .arg(&request.consumer)
+        .arg(request.min_idle_ms)
+        .arg(&request.start)
+        .arg("COUNT")
+        .arg(count)
+        .query_async(&mut conn)
+        .await
+        .map_err(|e| stream_error("XAUTOCLAIM", e))?;
+
+    let claimed = stream::claimed_entries(&reply).ok_or_else(unexpected_reply)?;
+
+    Ok(Json(RedisResponse {
+        result: serde_json::to_value(claimed).unwrap_or(Value::Null),
+    }))
+}
@@ -0,0 +1,386 @@
+// Redis commands and Pub/Sub over a WebSocket
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Path, Query, State,
+    },
+    http::{HeaderMap, StatusCode},
+    response::Response,
+    Json,
+};
+use futures::{SinkExt, StreamExt};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use redis::aio::ConnectionLike;
+use redis::{Cmd, Msg};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::task::JoinHandle;
+use tokio::time::{interval_at, Instant};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::auth::ApiKeyClaims;
+use crate::error::redis_error_message;
+use crate::handlers::pubsub::{message_json, open_subscriber, SubscriptionKind};
+use crate::handlers::redis::{
+    authenticate_and_get_instance, authorize_instance_command, ensure_api_key_active, error_message,
+    extract_api_key, get_redis_connection, parse_command, redis_value_to_json, ErrorResponse,
+};
+use crate::middleware::AppState;
+use crate::models::RedisInstance;
+use crate::services::blocking;
+use crate::services::redis_backend::BackendConnection;
+use crate::services::redis_pool::RedisEndpoint;
+
+/// Maximum number of channels and patterns a single socket may subscribe to
+const MAX_SUBSCRIPTIONS: usize = 32;
+
+/// Published messages that may wait for a slow client before its subscriptions are dropped
+const MAX_PENDING_PUSHES: usize = 256;
+
+/// How often an idle session checks that its API key is still valid
+const KEY_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A command frame sent by the client: `{"id": 1, "command": ["GET", "key"]}`
+#[derive(Debug, Deserialize)]
+struct CommandFrame {
+    /// Client-chosen correlation id, echoed back in the reply
+    #[serde(default)]
+    id: Value,
+    command: Vec<Value>,
+}
+
+/// Upgrade to a WebSocket that accepts JSON command frames and pushes Pub/Sub messages
+pub async fn handle_websocket(
+    State(state): State<Arc<AppState>>,
+    Path(instance_id): Path<Uuid>,
+    headers: HeaderMap,
+    Query(query): Query<HashMap<String, String>>,
+    ws: WebSocketUpgrade,
+) -> Result<Response, ErrorResponse> {
+    let api_key = extract_api_key(&headers, &Query(query)).ok_or_else(|| {
+        (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({"error": "Missing API key"})),
+        )
+    })?;
+
+    let (instance, claims) = authenticate_and_get_instance(&state, &api_key, instance_id).await?;
+    let conn = get_redis_connection(&state, &instance).await?;
+
+    info!("WebSocket session opened for instance {} with API key {}", instance_id, claims.key_prefix);
+
+    Ok(ws.on_upgrade(move |socket| async move {
+        let (pushes, push_rx) = mpsc::channel(MAX_PENDING_PUSHES);
+        let session = Session {
+            state,
+            instance,
+            claims,
+            conn,
+            pushes,
+            subscriptions: HashSet::new(),
+            subscriber: None,
+        };
+        session.run(socket, push_rx).await;
+    }))
+}
+
+/// The Redis connection a session's subscriptions live on, and the task reading it
+struct Subscriber {
+    /// SUBSCRIBE/UNSUBSCRIBE commands for the task to write
+    commands: mpsc::Sender<Cmd>,
+    /// Ends with the reason the subscriptions stopped
+    task: JoinHandle<&'static str>,
+}
+
+/// What woke up a session
+enum Event {
+    Frame(Option<Result<Message, axum::Error>>),
+    Push(Value),
+    SubscriberClosed(&'static str),
+    KeyCheck,
+}
+
+/// State of one WebSocket connection
+struct Session {
+    state: Arc<AppState>,
+    instance: RedisInstance,
+    claims: ApiKeyClaims,
+    /// Pooled connection used for regular commands
+    conn: BackendConnection,
+    /// Messages forwarded from the subscriber task to the socket
+    pushes: mpsc::Sender<Value>,
+    /// Subscribed channels and patterns
+    subscriptions: HashSet<(SubscriptionKind, String)>,
+    /// Opened on the first subscription and shared by all of them
+    subscriber: Option<Subscriber>,
+}
+
+impl Session {
+    async fn run(mut self, socket: WebSocket, mut push_rx: mpsc::Receiver<Value>) {
+        let (mut sender, mut receiver) = socket.split();
+        // Sessions that only receive messages send no frames to check the key on
+        let mut key_checks = interval_at(Instant::now() + KEY_CHECK_INTERVAL, KEY_CHECK_INTERVAL);
+
+        loop {
+            let event = tokio::select! {
+                incoming = receiver.next() => Event::Frame(incoming),
+                Some(push) = push_rx.recv() => Event::Push(push),
+                reason = subscriber_closed(&mut self.subscriber) => Event::SubscriberClosed(reason),
+                _ = key_checks.tick() => Event::KeyCheck,
+            };
+
+            // A key that expired or was revoked since the socket opened ends the session
+            if matches!(event, Event::Frame(Some(Ok(Message::Text(_)))) | Event::KeyCheck) {
+                if let Err(e) = ensure_api_key_active(&self.state, &self.claims).await {
+                    info!(
+                        "Closing WebSocket session on instance {}: API key {} is no longer valid",
+                        self.instance.id, self.claims.key_prefix
+                    );
+                    let closed = json!({"type": "session_closed", "reason": error_message(e)});
+                    let _ = sender.send(Message::Text(closed.to_string())).await;
+                    break;
+                }
+            }
+
+            let outgoing = match event {
+                Event::Frame(Some(Ok(Message::Text(text)))) => self.handle_frame(&text).await,
+                Event::Frame(Some(Ok(Message::Binary(_)))) => {
+                    json!({"id": null, "error": "Binary frames are not supported"})
+                }
+                // Ping/pong frames are answered by axum
+                Event::Frame(Some(Ok(Message::Ping(_)))) | Event::Frame(Some(Ok(Message::Pong(_)))) => continue,
+                Event::Frame(Some(Ok(Message::Close(_)))) | Event::Frame(None) => break,
+                Event::Frame(Some(Err(e))) => {
+                    warn!("WebSocket error on instance {}: {}", self.instance.id, e);
+                    break;
+                }
+                Event::Push(push) => push,
+                Event::SubscriberClosed(reason) => {
+                    warn!("WebSocket subscriptions on instance {} closed: {}", self.instance.id, reason);
+                    self.subscriber = None;
+                    self.subscriptions.clear();
+                    json!({"type": "subscription_closed", "reason": reason})
+                }
+                Event::KeyCheck => continue,
+            };
+
+            if sender.send(Message::Text(outgoing.to_string())).await.is_err() {
+                break;
+            }
+        }
+
+        // Stopping the subscriber task closes its Redis connection
+        if let Some(subscriber) = self.subscriber.take() {
+            subscriber.task.abort();
+        }
+        info!("WebSocket session closed for instance {}", self.instance.id);
+    }
+    /// Run one command frame and build its reply
+    async fn handle_frame(&mut self, text: &str) -> Value {
+        let frame: CommandFrame = match serde_json::from_str(text) {
+            Ok(frame) => frame,
+            Err(e) => return json!({"id": null, "error": format!("Invalid frame: {}", e)}),
+        };
+
+        match self.execute(&frame.command).await {
+            Ok(result) => json!({"id": frame.id, "result": result}),
+            Err(error) => json!({"id": frame.id, "error": error}),
+        }
+    }
+
+    async fn execute(&mut self, payload: &[Value]) -> Result<Value, String> {
+        let (command, args) = parse_command(payload).map_err(error_message)?;
+        let command = authorize_instance_command(&self.state, &self.instance, &self.claims, &command)
+            .await
+            .map_err(error_message)?;
+
+        if self.state.rate_limit_enabled {
+            let decision = self
+                .state
+                .rate_limiter
+                .check_api_key_detailed(&self.claims.api_key_id.to_string(), None)
+                .await;
+            if !decision.allowed {
+                return Err(format!(
+                    "Rate limit exceeded, retry in {} seconds",
+                    decision.retry_after_secs()
+                ));
+            }
+        }
+
+        match command.as_str() {
+            "SUBSCRIBE" => self.subscribe(SubscriptionKind::Channel, args).await,
+            "PSUBSCRIBE" => self.subscribe(SubscriptionKind::Pattern, args).await,
+            "UNSUBSCRIBE" => Ok(self.unsubscribe(SubscriptionKind::Channel, args).await),
+            "PUNSUBSCRIBE" => Ok(self.unsubscribe(SubscriptionKind::Pattern, args).await),
+            // The command connection is shared with other clients
+            "MULTI" | "EXEC" | "DISCARD" | "WATCH" | "UNWATCH" => Err(format!(
+                "{} is not supported over WebSocket, use the multi-exec endpoint",
+                command
+            )),
+            _ if blocking::is_blocking(&command.to_uppercase()) => Err(format!(
+                "{} is not supported over WebSocket, send it to the command endpoint",
+                command
+            )),
+            _ => {
+                let mut args = args;
+                RedisEndpoint::namespace_channels(&self.instance, &command, &mut args);
+                redis::cmd(&command)
+                    .arg(args)
+                    .query_async::<_, redis::Value>(&mut self.conn)
+                    .await
+                    .map(redis_value_to_json)
+                    .map_err(|e| redis_error_message(&e))
+            }
+        }
+    }
+
+    /// Subscribe to channels or patterns, returning the number of active subscriptions
+    async fn subscribe(&mut self, kind: SubscriptionKind, targets: Vec<String>) -> Result<Value, String> {
+        if targets.is_empty() {
+            return Err(format!(
+                "ERR wrong number of arguments for '{}' command",
+                kind.command().to_lowercase()
+            ));
+        }
+
+        let mut added: Vec<String> = Vec::new();
+        for target in targets {
+            if !self.subscriptions.contains(&(kind, target.clone())) && !added.contains(&target) {
+                added.push(target);
+            }
+        }
+        if added.is_empty() {
+            return Ok(json!(self.subscriptions.len()));
+        }
+        if self.subscriptions.len() + added.len() > MAX_SUBSCRIPTIONS {
+            return Err(format!("Too many subscriptions (limit is {})", MAX_SUBSCRIPTIONS));
+        }
+
+        let commands = self.subscriber().await?;
+        let mut command = redis::cmd(kind.command());
+        command.arg(self.namespaced(kind.command(), &added));
+        commands
+            .send(command)
+            .await
+            .map_err(|_| "Subscription connection closed".to_string())?;
+
+        info!("WebSocket subscribed ({} {:?}) on instance {}", kind.command(), added, self.instance.id);
+        self.subscriptions.extend(added.into_iter().map(|target| (kind, target)));
+        Ok(json!(self.subscriptions.len()))
+    }
+
+    /// Drop subscriptions (all of the given kind when no targets are given)
+    async fn unsubscribe(&mut self, kind: SubscriptionKind, targets: Vec<String>) -> Value {
+        let removed: Vec<String> = if targets.is_empty() {
+            self.subscriptions
+                .iter()
+                .filter(|(k, _)| *k == kind)
+                .map(|(_, target)| target.clone())
+                .collect()
+        } else {
+            targets
+                .into_iter()
+                .filter(|target| self.subscriptions.contains(&(kind, target.clone())))
+                .collect()
+        };
+
+        if let (Some(subscriber), false) = (&self.subscriber, removed.is_empty()) {
+            let mut command = redis::cmd(kind.unsubscribe_command());
+            command.arg(self.namespaced(kind.unsubscribe_command(), &removed));
+            // A closed subscriber is reported by the run loop
+            let _ = subscriber.commands.send(command).await;
+        }
+        for target in removed {
+            self.subscriptions.remove(&(kind, target));
+        }
+
+        json!(self.subscriptions.len())
+    }
+
+    /// Channels or patterns as sent to Redis, in the instance's channel namespace
+    fn namespaced(&self, command: &str, targets: &[String]) -> Vec<String> {
+        let mut targets = targets.to_vec();
+        RedisEndpoint::namespace_channels(&self.instance, command, &mut targets);
+        targets
+    }
+
+    /// Commands channel of the session's subscriber, opening its connection if needed
+    async fn subscriber(&mut self) -> Result<mpsc::Sender<Cmd>, String> {
+        if let Some(subscriber) = &self.subscriber {
+            return Ok(subscriber.commands.clone());
+        }
+
+        let conn = open_subscriber(&self.state, &self.instance).await.map_err(error_message)?;
+        let (commands, command_rx) = mpsc::channel(MAX_SUBSCRIPTIONS);
+        let namespace = RedisEndpoint::channel_namespace(&self.instance);
+        let task = tokio::spawn(forward_messages(conn, command_rx, self.pushes.clone(), namespace));
+        self.subscriber = Some(Subscriber {
+            commands: commands.clone(),
+            task,
+        });
+        Ok(commands)
+    }
+}
+
+/// Wait for the session's subscriber task to end, or forever if it has none
+async fn subscriber_closed(subscriber: &mut Option<Subscriber>) -> &'static str {
+    match subscriber {
+        Some(subscriber) => (&mut subscriber.task).await.unwrap_or("Subscription task failed"),
+        None => std::future::pending().await,
+    }
+}
+
+/// Write subscription changes to a subscriber connection and push the messages it receives
+///
+/// Both directions share the connection without splitting it: commands are written with
+/// no replies expected, and replies (subscribe confirmations and messages alike) are read
+/// one at a time. A client that falls `MAX_PENDING_PUSHES` messages behind loses its
+/// subscriptions rather than letting them queue up without bound.
+async fn forward_messages(
+    mut conn: BackendConnection,
+    mut commands: mpsc::Receiver<Cmd>,
+    pushes: mpsc::Sender<Value>,
+    namespace: Option<String>,
+) -> &'static str {
+    let next_reply = redis::pipe();
+
+    loop {
+        let reply = tokio::select! {
+            command = commands.recv() => {
+                let Some(command) = command else {
+                    return "Session closed";
+                };
+                let mut pipe = redis::pipe();
+                pipe.add_command(command);
+                match conn.req_packed_commands(&pipe, 0, 0).await {
+                    Ok(_) => continue,
+                    Err(e) => Err(e),
+                }
+            }
+            reply = conn.req_packed_commands(&next_reply, 0, 1) => reply,
+        };
+
+        match reply {
+            Ok(values) => {
+                for msg in values.iter().filter_map(Msg::from_value) {
+                    let mut push = message_json(&msg, namespace.as_deref());
+                    push["type"] = json!("message");
+                    match pushes.try_send(push) {
+                        Ok(()) => {}
+                        Err(TrySendError::Full(_)) => return "Client is not reading messages fast enough",
+                        Err(TrySendError::Closed(_)) => return "Session closed",
+                    }
+                }
+            }
+            Err(e) if e.is_io_error() || e.is_connection_dropped() => return "Redis connection lost",
+            // e.g. a subscribe the server refused; the connection itself is still usable
+            Err(e) => warn!("Subscription command failed: {}", redis_error_message(&e)),
+        }
+    }
+}
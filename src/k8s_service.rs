@@ -2,15 +2,18 @@
 
 use k8s_openapi::api::apps::v1::{Deployment, DeploymentSpec};
 use k8s_openapi::api::core::v1::{
-    Container, ContainerPort, EnvVar, PodSpec, PodTemplateSpec, Secret, Service, ServicePort, ServiceSpec,
+    Container, ContainerPort, EnvVar, ExecAction, Lifecycle, LifecycleHandler, PodSpec, PodTemplateSpec, Secret, Service, ServicePort, ServiceSpec,
 };
 use k8s_openapi::api::networking::v1::{Ingress, IngressBackend, IngressRule, IngressServiceBackend, IngressSpec, HTTPIngressPath, HTTPIngressRuleValue};
 use k8s_openapi::apimachinery::pkg::apis::meta::v1::{LabelSelector, ObjectMeta};
 use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
+use kube::api::{Patch, PatchParams};
 use kube::{Api, Client, Error as KubeError};
 use std::collections::BTreeMap;
 use uuid::Uuid;
 
+use crate::services::cluster::Topology;
+
 pub struct K8sRedisService {
     client: Client,
 }
@@ -26,6 +29,7 @@ pub struct RedisDeploymentConfig {
     pub max_memory: i64,
     pub redis_password: String,
     pub port: i32,
+    pub topology: Topology,
 }
 
 #[derive(Debug)]
@@ -109,6 +113,46 @@ impl K8sRedisService {
         Ok(())
     }
 
+    /// Read the password stored in an instance's secret
+    pub async fn get_redis_password(
+        &self,
+        namespace: &str,
+        slug: &str,
+    ) -> Result<Option<String>, KubeError> {
+        let secret_name = format!("redis-{}-secret", slug);
+        let secrets: Api<Secret> = Api::namespaced(self.client.clone(), namespace);
+
+        let password = secrets
+            .get_opt(&secret_name)
+            .await?
+            .and_then(|secret| secret.data)
+            .and_then(|mut data| data.remove("redis-password"))
+            .and_then(|value| String::from_utf8(value.0).ok());
+
+        Ok(password)
+    }
+
+    /// Replace the password stored in an instance's secret
+    ///
+    /// The running server is not affected; the new value is picked up when the pod restarts.
+    pub async fn update_redis_password(
+        &self,
+        namespace: &str,
+        slug: &str,
+        password: &str,
+    ) -> Result<(), KubeError> {
+        let secret_name = format!("redis-{}-secret", slug);
+        let secrets: Api<Secret> = Api::namespaced(self.client.clone(), namespace);
+
+        let patch = serde_json::json!({
+            "stringData": { "redis-password": password }
+        });
+        secrets
+            .patch(&secret_name, &PatchParams::default(), &Patch::Merge(&patch))
+            .await?;
+        Ok(())
+    }
+
     /// Check deployment status
     pub async fn get_deployment_status(
         &self,
@@ -259,15 +303,8 @@ impl K8sRedisService {
                                     ..Default::default()
                                 },
                             ]),
-                            command: Some(vec![
-                                "redis-server".to_string(),
-                                "--requirepass".to_string(),
-                                "$(REDIS_PASSWORD)".to_string(),
-                                "--maxmemory".to_string(),
-                                format!("{}b", config.max_memory),
-                                "--maxmemory-policy".to_string(),
-                                "allkeys-lru".to_string(),
-                            ]),
+                            command: Some(redis_server_command(config)),
+                            lifecycle: cluster_lifecycle(config),
                             resources: Some(k8s_openapi::api::core::v1::ResourceRequirements {
                                 limits: Some({
                                     let mut limits = BTreeMap::new();
@@ -392,4 +429,47 @@ impl K8sRedisService {
         ingresses.create(&Default::default(), &ingress).await?;
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+fn redis_server_command(config: &RedisDeploymentConfig) -> Vec<String> {
+    let mut command = vec![
+        "redis-server".to_string(),
+        "--requirepass".to_string(),
+        "$(REDIS_PASSWORD)".to_string(),
+        "--maxmemory".to_string(),
+        format!("{}b", config.max_memory),
+        "--maxmemory-policy".to_string(),
+        "allkeys-lru".to_string(),
+    ];
+    if config.topology == Topology::Cluster {
+        command.extend([
+            "--cluster-enabled".to_string(),
+            "yes".to_string(),
+            "--masterauth".to_string(),
+            "$(REDIS_PASSWORD)".to_string(),
+        ]);
+    }
+    command
+}
+
+/// Cluster instances start as a single shard owning every slot, so cluster clients can use
+/// them right away
+fn cluster_lifecycle(config: &RedisDeploymentConfig) -> Option<Lifecycle> {
+    if config.topology != Topology::Cluster {
+        return None;
+    }
+
+    // redis-server listens on its default port inside the pod
+    let assign_slots = "until redis-cli -a \"$REDIS_PASSWORD\" --no-auth-warning PING; do sleep 1; done; \
+        redis-cli -a \"$REDIS_PASSWORD\" --no-auth-warning CLUSTER ADDSLOTSRANGE 0 16383 || true"
+        .to_string();
+    Some(Lifecycle {
+        post_start: Some(LifecycleHandler {
+            exec: Some(ExecAction {
+                command: Some(vec!["sh".to_string(), "-c".to_string(), assign_slots]),
+            }),
+            ..Default::default()
+        }),
+        ..Default::default()
+    })
+}
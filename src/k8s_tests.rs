@@ -5,6 +5,7 @@ use uuid::Uuid;
 mod tests {
     use super::*;
     use crate::k8s_service::{K8sRedisService, RedisDeploymentConfig, K8sDeploymentResult};
+    use crate::services::cluster::Topology;
 
     #[tokio::test]
     async fn test_k8s_service_initialization() {
@@ -44,6 +45,7 @@ mod tests {
             max_memory: 1024 * 1024 * 100, // 100MB
             redis_password: "test-password".to_string(),
             port: 6379,
+            topology: Topology::Standalone,
         };
 
         assert_eq!(config.name, "test-redis");
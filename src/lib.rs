@@ -8,6 +8,8 @@ pub mod middleware;
 pub mod handlers;
 pub mod api_models;
 pub mod k8s_service;
+#[cfg(test)]
+mod k8s_tests;
 pub mod error;
 pub mod monitoring;
 
@@ -6,7 +6,7 @@ use axum::{
     Router,
 };
 use serde_json::json;
-use sqlx::{PgPool, Row};
+use sqlx::PgPool;
 use std::sync::Arc;
 use tokio::net::TcpListener;
 use tower_http::cors::CorsLayer;
@@ -14,19 +14,8 @@ use tower_http::trace::TraceLayer;
 use tracing::{info, warn, debug};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
 
-mod api_models;
-mod auth;
-mod handlers;
-pub mod k8s_service;
-#[cfg(test)]
-mod k8s_tests;
-mod middleware;
-mod models;
-pub mod services;
-mod config;
-mod monitoring;
-
-use config::Config;
+use redisgate::config::Config;
+use redisgate::{handlers, middleware, services};
 
 #[tokio::main]
 async fn main() {
@@ -113,9 +102,27 @@ async fn main() {
     let app_state = Arc::new(middleware::AppState::with_config(
         pool.clone(),
         &jwt_secret,
-        config.rate_limit.default_requests_per_second,
+        &config,
     ));
 
+    // Move instance passwords off retired master keys
+    match app_state.credentials.reencrypt_stale().await {
+        Ok(0) => {}
+        Ok(count) => info!("Re-encrypted {} instance passwords with the current master key", count),
+        Err(e) => warn!("Failed to re-encrypt instance passwords: {}", e),
+    }
+
+    // Native RESP listener for standard Redis clients
+    if config.proxy.enabled {
+        let proxy_state = app_state.clone();
+        let proxy_config = config.proxy.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handlers::resp_proxy::serve(proxy_state, proxy_config).await {
+                tracing::error!("RESP proxy stopped: {}", e);
+            }
+        });
+    }
+
     // Build protected API routes with auth middleware
     let protected_api = Router::new()
         .route("/organizations", post(handlers::organizations::create_organization))
@@ -133,7 +140,15 @@ async fn main() {
         .route("/organizations/:org_id/redis-instances", get(handlers::redis_instances::list_redis_instances))
         .route("/organizations/:org_id/redis-instances/:instance_id", get(handlers::redis_instances::get_redis_instance))
         .route("/organizations/:org_id/redis-instances/:instance_id/status", put(handlers::redis_instances::update_redis_instance_status))
+        .route("/organizations/:org_id/redis-instances/:instance_id/sentinel", put(handlers::redis_instances::update_redis_instance_sentinel))
         .route("/organizations/:org_id/redis-instances/:instance_id", delete(handlers::redis_instances::delete_redis_instance))
+        .route("/organizations/:org_id/redis-instances/:instance_id/credentials", get(handlers::redis_instances::get_redis_instance_credentials))
+        .route("/organizations/:org_id/redis-instances/:instance_id/rotate-password", post(handlers::redis_instances::rotate_redis_instance_password))
+        .route("/organizations/:org_id/redis-instances/:instance_id/command-policy", get(handlers::redis_instances::get_redis_instance_command_policy))
+        .route("/organizations/:org_id/redis-instances/:instance_id/command-policy", put(handlers::redis_instances::update_redis_instance_command_policy))
+        .route("/organizations/:org_id/redis-instances/:instance_id/scripts", get(handlers::redis_instances::list_redis_instance_scripts))
+        .route("/organizations/:org_id/redis-instances/:instance_id/scripts/:name", put(handlers::redis_instances::put_redis_instance_script))
+        .route("/organizations/:org_id/redis-instances/:instance_id/scripts/:name", delete(handlers::redis_instances::delete_redis_instance_script))
         .layer(axum_middleware::from_fn_with_state(
             app_state.clone(),
             middleware::auth_middleware,
@@ -148,6 +163,66 @@ async fn main() {
             middleware::auth_middleware,
         ));
 
+    // Redis HTTP API routes (uses API key authentication)
+    let redis_api = Router::new()
+        .route("/redis/:instance_id/ping", get(handlers::redis::handle_ping).post(handlers::redis::handle_path_command))
+        .route("/redis/:instance_id/set/:key/:value", get(handlers::redis::handle_set).post(handlers::redis::handle_path_command))
+        .route("/redis/:instance_id/get/:key", get(handlers::redis::handle_get).post(handlers::redis::handle_path_command))
+        .route("/redis/:instance_id/del/:key", get(handlers::redis::handle_del).post(handlers::redis::handle_path_command))
+        .route("/redis/:instance_id/incr/:key", get(handlers::redis::handle_incr).post(handlers::redis::handle_path_command))
+        .route("/redis/:instance_id/hset/:key/:field/:value", get(handlers::redis::handle_hset).post(handlers::redis::handle_path_command))
+        .route("/redis/:instance_id/hget/:key/:field", get(handlers::redis::handle_hget).post(handlers::redis::handle_path_command))
+        .route("/redis/:instance_id/lpush/:key/:value", get(handlers::redis::handle_lpush).post(handlers::redis::handle_path_command))
+        .route("/redis/:instance_id/lpop/:key", get(handlers::redis::handle_lpop).post(handlers::redis::handle_path_command))
+        .route("/redis/:instance_id/expire/:key/:seconds", get(handlers::redis::handle_expire).post(handlers::redis::handle_path_command))
+        .route("/redis/:instance_id/ttl/:key", get(handlers::redis::handle_ttl).post(handlers::redis::handle_path_command))
+        .route("/redis/:instance_id/exists/:key", get(handlers::redis::handle_exists).post(handlers::redis::handle_path_command))
+        .route("/redis/:instance_id/decr/:key", get(handlers::redis::handle_decr).post(handlers::redis::handle_path_command))
+
+        // Set operations
+        .route("/redis/:instance_id/sadd/:key/:member", get(handlers::redis::handle_sadd).post(handlers::redis::handle_path_command))
+        .route("/redis/:instance_id/smembers/:key", get(handlers::redis::handle_smembers).post(handlers::redis::handle_path_command))
+        .route("/redis/:instance_id/sismember/:key/:member", get(handlers::redis::handle_sismember).post(handlers::redis::handle_path_command))
+        .route("/redis/:instance_id/srem/:key/:member", get(handlers::redis::handle_srem).post(handlers::redis::handle_path_command))
+
+        // Generic Redis command endpoint (for POST with JSON body)
+        .route("/redis/:instance_id", post(handlers::redis::handle_generic_command))
+        // Pipelined batch of commands
+        .route("/redis/:instance_id/pipeline", post(handlers::redis::handle_pipeline))
+        // Atomic MULTI/EXEC transaction with optional WATCH keys
+        .route("/redis/:instance_id/multi-exec", post(handlers::redis::handle_multi_exec))
+        // Pub/Sub streamed as Server-Sent Events
+        .route("/redis/:instance_id/subscribe/:channel", get(handlers::pubsub::handle_subscribe))
+        .route("/redis/:instance_id/psubscribe/:pattern", get(handlers::pubsub::handle_psubscribe))
+        // JSON-framed commands and Pub/Sub over a WebSocket
+        .route("/redis/:instance_id/ws", get(handlers::websocket::handle_websocket))
+        // Leaderboards over sorted sets, highest score first
+        .route("/redis/:instance_id/leaderboard/:key/top/:n", get(handlers::leaderboard::handle_leaderboard_top))
+        .route("/redis/:instance_id/leaderboard/:key/rank/:member", get(handlers::leaderboard::handle_leaderboard_rank))
+        // Key browser over SCAN
+        .route("/redis/:instance_id/keys", get(handlers::key_browser::handle_browse_keys))
+        // Registered Lua scripts, run with EVALSHA
+        .route("/redis/:instance_id/scripts/:name", post(handlers::scripts::handle_invoke_script))
+        // Streams, with consumer groups and long-poll reads
+        .route(
+            "/redis/:instance_id/streams/:key",
+            get(handlers::streams::handle_stream_range).post(handlers::streams::handle_stream_append),
+        )
+        .route("/redis/:instance_id/streams/:key/groups", post(handlers::streams::handle_create_group))
+        .route("/redis/:instance_id/streams/:key/groups/:group/read", post(handlers::streams::handle_group_read))
+        .route("/redis/:instance_id/streams/:key/groups/:group/ack", post(handlers::streams::handle_group_ack))
+        .route("/redis/:instance_id/streams/:key/groups/:group/claim", post(handlers::streams::handle_group_claim))
+
+        // Upstash-style commands in the path: /redis/:instance_id/set/foo/bar/EX/100
+        .route(
+            "/redis/:instance_id/*path",
+            get(handlers::redis::handle_path_command).post(handlers::redis::handle_path_command),
+        )
+        .route_layer(axum_middleware::from_fn_with_state(
+            app_state.clone(),
+            middleware::rate_limit_middleware,
+        ));
+
     // Build application with all routes
     let app = Router::new()
         // Frontend UI
@@ -184,32 +259,8 @@ async fn main() {
         // Protected API routes
         .nest("/api", protected_api)
 
-        // Redis HTTP API routes (uses API key authentication)
-        .route("/redis/:instance_id/ping", get(handlers::redis::handle_ping))
-        .route("/redis/:instance_id/set/:key/:value", get(handlers::redis::handle_set))
-        .route("/redis/:instance_id/get/:key", get(handlers::redis::handle_get))
-        .route("/redis/:instance_id/del/:key", get(handlers::redis::handle_del))
-        .route("/redis/:instance_id/incr/:key", get(handlers::redis::handle_incr))
-        .route("/redis/:instance_id/hset/:key/:field/:value", get(handlers::redis::handle_hset))
-        .route("/redis/:instance_id/hget/:key/:field", get(handlers::redis::handle_hget))
-        .route("/redis/:instance_id/lpush/:key/:value", get(handlers::redis::handle_lpush))
-        .route("/redis/:instance_id/lpop/:key", get(handlers::redis::handle_lpop))
-        .route("/redis/:instance_id/expire/:key/:seconds", get(handlers::redis::handle_expire))
-        .route("/redis/:instance_id/ttl/:key", get(handlers::redis::handle_ttl))
-        .route("/redis/:instance_id/exists/:key", get(handlers::redis::handle_exists))
-        .route("/redis/:instance_id/decr/:key", get(handlers::redis::handle_decr))
-
-        // Set operations
-        .route("/redis/:instance_id/sadd/:key/:member", get(handlers::redis::handle_sadd))
-        .route("/redis/:instance_id/smembers/:key", get(handlers::redis::handle_smembers))
-        .route("/redis/:instance_id/sismember/:key/:member", get(handlers::redis::handle_sismember))
-        .route("/redis/:instance_id/srem/:key/:member", get(handlers::redis::handle_srem))
-
-        // Generic Redis command endpoint (for POST with JSON body)
-        .route("/redis/:instance_id", post(handlers::redis::handle_generic_command))
-
-        // Catch-all route for debugging Redis requests
-        .route("/redis/:instance_id/*path", get(handlers::redis::handle_debug_request))
+        // Redis HTTP API routes (uses API key authentication, rate limited per key)
+        .merge(redis_api)
         .layer(CorsLayer::permissive())
         // Metrics tracking middleware (must be early in chain)
         .layer(axum_middleware::from_fn_with_state(
@@ -305,33 +356,6 @@ async fn serve_get_api_key() -> impl IntoResponse {
     Html(html)
 }
 
-async fn health_check(Extension(pool): Extension<Arc<PgPool>>) -> axum::response::Json<serde_json::Value> {
-    // Test database connection
-    let db_status = match sqlx::query("SELECT 1 as status")
-        .fetch_one(pool.as_ref())
-        .await
-    {
-        Ok(row) => {
-            let status: i32 = row.get("status");
-            if status == 1 {
-                "healthy"
-            } else {
-                "unhealthy"
-            }
-        }
-        Err(e) => {
-            warn!("Database health check failed: {}", e);
-            "unhealthy"
-        }
-    };
-
-    axum::response::Json(json!({
-        "status": "ok",
-        "database": db_status,
-        "timestamp": chrono::Utc::now()
-    }))
-}
-
 async fn version() -> axum::response::Json<serde_json::Value> {
     axum::response::Json(json!({
         "name": "redisgate",
@@ -1,15 +1,20 @@
 // Authentication middleware for protecting routes
 
 use axum::{
-    extract::{Request, State},
-    http::header::AUTHORIZATION,
+    extract::{Query, Request, State},
+    http::{header::AUTHORIZATION, HeaderMap, HeaderValue},
     middleware::Next,
-    response::Response,
+    response::{IntoResponse, Response},
 };
+use std::collections::HashMap;
 use sqlx::PgPool;
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::auth::{AuthError, JwtManager};
+use crate::config::Config;
+use crate::error::AppError;
+use crate::services::rate_limiter::RateLimitDecision;
 use crate::models::User;
 use crate::monitoring::Metrics;
 
@@ -94,27 +99,74 @@ pub struct AppState {
     pub db_pool: PgPool,
     pub jwt_manager: JwtManager,
     pub redis_pool: crate::services::redis_pool::RedisPool,
+    /// Where data-plane handlers get connections; `redis_pool` unless configured otherwise
+    pub redis_backend: Arc<dyn crate::services::redis_backend::RedisBackend>,
     pub metrics_service: Arc<crate::services::metrics::MetricsService>,
     pub rate_limiter: Arc<crate::services::rate_limiter::RateLimiter>,
+    pub rate_limit_enabled: bool,
     pub health_service: Arc<crate::services::health::HealthCheckService>,
+    pub api_key_cache: Arc<crate::services::api_key_cache::ApiKeyCache>,
+    pub credentials: Arc<crate::services::credentials::CredentialsService>,
+    pub command_policies: Arc<crate::services::command_policy::CommandPolicyService>,
+    pub scripts: Arc<crate::services::scripts::ScriptService>,
     pub metrics: Metrics,
+    /// Lifetime of API keys minted at login
+    pub api_key_expiry_days: u64,
+    /// Base URL of the gateway as seen by clients
+    pub public_url: String,
+    /// Whether instance connection URLs use TLS (`rediss://`)
+    pub instance_tls: bool,
+    /// How long a request may take; bounds blocking commands and long-poll reads
+    pub request_timeout: Duration,
 }
 
 impl AppState {
     pub fn new(db_pool: PgPool, jwt_secret: &str) -> Self {
-        // Use default rate limit if no config provided
-        Self::with_config(db_pool, jwt_secret, 100)
+        // Use default settings if no config provided
+        Self::with_config(db_pool, jwt_secret, &Config::default())
     }
 
-    pub fn with_config(db_pool: PgPool, jwt_secret: &str, rate_limit_rps: u32) -> Self {
+    pub fn with_config(db_pool: PgPool, jwt_secret: &str, config: &Config) -> Self {
+        let cipher = crate::services::credentials::CredentialCipher::from_config(&config.credentials)
+            .expect("Failed to load credentials master key");
+
+        let redis_pool = crate::services::redis_pool::RedisPool::with_config(&config.redis);
+        let redis_backend: Arc<dyn crate::services::redis_backend::RedisBackend> = match config.redis.backend {
+            crate::config::RedisBackendKind::Redis => Arc::new(redis_pool.clone()),
+            crate::config::RedisBackendKind::Memory => {
+                Arc::new(crate::services::memory_backend::MemoryBackend::new())
+            }
+        };
+
         Self {
+            credentials: Arc::new(crate::services::credentials::CredentialsService::new(
+                cipher,
+                db_pool.clone(),
+                config.redis.dev_databases,
+            )),
+            command_policies: Arc::new(crate::services::command_policy::CommandPolicyService::new(
+                db_pool.clone(),
+            )),
+            scripts: Arc::new(crate::services::scripts::ScriptService::new(db_pool.clone())),
             db_pool,
             jwt_manager: JwtManager::new(jwt_secret),
-            redis_pool: crate::services::redis_pool::RedisPool::new(),
+            redis_pool,
+            redis_backend,
             metrics_service: Arc::new(crate::services::metrics::MetricsService::new()),
-            rate_limiter: Arc::new(crate::services::rate_limiter::RateLimiter::new(rate_limit_rps)),
+            rate_limiter: Arc::new(crate::services::rate_limiter::RateLimiter::with_burst(
+                config.rate_limit.default_requests_per_second,
+                config.rate_limit.burst_size,
+            )),
+            rate_limit_enabled: config.rate_limit.enabled,
             health_service: Arc::new(crate::services::health::HealthCheckService::new()),
+            api_key_cache: Arc::new(crate::services::api_key_cache::ApiKeyCache::new(
+                Duration::from_secs(config.security.api_key_cache_ttl_seconds),
+            )),
             metrics: Metrics::new(),
+            api_key_expiry_days: config.security.api_key_expiry_days,
+            public_url: config.server.public_base_url(),
+            instance_tls: config.redis.instance_tls,
+            request_timeout: Duration::from_secs(config.server.request_timeout_seconds),
         }
     }
 }
@@ -177,3 +229,59 @@ pub async fn metrics_tracking_middleware(
 
     response
 }
+
+// Middleware for per-API-key rate limiting on the Redis HTTP API
+pub async fn rate_limit_middleware(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !state.rate_limit_enabled {
+        return next.run(request).await;
+    }
+
+    // Limit by API key id. Requests without a valid key are rejected here, so they cannot
+    // use up a shared bucket that every other keyless client would then be limited by.
+    let Some(token) = api_key_token(&request) else {
+        return AppError::Authentication("Missing API key".to_string()).into_response();
+    };
+    let Ok(token_data) = state.jwt_manager.verify_api_key_token(&token) else {
+        return AppError::Authentication("Invalid API key".to_string()).into_response();
+    };
+
+    let decision = state
+        .rate_limiter
+        .check_api_key_detailed(&token_data.claims.api_key_id.to_string(), None)
+        .await;
+
+    let mut response = if decision.allowed {
+        next.run(request).await
+    } else {
+        AppError::RateLimitExceeded.into_response()
+    };
+
+    apply_rate_limit_headers(response.headers_mut(), &decision);
+    response
+}
+
+// API key token from the Authorization header or the `_token` query parameter
+fn api_key_token(request: &Request) -> Option<String> {
+    if let Some(token) = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.strip_prefix("Bearer "))
+    {
+        return Some(token.to_string());
+    }
+
+    Query::<HashMap<String, String>>::try_from_uri(request.uri())
+        .ok()
+        .and_then(|Query(mut query)| query.remove("_token"))
+}
+
+fn apply_rate_limit_headers(headers: &mut HeaderMap, decision: &RateLimitDecision) {
+    headers.insert("x-ratelimit-limit", HeaderValue::from(decision.limit));
+    headers.insert("x-ratelimit-remaining", HeaderValue::from(decision.remaining));
+    headers.insert("retry-after", HeaderValue::from(decision.retry_after_secs()));
+}
@@ -77,6 +77,14 @@ pub struct RedisInstance {
     pub current_memory: Option<i64>,
     pub password_hash: Option<String>,
     pub redis_version: Option<String>,
+    /// "standalone" or "cluster"
+    pub topology: String,
+    /// Set for instances whose master is found through Sentinel
+    pub sentinel_master_name: Option<String>,
+    /// `host:port` of the sentinels watching the master
+    pub sentinel_endpoints: Vec<String>,
+    /// "kubernetes", or "development" for a database of the shared local Redis
+    pub deployment_mode: String,
 
     // Kubernetes configuration
     pub namespace: Option<String>,
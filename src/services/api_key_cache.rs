@@ -0,0 +1,100 @@
+use chrono::{DateTime, Utc};
+use moka::future::Cache;
+use sqlx::PgPool;
+use std::time::Duration;
+use tracing::{debug, info};
+use uuid::Uuid;
+
+/// Maximum number of API keys whose status is kept in memory
+const MAX_CACHED_KEYS: u64 = 100_000;
+
+/// Cached view of API key revocation status, backed by the `api_keys` table
+///
+/// API keys are JWTs, so the signature alone cannot tell whether a key has since been
+/// revoked, deactivated or expired in the database. Each key's status is looked up once
+/// and cached for a short TTL; revoking a key through the API updates the cache directly
+/// so it stops working immediately on this gateway.
+pub struct ApiKeyCache {
+    /// api_key_id -> whether the key may still be used
+    statuses: Cache<Uuid, bool>,
+}
+
+impl ApiKeyCache {
+    /// Create a new cache
+    ///
+    /// # Arguments
+    /// * `ttl` - How long a key's status is trusted before re-checking the database
+    pub fn new(ttl: Duration) -> Self {
+        info!("API key revocation cache initialized with {}s TTL", ttl.as_secs());
+
+        Self {
+            statuses: Cache::builder()
+                .max_capacity(MAX_CACHED_KEYS)
+                .time_to_live(ttl)
+                .build(),
+        }
+    }
+
+    /// Check whether an API key has been revoked, deactivated, deleted or has expired
+    pub async fn is_revoked(&self, pool: &PgPool, api_key_id: Uuid) -> Result<bool, sqlx::Error> {
+        if let Some(active) = self.statuses.get(&api_key_id).await {
+            return Ok(!active);
+        }
+
+        let row: Option<(Option<bool>, Option<DateTime<Utc>>)> =
+            sqlx::query_as("SELECT is_active, expires_at FROM api_keys WHERE id = $1")
+                .bind(api_key_id)
+                .fetch_optional(pool)
+                .await?;
+
+        let active = match row {
+            Some((is_active, expires_at)) => {
+                let expired = expires_at.is_some_and(|exp| exp <= Utc::now());
+                is_active.unwrap_or(true) && !expired
+            }
+            None => false,
+        };
+
+        debug!("API key {} status loaded from database (active: {})", api_key_id, active);
+        self.statuses.insert(api_key_id, active).await;
+
+        Ok(!active)
+    }
+
+    /// Mark an API key as revoked so it is rejected without waiting for the TTL
+    pub async fn revoke(&self, api_key_id: Uuid) {
+        self.statuses.insert(api_key_id, false).await;
+        info!("API key {} marked as revoked", api_key_id);
+    }
+
+    /// Drop a cached status so the next check goes to the database
+    pub async fn invalidate(&self, api_key_id: Uuid) {
+        self.statuses.invalidate(&api_key_id).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_revoke_is_cached() {
+        let cache = ApiKeyCache::new(Duration::from_secs(60));
+        let key_id = Uuid::new_v4();
+
+        cache.revoke(key_id).await;
+
+        assert_eq!(cache.statuses.get(&key_id).await, Some(false));
+    }
+
+    #[tokio::test]
+    async fn test_invalidate_removes_status() {
+        let cache = ApiKeyCache::new(Duration::from_secs(60));
+        let key_id = Uuid::new_v4();
+
+        cache.revoke(key_id).await;
+        cache.invalidate(key_id).await;
+
+        assert_eq!(cache.statuses.get(&key_id).await, None);
+    }
+}
@@ -0,0 +1,76 @@
+// Audit log entries for security-sensitive actions
+
+use serde_json::Value;
+use sqlx::PgPool;
+use tracing::error;
+use uuid::Uuid;
+
+/// One row of the `audit_logs` table
+#[derive(Debug, Clone)]
+pub struct AuditEvent {
+    pub user_id: Option<Uuid>,
+    pub organization_id: Option<Uuid>,
+    pub action: &'static str,
+    pub resource_type: &'static str,
+    pub resource_id: Option<Uuid>,
+    pub details: Value,
+    pub user_agent: Option<String>,
+    /// `None` for a successful action, otherwise the reason it failed
+    pub error_message: Option<String>,
+}
+
+impl AuditEvent {
+    pub fn new(action: &'static str, resource_type: &'static str, resource_id: Uuid) -> Self {
+        Self {
+            user_id: None,
+            organization_id: None,
+            action,
+            resource_type,
+            resource_id: Some(resource_id),
+            details: Value::Null,
+            user_agent: None,
+            error_message: None,
+        }
+    }
+
+    pub fn status(&self) -> &'static str {
+        if self.error_message.is_some() {
+            "failure"
+        } else {
+            "success"
+        }
+    }
+}
+
+/// Write an audit entry
+///
+/// Failing to audit should never fail the action being audited, so errors are only logged.
+pub async fn record(db_pool: &PgPool, event: &AuditEvent) {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO audit_logs (
+            user_id, organization_id, action, resource_type, resource_id,
+            details, user_agent, status, error_message
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        "#,
+    )
+    .bind(event.user_id)
+    .bind(event.organization_id)
+    .bind(event.action)
+    .bind(event.resource_type)
+    .bind(event.resource_id)
+    .bind(&event.details)
+    .bind(&event.user_agent)
+    .bind(event.status())
+    .bind(&event.error_message)
+    .execute(db_pool)
+    .await;
+
+    if let Err(e) = result {
+        error!(
+            "Failed to write audit log for {} on {} {:?}: {}",
+            event.action, event.resource_type, event.resource_id, e
+        );
+    }
+}
@@ -0,0 +1,133 @@
+// Blocking commands (BLPOP, BRPOP, BLMOVE, ...) bounded by the HTTP request timeout
+
+use std::time::Duration;
+use thiserror::Error;
+
+/// Time kept between the end of a blocking command and the request timeout, so the reply
+/// can still be sent before the client or a proxy gives up
+const RESPONSE_MARGIN: Duration = Duration::from_secs(1);
+
+/// Shortest cap, for very small request timeouts
+const MIN_BLOCK: Duration = Duration::from_secs(1);
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum BlockingError {
+    #[error("{0} requires a timeout")]
+    MissingTimeout(String),
+
+    #[error("Invalid timeout for {0}: must be a non-negative number of seconds")]
+    InvalidTimeout(String),
+}
+
+/// Longest time a blocking command may wait, given the server's request timeout
+pub fn max_block(request_timeout: Duration) -> Duration {
+    request_timeout.saturating_sub(RESPONSE_MARGIN).max(MIN_BLOCK)
+}
+
+/// Position of the timeout argument of a blocking list command, if `command` is one
+fn timeout_index(command: &str, arg_count: usize) -> Option<usize> {
+    match command {
+        // BLPOP key [key ...] timeout, BLMOVE source destination LEFT|RIGHT LEFT|RIGHT timeout
+        "BLPOP" | "BRPOP" | "BLMOVE" | "BRPOPLPUSH" => Some(arg_count.saturating_sub(1)),
+        // BLMPOP timeout numkeys key [key ...] LEFT|RIGHT [COUNT count]
+        "BLMPOP" => Some(0),
+        _ => None,
+    }
+}
+
+pub fn is_blocking(command: &str) -> bool {
+    timeout_index(command, 0).is_some()
+}
+
+/// Cap the timeout argument of a blocking command at `max`
+///
+/// A timeout of 0 ("wait forever") or above `max` is rewritten to `max`. Returns how long
+/// the command may block, or `None` if `command` (uppercase) is not a blocking command.
+pub fn cap_timeout(
+    command: &str,
+    args: &mut [Vec<u8>],
+    max: Duration,
+) -> Result<Option<Duration>, BlockingError> {
+    let Some(index) = timeout_index(command, args.len()) else {
+        return Ok(None);
+    };
+    let raw = args
+        .get(index)
+        .ok_or_else(|| BlockingError::MissingTimeout(command.to_string()))?;
+
+    let seconds: f64 = std::str::from_utf8(raw)
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .filter(|s: &f64| s.is_finite() && *s >= 0.0)
+        .ok_or_else(|| BlockingError::InvalidTimeout(command.to_string()))?;
+
+    let requested = Duration::from_secs_f64(seconds.min(max.as_secs_f64()));
+    let timeout = if requested.is_zero() { max } else { requested };
+
+    args[index] = format!("{:.3}", timeout.as_secs_f64()).into_bytes();
+    Ok(Some(timeout))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(values: &[&str]) -> Vec<Vec<u8>> {
+        values.iter().map(|v| v.as_bytes().to_vec()).collect()
+    }
+
+    const MAX: Duration = Duration::from_secs(29);
+
+    #[test]
+    fn test_max_block() {
+        assert_eq!(max_block(Duration::from_secs(30)), Duration::from_secs(29));
+        assert_eq!(max_block(Duration::from_secs(1)), MIN_BLOCK);
+    }
+
+    #[test]
+    fn test_timeout_kept_when_within_cap() {
+        let mut blpop = args(&["jobs", "other", "5"]);
+        assert_eq!(cap_timeout("BLPOP", &mut blpop, MAX), Ok(Some(Duration::from_secs(5))));
+        assert_eq!(blpop, args(&["jobs", "other", "5.000"]));
+
+        let mut blmove = args(&["src", "dst", "LEFT", "RIGHT", "0.5"]);
+        assert_eq!(cap_timeout("BLMOVE", &mut blmove, MAX), Ok(Some(Duration::from_millis(500))));
+        assert_eq!(blmove[4], b"0.500".to_vec());
+    }
+
+    #[test]
+    fn test_forever_and_long_timeouts_are_capped() {
+        let mut brpop = args(&["jobs", "0"]);
+        assert_eq!(cap_timeout("BRPOP", &mut brpop, MAX), Ok(Some(MAX)));
+        assert_eq!(brpop[1], b"29.000".to_vec());
+
+        let mut blmpop = args(&["3600", "1", "jobs", "LEFT"]);
+        assert_eq!(cap_timeout("BLMPOP", &mut blmpop, MAX), Ok(Some(MAX)));
+        assert_eq!(blmpop[0], b"29.000".to_vec());
+    }
+
+    #[test]
+    fn test_invalid_timeouts() {
+        assert_eq!(
+            cap_timeout("BLPOP", &mut args(&["jobs", "-1"]), MAX),
+            Err(BlockingError::InvalidTimeout("BLPOP".to_string()))
+        );
+        assert_eq!(
+            cap_timeout("BLPOP", &mut args(&["jobs", "soon"]), MAX),
+            Err(BlockingError::InvalidTimeout("BLPOP".to_string()))
+        );
+        assert_eq!(
+            cap_timeout("BLPOP", &mut args(&[]), MAX),
+            Err(BlockingError::MissingTimeout("BLPOP".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_other_commands_untouched() {
+        let mut lpop = args(&["jobs", "0"]);
+        assert_eq!(cap_timeout("LPOP", &mut lpop, MAX), Ok(None));
+        assert_eq!(lpop, args(&["jobs", "0"]));
+        assert!(is_blocking("BRPOPLPUSH"));
+        assert!(!is_blocking("RPOPLPUSH"));
+    }
+}
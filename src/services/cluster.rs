@@ -0,0 +1,298 @@
+// Redis Cluster support: instance topology, hash slots and same-slot checks for multi-key commands
+
+use redis::aio::ConnectionLike;
+use redis::{Arg, Cmd, ErrorKind, Pipeline, RedisError, RedisResult, Value};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+/// Number of hash slots keys are spread over in a Redis Cluster
+pub const SLOT_COUNT: u16 = 16384;
+
+/// How an instance's Redis is laid out
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Topology {
+    /// A single server (optionally with replicas behind it)
+    #[default]
+    Standalone,
+    /// A sharded Redis Cluster, reached through any of its nodes
+    Cluster,
+}
+
+impl Topology {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Topology::Standalone => "standalone",
+            Topology::Cluster => "cluster",
+        }
+    }
+
+    /// Read the `topology` column; unknown values are treated as standalone
+    pub fn from_column(value: &str) -> Self {
+        match value {
+            "cluster" => Topology::Cluster,
+            _ => Topology::Standalone,
+        }
+    }
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ClusterError {
+    /// Same text as Redis, so clients see one error whoever rejects the command
+    #[error("CROSSSLOT Keys in request don't hash to the same slot")]
+    CrossSlot,
+}
+
+/// CRC16 (XMODEM) as used by Redis Cluster
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0;
+    for &byte in data {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+/// Hash slot of a key
+///
+/// Only the part inside the first non-empty `{...}` is hashed when there is one, so
+/// `{user:1}:name` and `{user:1}:email` land in the same slot.
+pub fn key_slot(key: &[u8]) -> u16 {
+    let hashed = match key.iter().position(|&b| b == b'{') {
+        Some(open) => match key[open + 1..].iter().position(|&b| b == b'}') {
+            Some(len) if len > 0 => &key[open + 1..open + 1 + len],
+            _ => key,
+        },
+        None => key,
+    };
+    crc16(hashed) % SLOT_COUNT
+}
+
+/// Keys named by a command that can take more than one key
+///
+/// `args` excludes the command name. Returns nothing for single-key and keyless commands,
+/// which a cluster routes without any check.
+pub fn command_keys<'a>(command: &str, args: &'a [Vec<u8>]) -> Vec<&'a [u8]> {
+    let all = || args.iter().map(Vec::as_slice).collect::<Vec<_>>();
+    // `numkeys key [key ...]` starting at `at`
+    let counted = |at: usize| -> Vec<&'a [u8]> {
+        let count = args
+            .get(at)
+            .and_then(|n| std::str::from_utf8(n).ok())
+            .and_then(|n| n.parse::<usize>().ok())
+            .unwrap_or(0);
+        args.iter().skip(at + 1).take(count).map(Vec::as_slice).collect()
+    };
+
+    match command.to_uppercase().as_str() {
+        "DEL" | "UNLINK" | "EXISTS" | "TOUCH" | "MGET" | "WATCH" | "SDIFF" | "SINTER" | "SUNION"
+        | "SDIFFSTORE" | "SINTERSTORE" | "SUNIONSTORE" | "PFCOUNT" | "PFMERGE" => all(),
+        "MSET" | "MSETNX" => args.iter().step_by(2).map(Vec::as_slice).collect(),
+        "RENAME" | "RENAMENX" | "SMOVE" | "LMOVE" | "RPOPLPUSH" | "BLMOVE" | "BRPOPLPUSH" | "COPY"
+        | "GEOSEARCHSTORE" | "ZRANGESTORE" => args.iter().take(2).map(Vec::as_slice).collect(),
+        // The last argument is the timeout
+        "BLPOP" | "BRPOP" | "BZPOPMIN" | "BZPOPMAX" => {
+            args.iter().take(args.len().saturating_sub(1)).map(Vec::as_slice).collect()
+        }
+        "EVAL" | "EVALSHA" | "EVAL_RO" | "EVALSHA_RO" | "FCALL" | "FCALL_RO" => counted(1),
+        "ZUNION" | "ZINTER" | "ZDIFF" | "ZINTERCARD" | "SINTERCARD" | "LMPOP" | "ZMPOP" => counted(0),
+        "BLMPOP" | "BZMPOP" => counted(1),
+        "ZUNIONSTORE" | "ZINTERSTORE" | "ZDIFFSTORE" => {
+            let mut keys: Vec<&[u8]> = args.first().map(Vec::as_slice).into_iter().collect();
+            keys.extend(counted(1));
+            keys
+        }
+        // XREAD [COUNT n] [BLOCK ms] STREAMS key [key ...] id [id ...]
+        "XREAD" | "XREADGROUP" => match args.iter().position(|arg| arg.eq_ignore_ascii_case(b"STREAMS")) {
+            Some(at) => {
+                let streams = &args[at + 1..];
+                streams.iter().take(streams.len() / 2).map(Vec::as_slice).collect()
+            }
+            None => Vec::new(),
+        },
+        _ => Vec::new(),
+    }
+}
+
+/// Check that all keys of a command hash to one slot, as a cluster requires
+pub fn check_same_slot(command: &str, args: &[Vec<u8>]) -> Result<(), ClusterError> {
+    same_slot(command_keys(command, args)).map(|_| ())
+}
+
+/// The one slot all of `keys` hash to, or `None` when there are no keys
+pub fn same_slot<'a>(keys: impl IntoIterator<Item = &'a [u8]>) -> Result<Option<u16>, ClusterError> {
+    let mut slot = None;
+    for key in keys {
+        let key_slot = key_slot(key);
+        match slot {
+            Some(slot) if slot != key_slot => return Err(ClusterError::CrossSlot),
+            _ => slot = Some(key_slot),
+        }
+    }
+    Ok(slot)
+}
+
+/// Slot a command is routed to: that of its keys, or its key argument for single-key commands
+pub fn command_slot(command: &str, args: &[Vec<u8>]) -> Result<Option<u16>, ClusterError> {
+    let keys = command_keys(command, args);
+    if !keys.is_empty() {
+        return same_slot(keys);
+    }
+    let key = match command.to_uppercase().as_str() {
+        "PING" | "ECHO" | "INFO" | "SCAN" | "KEYS" | "RANDOMKEY" | "DBSIZE" | "TIME" | "CONFIG" | "CLIENT"
+        | "SCRIPT" | "FUNCTION" | "CLUSTER" | "PUBLISH" | "SELECT" | "FLUSHDB" | "FLUSHALL" => None,
+        // MEMORY USAGE key, OBJECT ENCODING key
+        "MEMORY" | "OBJECT" => args.get(1),
+        _ => args.first(),
+    };
+    Ok(key.map(|key| key_slot(key)))
+}
+
+/// Group commands by the slot they go to, keeping their order within each group
+///
+/// Commands without keys join the first group, so a pipeline of them still goes out once.
+/// Returns the index of each command in `commands`.
+pub fn split_by_slot(commands: &[(String, Vec<Vec<u8>>)]) -> Result<Vec<Vec<usize>>, ClusterError> {
+    let mut groups: BTreeMap<Option<u16>, Vec<usize>> = BTreeMap::new();
+    for (index, (command, args)) in commands.iter().enumerate() {
+        groups.entry(command_slot(command, args)?).or_default().push(index);
+    }
+
+    let keyless = groups.remove(&None).unwrap_or_default();
+    let mut groups: Vec<Vec<usize>> = groups.into_values().collect();
+    match groups.first_mut() {
+        Some(first) => {
+            first.extend(keyless);
+            first.sort_unstable();
+        }
+        None if !keyless.is_empty() => groups.push(keyless),
+        None => {}
+    }
+    Ok(groups)
+}
+
+/// Command name and arguments of a `Cmd`
+fn command_parts(cmd: &Cmd) -> (String, Vec<Vec<u8>>) {
+    let mut parts = cmd.args_iter().filter_map(|arg| match arg {
+        Arg::Simple(bytes) => Some(bytes.to_vec()),
+        Arg::Cursor => None,
+    });
+    let name = parts
+        .next()
+        .map(|name| String::from_utf8_lossy(&name).into_owned())
+        .unwrap_or_default();
+    (name, parts.collect())
+}
+
+/// Run a pipeline on a cluster connection as one pipeline per slot
+///
+/// A cluster connection sends a whole pipeline to the node of its first key, so commands for
+/// other slots would be refused with MOVED. Replies come back in the order of `pipe`'s commands.
+/// Commands must not be `ignore()`d. Atomic pipelines (MULTI/EXEC) are not split; callers
+/// check their keys share a slot instead.
+pub async fn query_by_slot<C: ConnectionLike>(conn: &mut C, pipe: &Pipeline) -> RedisResult<Vec<Value>> {
+    let cmds: Vec<&Cmd> = pipe.cmd_iter().collect();
+    let commands: Vec<(String, Vec<Vec<u8>>)> = cmds.iter().map(|cmd| command_parts(cmd)).collect();
+    let groups = split_by_slot(&commands)
+        .map_err(|_| RedisError::from((ErrorKind::CrossSlot, "Keys in request don't hash to the same slot")))?;
+
+    let mut replies = vec![Value::Nil; cmds.len()];
+    for group in groups {
+        let mut slot_pipe = redis::pipe();
+        for &index in &group {
+            slot_pipe.add_command(cmds[index].clone());
+        }
+        let values: Vec<Value> = slot_pipe.query_async(conn).await?;
+        if values.len() != group.len() {
+            return Err(RedisError::from((ErrorKind::ResponseError, "Unexpected pipeline reply length")));
+        }
+        for (index, value) in group.into_iter().zip(values) {
+            replies[index] = value;
+        }
+    }
+    Ok(replies)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(values: &[&str]) -> Vec<Vec<u8>> {
+        values.iter().map(|v| v.as_bytes().to_vec()).collect()
+    }
+
+    #[test]
+    fn test_key_slot() {
+        // Reference values from the Redis Cluster specification and CLUSTER KEYSLOT
+        assert_eq!(crc16(b"123456789"), 0x31C3);
+        assert_eq!(key_slot(b"foo"), 12182);
+        assert_eq!(key_slot(b"{user1000}.following"), key_slot(b"{user1000}.followers"));
+        assert_eq!(key_slot(b"{user1000}.following"), key_slot(b"user1000"));
+        // An empty hash tag hashes the whole key
+        assert_eq!(key_slot(b"foo{}{bar}"), crc16(b"foo{}{bar}") % SLOT_COUNT);
+    }
+
+    #[test]
+    fn test_command_keys() {
+        assert_eq!(command_keys("mset", &args(&["a", "1", "b", "2"])), vec![b"a" as &[u8], b"b"]);
+        assert_eq!(command_keys("BLPOP", &args(&["a", "b", "5"])), vec![b"a" as &[u8], b"b"]);
+        assert_eq!(command_keys("EVALSHA", &args(&["sha", "2", "a", "b", "arg"])), vec![b"a" as &[u8], b"b"]);
+        assert_eq!(command_keys("ZUNIONSTORE", &args(&["d", "2", "a", "b"])), vec![b"d" as &[u8], b"a", b"b"]);
+        assert_eq!(
+            command_keys("XREAD", &args(&["COUNT", "1", "STREAMS", "s1", "s2", "0", "0"])),
+            vec![b"s1" as &[u8], b"s2"]
+        );
+        assert!(command_keys("GET", &args(&["a"])).is_empty());
+    }
+
+    #[test]
+    fn test_check_same_slot() {
+        assert!(check_same_slot("MGET", &args(&["{user:1}:name", "{user:1}:email"])).is_ok());
+        assert_eq!(check_same_slot("MGET", &args(&["foo", "bar"])), Err(ClusterError::CrossSlot));
+        assert!(check_same_slot("GET", &args(&["foo"])).is_ok());
+    }
+
+    #[test]
+    fn test_split_by_slot() {
+        let commands = vec![
+            ("SET".to_string(), args(&["foo", "1"])),
+            ("PING".to_string(), vec![]),
+            ("GET".to_string(), args(&["bar"])),
+            ("INCR".to_string(), args(&["foo"])),
+        ];
+        let mut groups = split_by_slot(&commands).unwrap();
+        groups.sort();
+        // bar (slot 5061) sorts before foo (slot 12182), so the keyless PING joins bar's group
+        assert_eq!(groups, vec![vec![0, 3], vec![1, 2]]);
+
+        let crossing = vec![("MGET".to_string(), args(&["foo", "bar"]))];
+        assert_eq!(split_by_slot(&crossing), Err(ClusterError::CrossSlot));
+    }
+
+    #[test]
+    fn test_command_slot_of_subcommands() {
+        assert_eq!(command_slot("MEMORY", &args(&["USAGE", "foo"])), Ok(Some(12182)));
+        assert_eq!(command_slot("PING", &args(&["hello"])), Ok(None));
+    }
+
+    #[tokio::test]
+    async fn test_query_by_slot_keeps_order() {
+        let mut conn = crate::services::memory_backend::MemoryBackend::new()
+            .connect(&crate::services::redis_pool::RedisEndpoint::local_development(6379, 0));
+        let mut pipe = redis::pipe();
+        pipe.cmd("SET").arg("foo").arg("1").ignore();
+        pipe.cmd("SET").arg("bar").arg("2").ignore();
+        let _: () = pipe.query_async(&mut conn).await.unwrap();
+
+        let mut pipe = redis::pipe();
+        pipe.cmd("GET").arg("foo").cmd("GET").arg("bar").cmd("GET").arg("foo");
+        let replies = query_by_slot(&mut conn, &pipe).await.unwrap();
+        assert_eq!(
+            replies,
+            vec![Value::Data(b"1".to_vec()), Value::Data(b"2".to_vec()), Value::Data(b"1".to_vec())]
+        );
+    }
+}
@@ -0,0 +1,401 @@
+// Per-instance command policy: which commands may run and under which names
+
+use moka::future::Cache;
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use sqlx::PgPool;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tracing::{debug, info};
+use uuid::Uuid;
+
+/// How long a policy is trusted before re-reading the database (other gateways pick up
+/// changes within this time; the gateway that applied a change sees it immediately)
+const POLICY_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// Maximum number of instance policies kept in memory
+const MAX_CACHED_POLICIES: u64 = 10_000;
+
+/// Longest accepted command name or alias
+const MAX_COMMAND_NAME_LEN: usize = 64;
+
+/// Allowed, denied and renamed commands as stored in the policy table
+type PolicyRow = (Vec<String>, Vec<String>, Json<BTreeMap<String, String>>);
+
+/// Commands blocked on instances that have no policy of their own
+pub const DEFAULT_DENIED_COMMANDS: &[&str] = &[
+    "FLUSHALL", "FLUSHDB", "CONFIG", "DEBUG", "SHUTDOWN", "KEYS", "MONITOR", "SAVE", "BGSAVE",
+    "BGREWRITEAOF", "REPLICAOF", "SLAVEOF", "MIGRATE", "MODULE", "ACL", "SYNC", "PSYNC",
+    "SWAPDB", "FAILOVER", "CLUSTER", "RESET", "EVAL", "EVALSHA", "EVAL_RO", "EVALSHA_RO",
+    "FCALL", "FCALL_RO", "SCRIPT", "FUNCTION",
+];
+
+/// Why a command was rejected by an instance's policy
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum PolicyViolation {
+    #[error("Command {0} is disabled on this instance")]
+    Denied(String),
+
+    #[error("Command {0} is not in this instance's allowed commands")]
+    NotAllowed(String),
+
+    /// The command was renamed (or disabled through the rename map), so its original name is unknown
+    #[error("Unknown command '{0}'")]
+    Renamed(String),
+}
+
+#[derive(Debug, Error)]
+pub enum PolicyError {
+    #[error("Invalid command name: {0:?}")]
+    InvalidName(String),
+
+    #[error("Alias {0} is used for more than one command")]
+    DuplicateAlias(String),
+
+    #[error("Alias {0} is itself a renamed command")]
+    AliasShadowsRename(String),
+
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+/// Commands an instance accepts through the gateway
+///
+/// All names are upper case. A command is rejected if its original name is renamed, if it
+/// is denied, or if an allow list is set and does not contain it. Deny and allow lists
+/// always refer to original command names, not aliases.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommandPolicy {
+    /// When non-empty, only these commands may run
+    pub allowed_commands: Vec<String>,
+    /// Commands that may never run
+    pub denied_commands: Vec<String>,
+    /// Original command name -> name clients must use instead; an empty name disables the command
+    pub renamed_commands: BTreeMap<String, String>,
+}
+
+impl Default for CommandPolicy {
+    /// Block administrative and destructive commands, allow everything else
+    fn default() -> Self {
+        Self {
+            allowed_commands: Vec::new(),
+            denied_commands: DEFAULT_DENIED_COMMANDS.iter().map(|c| c.to_string()).collect(),
+            renamed_commands: BTreeMap::new(),
+        }
+    }
+}
+
+impl CommandPolicy {
+    /// Check a command as sent by a client, returning the name to send to Redis
+    pub fn resolve(&self, command: &str) -> Result<String, PolicyViolation> {
+        let requested = command.to_uppercase();
+
+        let alias_of = self
+            .renamed_commands
+            .iter()
+            .find(|(_, alias)| !alias.is_empty() && **alias == requested)
+            .map(|(original, _)| original.clone());
+
+        let command = match alias_of {
+            Some(original) => original,
+            None if self.renamed_commands.contains_key(&requested) => {
+                return Err(PolicyViolation::Renamed(requested))
+            }
+            None => requested,
+        };
+
+        self.check_lists(&command)?;
+        Ok(command)
+    }
+
+    /// Check a command by its original name, as called from a server-side script
+    ///
+    /// Renaming only changes the name clients use, so a renamed command is allowed here
+    /// unless it was disabled by renaming it to "".
+    pub fn permits(&self, command: &str) -> Result<(), PolicyViolation> {
+        let command = command.to_uppercase();
+        if self.renamed_commands.get(&command).is_some_and(|alias| alias.is_empty()) {
+            return Err(PolicyViolation::Denied(command));
+        }
+        self.check_lists(&command)
+    }
+
+    fn check_lists(&self, command: &str) -> Result<(), PolicyViolation> {
+        if self.denied_commands.iter().any(|denied| denied == command) {
+            return Err(PolicyViolation::Denied(command.to_string()));
+        }
+        if !self.allowed_commands.is_empty() && !self.allowed_commands.iter().any(|allowed| allowed == command) {
+            return Err(PolicyViolation::NotAllowed(command.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Upper-case, de-duplicate and validate all names
+    pub fn normalized(self) -> Result<Self, PolicyError> {
+        let allowed_commands = normalize_names(self.allowed_commands)?;
+        let denied_commands = normalize_names(self.denied_commands)?;
+
+        let mut renamed_commands = BTreeMap::new();
+        for (original, alias) in self.renamed_commands {
+            let original = normalize_name(&original)?;
+            let alias = if alias.is_empty() { alias } else { normalize_name(&alias)? };
+            renamed_commands.insert(original, alias);
+        }
+
+        let mut aliases: Vec<&String> = renamed_commands.values().filter(|a| !a.is_empty()).collect();
+        aliases.sort();
+        if let Some(pair) = aliases.windows(2).find(|pair| pair[0] == pair[1]) {
+            return Err(PolicyError::DuplicateAlias(pair[0].clone()));
+        }
+        if let Some(alias) = aliases.iter().find(|alias| renamed_commands.contains_key(alias.as_str())) {
+            return Err(PolicyError::AliasShadowsRename(alias.to_string()));
+        }
+
+        Ok(Self {
+            allowed_commands,
+            denied_commands,
+            renamed_commands,
+        })
+    }
+}
+
+fn normalize_name(name: &str) -> Result<String, PolicyError> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_COMMAND_NAME_LEN
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if !valid {
+        return Err(PolicyError::InvalidName(name.to_string()));
+    }
+    Ok(name.to_uppercase())
+}
+
+fn normalize_names(names: Vec<String>) -> Result<Vec<String>, PolicyError> {
+    let mut names = names
+        .iter()
+        .map(|name| normalize_name(name))
+        .collect::<Result<Vec<_>, _>>()?;
+    names.sort();
+    names.dedup();
+    Ok(names)
+}
+
+/// Loads instance policies from the `redis_instance_command_policies` table and caches them
+pub struct CommandPolicyService {
+    db_pool: PgPool,
+    /// instance_id -> policy (the default policy if the instance has none stored)
+    policies: Cache<Uuid, Arc<CommandPolicy>>,
+}
+
+impl CommandPolicyService {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self {
+            db_pool,
+            policies: Cache::builder()
+                .max_capacity(MAX_CACHED_POLICIES)
+                .time_to_live(POLICY_CACHE_TTL)
+                .build(),
+        }
+    }
+
+    /// Policy of an instance
+    pub async fn policy(&self, instance_id: Uuid) -> Result<Arc<CommandPolicy>, sqlx::Error> {
+        if let Some(policy) = self.policies.get(&instance_id).await {
+            return Ok(policy);
+        }
+
+        let row: Option<PolicyRow> = sqlx::query_as(
+            r#"
+            SELECT allowed_commands, denied_commands, renamed_commands
+            FROM redis_instance_command_policies
+            WHERE instance_id = $1
+            "#,
+        )
+        .bind(instance_id)
+        .fetch_optional(&self.db_pool)
+        .await?;
+
+        let policy = Arc::new(match row {
+            Some((allowed_commands, denied_commands, Json(renamed_commands))) => CommandPolicy {
+                allowed_commands,
+                denied_commands,
+                renamed_commands,
+            },
+            None => CommandPolicy::default(),
+        });
+
+        debug!("Command policy for instance {} loaded from database", instance_id);
+        self.policies.insert(instance_id, policy.clone()).await;
+        Ok(policy)
+    }
+
+    /// Validate and store an instance's policy, replacing any previous one
+    pub async fn set_policy(
+        &self,
+        instance_id: Uuid,
+        policy: CommandPolicy,
+        updated_by: Uuid,
+    ) -> Result<CommandPolicy, PolicyError> {
+        let policy = policy.normalized()?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO redis_instance_command_policies
+                (instance_id, allowed_commands, denied_commands, renamed_commands, updated_by)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (instance_id) DO UPDATE
+            SET allowed_commands = EXCLUDED.allowed_commands,
+                denied_commands = EXCLUDED.denied_commands,
+                renamed_commands = EXCLUDED.renamed_commands,
+                updated_by = EXCLUDED.updated_by,
+                updated_at = NOW()
+            "#,
+        )
+        .bind(instance_id)
+        .bind(&policy.allowed_commands)
+        .bind(&policy.denied_commands)
+        .bind(Json(&policy.renamed_commands))
+        .bind(updated_by)
+        .execute(&self.db_pool)
+        .await?;
+
+        self.policies.insert(instance_id, Arc::new(policy.clone())).await;
+        info!("Command policy for instance {} updated", instance_id);
+        Ok(policy)
+    }
+
+    /// Drop a cached policy so the next lookup reads the database
+    pub async fn invalidate(&self, instance_id: Uuid) {
+        self.policies.invalidate(&instance_id).await;
+    }
+
+    /// Use a policy for an instance without the database, until it expires from the cache
+    ///
+    /// For tests and tools that run without PostgreSQL.
+    pub async fn preload(&self, instance_id: Uuid, policy: CommandPolicy) {
+        self.policies.insert(instance_id, Arc::new(policy)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn test_default_policy_blocks_admin_commands() {
+        let policy = CommandPolicy::default();
+        for command in ["flushall", "CONFIG", "Debug", "SHUTDOWN", "KEYS", "MONITOR"] {
+            assert!(matches!(policy.resolve(command), Err(PolicyViolation::Denied(_))));
+        }
+        assert_eq!(policy.resolve("get").unwrap(), "GET");
+    }
+
+    #[test]
+    fn test_allow_list() {
+        let policy = CommandPolicy {
+            allowed_commands: names(&["GET", "SET"]),
+            denied_commands: Vec::new(),
+            renamed_commands: BTreeMap::new(),
+        };
+        assert_eq!(policy.resolve("set").unwrap(), "SET");
+        assert_eq!(
+            policy.resolve("DEL"),
+            Err(PolicyViolation::NotAllowed("DEL".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_deny_list_wins_over_allow_list() {
+        let policy = CommandPolicy {
+            allowed_commands: names(&["GET", "DEL"]),
+            denied_commands: names(&["DEL"]),
+            renamed_commands: BTreeMap::new(),
+        };
+        assert_eq!(policy.resolve("DEL"), Err(PolicyViolation::Denied("DEL".to_string())));
+    }
+
+    #[test]
+    fn test_renamed_command() {
+        let mut policy = CommandPolicy::default();
+        policy.renamed_commands.insert("FLUSHDB".to_string(), "FLUSHDB_7F3A".to_string());
+        policy.renamed_commands.insert("EVAL".to_string(), String::new());
+        policy.denied_commands.retain(|c| c != "FLUSHDB");
+
+        assert_eq!(policy.resolve("flushdb_7f3a").unwrap(), "FLUSHDB");
+        assert_eq!(
+            policy.resolve("FLUSHDB"),
+            Err(PolicyViolation::Renamed("FLUSHDB".to_string()))
+        );
+        assert_eq!(policy.resolve("EVAL"), Err(PolicyViolation::Renamed("EVAL".to_string())));
+    }
+
+    #[test]
+    fn test_permits_checks_original_names() {
+        let mut policy = CommandPolicy::default();
+        policy.renamed_commands.insert("DEL".to_string(), "REMOVE".to_string());
+        policy.renamed_commands.insert("EVAL".to_string(), String::new());
+
+        assert_eq!(policy.permits("del"), Ok(()));
+        assert_eq!(policy.permits("EVAL"), Err(PolicyViolation::Denied("EVAL".to_string())));
+        assert_eq!(policy.permits("FLUSHALL"), Err(PolicyViolation::Denied("FLUSHALL".to_string())));
+
+        policy.allowed_commands = vec!["GET".to_string()];
+        assert_eq!(policy.permits("SET"), Err(PolicyViolation::NotAllowed("SET".to_string())));
+    }
+
+    #[test]
+    fn test_alias_of_denied_command_is_denied() {
+        let mut policy = CommandPolicy::default();
+        policy.renamed_commands.insert("CONFIG".to_string(), "CFG".to_string());
+        assert_eq!(policy.resolve("CFG"), Err(PolicyViolation::Denied("CONFIG".to_string())));
+    }
+
+    #[test]
+    fn test_normalized() {
+        let policy = CommandPolicy {
+            allowed_commands: names(&["get", "GET", "set"]),
+            denied_commands: names(&["flushall"]),
+            renamed_commands: [("config".to_string(), "my-config".to_string())].into(),
+        }
+        .normalized()
+        .unwrap();
+
+        assert_eq!(policy.allowed_commands, names(&["GET", "SET"]));
+        assert_eq!(policy.denied_commands, names(&["FLUSHALL"]));
+        assert_eq!(policy.renamed_commands.get("CONFIG").unwrap(), "MY-CONFIG");
+    }
+
+    #[test]
+    fn test_normalized_rejects_invalid_policies() {
+        let invalid_name = CommandPolicy {
+            denied_commands: names(&["FLUSH ALL"]),
+            ..CommandPolicy::default()
+        };
+        assert!(matches!(invalid_name.normalized(), Err(PolicyError::InvalidName(_))));
+
+        let duplicate_alias = CommandPolicy {
+            renamed_commands: [
+                ("CONFIG".to_string(), "X".to_string()),
+                ("DEBUG".to_string(), "x".to_string()),
+            ]
+            .into(),
+            ..CommandPolicy::default()
+        };
+        assert!(matches!(duplicate_alias.normalized(), Err(PolicyError::DuplicateAlias(_))));
+
+        let chained = CommandPolicy {
+            renamed_commands: [
+                ("CONFIG".to_string(), "DEBUG".to_string()),
+                ("DEBUG".to_string(), "D".to_string()),
+            ]
+            .into(),
+            ..CommandPolicy::default()
+        };
+        assert!(matches!(chained.normalized(), Err(PolicyError::AliasShadowsRename(_))));
+    }
+}
@@ -0,0 +1,187 @@
+// Redis command classification and API key scope checks
+
+use std::fmt;
+
+/// Access class of a Redis command, used to decide which API key scopes may run it
+///
+/// Ordered from least to most privileged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CommandClass {
+    /// Commands that only read data
+    Read,
+    /// Commands that modify data
+    Write,
+    /// Server inspection and management commands
+    Admin,
+    /// Commands that can destroy data or take the server down
+    Dangerous,
+}
+
+impl CommandClass {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CommandClass::Read => "read",
+            CommandClass::Write => "write",
+            CommandClass::Admin => "admin",
+            CommandClass::Dangerous => "dangerous",
+        }
+    }
+}
+
+impl fmt::Display for CommandClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+const READ_COMMANDS: &[&str] = &[
+    "PING", "ECHO", "GET", "MGET", "GETRANGE", "STRLEN", "EXISTS", "TTL", "PTTL", "EXPIRETIME",
+    "PEXPIRETIME", "TYPE", "SCAN", "RANDOMKEY", "DUMP", "TOUCH", "LCS", "SUBSTR",
+    "HGET", "HMGET", "HGETALL", "HKEYS", "HVALS", "HLEN", "HEXISTS", "HSTRLEN", "HSCAN",
+    "HRANDFIELD",
+    "LLEN", "LRANGE", "LINDEX", "LPOS",
+    "SMEMBERS", "SISMEMBER", "SMISMEMBER", "SCARD", "SRANDMEMBER", "SSCAN", "SINTER", "SUNION",
+    "SDIFF", "SINTERCARD",
+    "ZRANGE", "ZRANGEBYSCORE", "ZRANGEBYLEX", "ZREVRANGE", "ZREVRANGEBYSCORE", "ZREVRANGEBYLEX",
+    "ZRANK", "ZREVRANK", "ZSCORE", "ZMSCORE", "ZCARD", "ZCOUNT", "ZLEXCOUNT", "ZSCAN",
+    "ZRANDMEMBER", "ZINTER", "ZUNION", "ZDIFF", "ZINTERCARD",
+    "XRANGE", "XREVRANGE", "XLEN", "XREAD", "XINFO", "XPENDING",
+    "BITCOUNT", "BITPOS", "GETBIT", "PFCOUNT",
+    "GEOPOS", "GEODIST", "GEOHASH", "GEORADIUS_RO", "GEORADIUSBYMEMBER_RO", "GEOSEARCH",
+    "SUBSCRIBE", "PSUBSCRIBE", "UNSUBSCRIBE", "PUNSUBSCRIBE", "SSUBSCRIBE", "SUNSUBSCRIBE",
+];
+
+const WRITE_COMMANDS: &[&str] = &[
+    "SET", "SETEX", "PSETEX", "SETNX", "MSET", "MSETNX", "GETSET", "GETDEL", "GETEX", "SETRANGE",
+    "APPEND", "INCR", "INCRBY", "INCRBYFLOAT", "DECR", "DECRBY",
+    "DEL", "UNLINK", "EXPIRE", "PEXPIRE", "EXPIREAT", "PEXPIREAT", "PERSIST", "RENAME", "RENAMENX",
+    "COPY", "RESTORE",
+    "HSET", "HSETNX", "HMSET", "HDEL", "HINCRBY", "HINCRBYFLOAT",
+    "LPUSH", "RPUSH", "LPUSHX", "RPUSHX", "LPOP", "RPOP", "LSET", "LREM", "LTRIM", "LINSERT",
+    "LMOVE", "RPOPLPUSH", "LMPOP", "BLPOP", "BRPOP", "BLMOVE", "BRPOPLPUSH", "BLMPOP",
+    "SADD", "SREM", "SPOP", "SMOVE", "SINTERSTORE", "SUNIONSTORE", "SDIFFSTORE",
+    "ZADD", "ZINCRBY", "ZREM", "ZREMRANGEBYRANK", "ZREMRANGEBYSCORE", "ZREMRANGEBYLEX",
+    "ZPOPMIN", "ZPOPMAX", "BZPOPMIN", "BZPOPMAX", "ZMPOP", "BZMPOP", "ZRANGESTORE",
+    "ZINTERSTORE", "ZUNIONSTORE", "ZDIFFSTORE",
+    "XADD", "XDEL", "XTRIM", "XACK", "XCLAIM", "XAUTOCLAIM", "XGROUP", "XREADGROUP", "XSETID",
+    "SETBIT", "BITOP", "BITFIELD", "PFADD", "PFMERGE",
+    "GEOADD", "GEORADIUS", "GEORADIUSBYMEMBER", "GEOSEARCHSTORE",
+    "PUBLISH", "SPUBLISH",
+    "MULTI", "EXEC", "DISCARD", "WATCH", "UNWATCH",
+];
+
+const DANGEROUS_COMMANDS: &[&str] = &[
+    "FLUSHALL", "FLUSHDB", "CONFIG", "SHUTDOWN", "DEBUG", "SAVE", "BGSAVE", "BGREWRITEAOF",
+    "REPLICAOF", "SLAVEOF", "MIGRATE", "MODULE", "ACL", "MONITOR", "SYNC", "PSYNC", "SWAPDB",
+    "FAILOVER", "CLUSTER", "RESET",
+    // A Lua script can run any command, including the ones above, so raw scripting is only
+    // for fully trusted keys; everyone else goes through the script registry
+    "EVAL", "EVALSHA", "EVAL_RO", "EVALSHA_RO", "FCALL", "FCALL_RO", "SCRIPT", "FUNCTION",
+];
+
+/// Classify a Redis command by name (case-insensitive)
+///
+/// Anything that is not a known read, write or dangerous command (INFO, CLIENT, KEYS,
+/// unknown commands, ...) is treated as an admin command so that new or unusual
+/// commands are not available to read/write keys by accident.
+pub fn classify_command(command: &str) -> CommandClass {
+    let command = command.to_uppercase();
+    let command = command.as_str();
+
+    if READ_COMMANDS.contains(&command) {
+        CommandClass::Read
+    } else if WRITE_COMMANDS.contains(&command) {
+        CommandClass::Write
+    } else if DANGEROUS_COMMANDS.contains(&command) {
+        CommandClass::Dangerous
+    } else {
+        CommandClass::Admin
+    }
+}
+
+/// Check whether a set of API key scopes allows a command class
+///
+/// Scopes are hierarchical: `write` includes `read`, and `admin` includes `write`.
+/// Dangerous commands need the explicit `dangerous` scope or the `*` wildcard.
+pub fn scopes_allow(scopes: &[String], class: CommandClass) -> bool {
+    scopes.iter().any(|scope| {
+        matches!(
+            (scope.as_str(), class),
+            ("*" | "dangerous", _)
+                | ("admin", CommandClass::Read | CommandClass::Write | CommandClass::Admin)
+                | ("write", CommandClass::Read | CommandClass::Write)
+                | ("read", CommandClass::Read)
+        )
+    })
+}
+
+/// Check whether a set of API key scopes allows a specific command
+pub fn command_allowed(scopes: &[String], command: &str) -> bool {
+    scopes_allow(scopes, classify_command(command))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scopes(values: &[&str]) -> Vec<String> {
+        values.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_classify_command() {
+        assert_eq!(classify_command("GET"), CommandClass::Read);
+        assert_eq!(classify_command("get"), CommandClass::Read);
+        assert_eq!(classify_command("SET"), CommandClass::Write);
+        assert_eq!(classify_command("INFO"), CommandClass::Admin);
+        assert_eq!(classify_command("FLUSHALL"), CommandClass::Dangerous);
+        assert_eq!(classify_command("NOT_A_COMMAND"), CommandClass::Admin);
+    }
+
+    #[test]
+    fn test_scripting_is_dangerous() {
+        for command in ["EVAL", "evalsha", "EVAL_RO", "FCALL", "SCRIPT", "FUNCTION"] {
+            assert_eq!(classify_command(command), CommandClass::Dangerous);
+        }
+        assert!(!command_allowed(&scopes(&["write"]), "EVAL"));
+        assert!(!command_allowed(&scopes(&["admin"]), "FCALL"));
+        assert!(command_allowed(&scopes(&["dangerous"]), "EVAL"));
+    }
+
+    #[test]
+    fn test_read_scope() {
+        let read = scopes(&["read"]);
+        assert!(command_allowed(&read, "GET"));
+        assert!(!command_allowed(&read, "SET"));
+        assert!(!command_allowed(&read, "FLUSHALL"));
+    }
+
+    #[test]
+    fn test_write_scope_includes_read() {
+        let write = scopes(&["write"]);
+        assert!(command_allowed(&write, "GET"));
+        assert!(command_allowed(&write, "DEL"));
+        assert!(!command_allowed(&write, "INFO"));
+    }
+
+    #[test]
+    fn test_admin_scope_excludes_dangerous() {
+        let admin = scopes(&["admin"]);
+        assert!(command_allowed(&admin, "INFO"));
+        assert!(command_allowed(&admin, "SET"));
+        assert!(!command_allowed(&admin, "FLUSHALL"));
+    }
+
+    #[test]
+    fn test_wildcard_scope() {
+        let all = scopes(&["*"]);
+        assert!(command_allowed(&all, "GET"));
+        assert!(command_allowed(&all, "FLUSHALL"));
+        assert!(command_allowed(&all, "CONFIG"));
+    }
+
+    #[test]
+    fn test_no_scopes_denies_everything() {
+        assert!(!command_allowed(&[], "PING"));
+    }
+}
@@ -0,0 +1,503 @@
+// Encryption at rest for Redis instance credentials
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, Nonce, OsRng, Payload};
+use aes_gcm::Aes256Gcm;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use moka::future::Cache;
+use sqlx::{PgExecutor, PgPool};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+use thiserror::Error;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::config::CredentialsConfig;
+use crate::models::RedisInstance;
+use crate::services::redis_pool::RedisEndpoint;
+
+/// AES-256 key length in bytes
+const KEY_LEN: usize = 32;
+
+/// AES-GCM nonce length in bytes
+const NONCE_LEN: usize = 12;
+
+/// How long decrypted passwords are kept in memory before re-reading the database
+const PASSWORD_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Attempts at assigning a development database when other instances take the same one
+const DEV_DATABASE_ATTEMPTS: usize = 3;
+
+#[derive(Error, Debug)]
+pub enum CredentialsError {
+    #[error("Invalid master key: {0}")]
+    InvalidKey(String),
+
+    #[error("Failed to access master key file: {0}")]
+    KeyFile(#[from] std::io::Error),
+
+    #[error("No master key with version {0}")]
+    UnknownKeyVersion(i32),
+
+    #[error("Encryption failed")]
+    Encryption,
+
+    #[error("Decryption failed: {0}")]
+    Decryption(String),
+
+    #[error("All {0} databases of the local Redis are assigned to development instances")]
+    NoDevDatabase(u32),
+
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+/// A secret encrypted with one version of the master key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncryptedSecret {
+    /// base64(nonce || ciphertext || tag)
+    pub ciphertext: String,
+    pub key_version: i32,
+}
+
+/// AES-256-GCM encryption with versioned master keys
+///
+/// New secrets are always encrypted with the current key; retired keys are kept only
+/// to decrypt secrets written before a rotation.
+pub struct CredentialCipher {
+    current_version: i32,
+    keys: HashMap<i32, Aes256Gcm>,
+}
+
+impl CredentialCipher {
+    /// Create a cipher from raw keys
+    pub fn new(
+        current_version: i32,
+        current_key: &[u8],
+        previous_keys: Vec<(i32, Vec<u8>)>,
+    ) -> Result<Self, CredentialsError> {
+        let mut keys = HashMap::new();
+        keys.insert(current_version, build_cipher(current_key)?);
+        for (version, key) in previous_keys {
+            keys.insert(version, build_cipher(&key)?);
+        }
+
+        Ok(Self {
+            current_version,
+            keys,
+        })
+    }
+
+    /// Load keys from configuration, generating a local key file if no key is configured
+    pub fn from_config(config: &CredentialsConfig) -> Result<Self, CredentialsError> {
+        let current_key = match config.master_key.as_deref().filter(|k| !k.trim().is_empty()) {
+            Some(key) => decode_key(key)?,
+            None => load_or_create_key_file(Path::new(&config.master_key_file))?,
+        };
+
+        let previous_keys = config
+            .previous_keys
+            .iter()
+            .map(|k| Ok((k.version, decode_key(&k.key)?)))
+            .collect::<Result<Vec<_>, CredentialsError>>()?;
+
+        info!(
+            "Credential encryption ready (key version {}, {} retired keys)",
+            config.key_version,
+            previous_keys.len()
+        );
+        Self::new(config.key_version, &current_key, previous_keys)
+    }
+
+    pub fn current_version(&self) -> i32 {
+        self.current_version
+    }
+
+    /// Encrypt a secret with the current key
+    ///
+    /// `context` (e.g. the instance id) is authenticated along with the secret, so a
+    /// ciphertext copied to another record fails to decrypt.
+    pub fn encrypt(&self, plaintext: &str, context: &[u8]) -> Result<EncryptedSecret, CredentialsError> {
+        let cipher = self
+            .keys
+            .get(&self.current_version)
+            .ok_or(CredentialsError::UnknownKeyVersion(self.current_version))?;
+
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, Payload { msg: plaintext.as_bytes(), aad: context })
+            .map_err(|_| CredentialsError::Encryption)?;
+
+        let mut combined = nonce.to_vec();
+        combined.extend_from_slice(&ciphertext);
+
+        Ok(EncryptedSecret {
+            ciphertext: BASE64.encode(combined),
+            key_version: self.current_version,
+        })
+    }
+
+    /// Decrypt a secret with the key version it was encrypted with
+    pub fn decrypt(&self, secret: &EncryptedSecret, context: &[u8]) -> Result<String, CredentialsError> {
+        let cipher = self
+            .keys
+            .get(&secret.key_version)
+            .ok_or(CredentialsError::UnknownKeyVersion(secret.key_version))?;
+
+        let combined = BASE64
+            .decode(&secret.ciphertext)
+            .map_err(|e| CredentialsError::Decryption(e.to_string()))?;
+        if combined.len() <= NONCE_LEN {
+            return Err(CredentialsError::Decryption("ciphertext too short".to_string()));
+        }
+
+        let (nonce, ciphertext) = combined.split_at(NONCE_LEN);
+        let plaintext = cipher
+            .decrypt(
+                Nonce::<Aes256Gcm>::from_slice(nonce),
+                Payload { msg: ciphertext, aad: context },
+            )
+            .map_err(|_| CredentialsError::Decryption("authentication failed".to_string()))?;
+
+        String::from_utf8(plaintext)
+            .map_err(|_| CredentialsError::Decryption("secret is not valid UTF-8".to_string()))
+    }
+}
+
+fn build_cipher(key: &[u8]) -> Result<Aes256Gcm, CredentialsError> {
+    Aes256Gcm::new_from_slice(key).map_err(|_| {
+        CredentialsError::InvalidKey(format!("expected {} bytes, got {}", KEY_LEN, key.len()))
+    })
+}
+
+fn decode_key(encoded: &str) -> Result<Vec<u8>, CredentialsError> {
+    let key = BASE64
+        .decode(encoded.trim())
+        .map_err(|e| CredentialsError::InvalidKey(format!("not valid base64: {}", e)))?;
+    if key.len() != KEY_LEN {
+        return Err(CredentialsError::InvalidKey(format!(
+            "expected {} bytes, got {}",
+            KEY_LEN,
+            key.len()
+        )));
+    }
+    Ok(key)
+}
+
+/// Read the master key file, creating it with a fresh random key on first start
+fn load_or_create_key_file(path: &Path) -> Result<Vec<u8>, CredentialsError> {
+    if path.exists() {
+        let contents = std::fs::read_to_string(path)?;
+        return decode_key(&contents);
+    }
+
+    warn!(
+        "Master key file {} not found, generating a new key. Back it up: stored instance passwords cannot be recovered without it",
+        path.display()
+    );
+
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let key = Aes256Gcm::generate_key(OsRng);
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(BASE64.encode(key).as_bytes())?;
+
+    Ok(key.to_vec())
+}
+
+/// Stores and decrypts Redis instance passwords
+///
+/// All code that connects to an instance resolves its endpoint through this service,
+/// so passwords only exist in plaintext in memory. It also keeps development instances,
+/// which share the local Redis, apart by giving each its own logical database.
+pub struct CredentialsService {
+    cipher: CredentialCipher,
+    db_pool: PgPool,
+    /// instance_id -> decrypted password (None if the instance has no stored password)
+    passwords: Cache<Uuid, Option<String>>,
+    /// Number of logical databases of the local Redis
+    dev_databases: u32,
+    /// instance_id -> database assigned to a development instance
+    dev_database_indexes: Cache<Uuid, i32>,
+}
+
+impl CredentialsService {
+    pub fn new(cipher: CredentialCipher, db_pool: PgPool, dev_databases: u32) -> Self {
+        Self {
+            cipher,
+            db_pool,
+            passwords: Cache::builder().time_to_live(PASSWORD_CACHE_TTL).build(),
+            dev_databases,
+            dev_database_indexes: Cache::builder().build(),
+        }
+    }
+
+    /// Encrypt and store an instance password, replacing any previous one
+    pub async fn store_instance_password<'e, E: PgExecutor<'e>>(
+        &self,
+        executor: E,
+        instance_id: Uuid,
+        password: &str,
+    ) -> Result<(), CredentialsError> {
+        let secret = self.cipher.encrypt(password, instance_id.as_bytes())?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO redis_instance_credentials (instance_id, password_ciphertext, key_version)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (instance_id) DO UPDATE
+            SET password_ciphertext = EXCLUDED.password_ciphertext,
+                key_version = EXCLUDED.key_version,
+                updated_at = NOW()
+            "#,
+        )
+        .bind(instance_id)
+        .bind(&secret.ciphertext)
+        .bind(secret.key_version)
+        .execute(executor)
+        .await?;
+
+        self.invalidate(instance_id).await;
+        Ok(())
+    }
+
+    /// Decrypted password of an instance, or `None` if no password is stored
+    pub async fn instance_password(&self, instance_id: Uuid) -> Result<Option<String>, CredentialsError> {
+        if let Some(password) = self.passwords.get(&instance_id).await {
+            return Ok(password);
+        }
+
+        let row: Option<(String, i32)> = sqlx::query_as(
+            "SELECT password_ciphertext, key_version FROM redis_instance_credentials WHERE instance_id = $1",
+        )
+        .bind(instance_id)
+        .fetch_optional(&self.db_pool)
+        .await?;
+
+        let password = match row {
+            Some((ciphertext, key_version)) => Some(self.cipher.decrypt(
+                &EncryptedSecret { ciphertext, key_version },
+                instance_id.as_bytes(),
+            )?),
+            None => None,
+        };
+
+        self.passwords.insert(instance_id, password.clone()).await;
+        Ok(password)
+    }
+
+    /// Resolve where and how to connect to an instance
+    pub async fn endpoint(&self, instance: &RedisInstance) -> Result<RedisEndpoint, CredentialsError> {
+        let password = self.instance_password(instance.id).await?;
+        let mut endpoint = RedisEndpoint::for_instance(instance, password);
+        if RedisEndpoint::is_local_development(instance) {
+            endpoint.database = self.dev_database(instance.id).await?;
+        }
+        Ok(endpoint)
+    }
+
+    /// Logical database of a development instance, assigning the lowest free one on first use
+    ///
+    /// Database 0 is left to whatever else uses the local Redis.
+    pub async fn dev_database(&self, instance_id: Uuid) -> Result<i32, CredentialsError> {
+        if let Some(database) = self.dev_database_indexes.get(&instance_id).await {
+            return Ok(database);
+        }
+
+        for _ in 0..DEV_DATABASE_ATTEMPTS {
+            let assigned: Result<Option<(i32,)>, sqlx::Error> = sqlx::query_as(
+                r#"
+                INSERT INTO redis_instance_dev_databases (instance_id, db_index)
+                SELECT $1, candidate
+                FROM generate_series(1, $2 - 1) AS candidate
+                WHERE candidate NOT IN (SELECT db_index FROM redis_instance_dev_databases)
+                ORDER BY candidate
+                LIMIT 1
+                ON CONFLICT (instance_id) DO NOTHING
+                RETURNING db_index
+                "#,
+            )
+            .bind(instance_id)
+            .bind(self.dev_databases as i32)
+            .fetch_optional(&self.db_pool)
+            .await;
+
+            let database = match assigned {
+                Ok(Some((database,))) => {
+                    info!("Assigned database {} of the local Redis to instance {}", database, instance_id);
+                    database
+                }
+                // Already assigned (possibly concurrently), or no database is free
+                Ok(None) => {
+                    let existing: Option<(i32,)> = sqlx::query_as(
+                        "SELECT db_index FROM redis_instance_dev_databases WHERE instance_id = $1",
+                    )
+                    .bind(instance_id)
+                    .fetch_optional(&self.db_pool)
+                    .await?;
+                    existing
+                        .map(|(database,)| database)
+                        .ok_or(CredentialsError::NoDevDatabase(self.dev_databases))?
+                }
+                // Another instance took the same database; pick again
+                Err(sqlx::Error::Database(e)) if e.is_unique_violation() => continue,
+                Err(e) => return Err(e.into()),
+            };
+
+            self.dev_database_indexes.insert(instance_id, database).await;
+            return Ok(database);
+        }
+
+        Err(CredentialsError::NoDevDatabase(self.dev_databases))
+    }
+
+    /// Endpoint of the database assigned to a deleted development instance, if it has one
+    pub async fn assigned_dev_endpoint(&self, instance_id: Uuid) -> Result<Option<RedisEndpoint>, CredentialsError> {
+        let row: Option<(i32, Option<i32>)> = sqlx::query_as(
+            r#"
+            SELECT d.db_index, i.port
+            FROM redis_instance_dev_databases d
+            JOIN redis_instances i ON i.id = d.instance_id
+            WHERE d.instance_id = $1
+            "#,
+        )
+        .bind(instance_id)
+        .fetch_optional(&self.db_pool)
+        .await?;
+
+        Ok(row.map(|(database, port)| RedisEndpoint::local_development(port.unwrap_or(6379) as u16, database)))
+    }
+
+    /// Free the database of a development instance so another instance can use it
+    pub async fn release_dev_database(&self, instance_id: Uuid) -> Result<(), CredentialsError> {
+        sqlx::query("DELETE FROM redis_instance_dev_databases WHERE instance_id = $1")
+            .bind(instance_id)
+            .execute(&self.db_pool)
+            .await?;
+        self.dev_database_indexes.invalidate(&instance_id).await;
+        Ok(())
+    }
+
+    /// Drop a cached password so the next lookup reads the database
+    pub async fn invalidate(&self, instance_id: Uuid) {
+        self.passwords.invalidate(&instance_id).await;
+    }
+
+    /// Use a password for an instance without the database, until it expires from the cache
+    ///
+    /// For tests and tools that run without PostgreSQL.
+    pub async fn preload_password(&self, instance_id: Uuid, password: Option<String>) {
+        self.passwords.insert(instance_id, password).await;
+    }
+
+    /// Re-encrypt passwords still stored under a retired key version
+    ///
+    /// Returns the number of passwords that were re-encrypted.
+    pub async fn reencrypt_stale(&self) -> Result<u64, CredentialsError> {
+        let rows: Vec<(Uuid, String, i32)> = sqlx::query_as(
+            "SELECT instance_id, password_ciphertext, key_version FROM redis_instance_credentials WHERE key_version <> $1",
+        )
+        .bind(self.cipher.current_version())
+        .fetch_all(&self.db_pool)
+        .await?;
+
+        let mut reencrypted = 0;
+        for (instance_id, ciphertext, key_version) in rows {
+            let secret = EncryptedSecret { ciphertext, key_version };
+            let password = match self.cipher.decrypt(&secret, instance_id.as_bytes()) {
+                Ok(password) => password,
+                Err(e) => {
+                    error!("Cannot re-encrypt password of instance {}: {}", instance_id, e);
+                    continue;
+                }
+            };
+
+            self.store_instance_password(&self.db_pool, instance_id, &password).await?;
+            reencrypted += 1;
+        }
+
+        Ok(reencrypted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cipher() -> CredentialCipher {
+        CredentialCipher::new(2, &[2u8; KEY_LEN], vec![(1, vec![1u8; KEY_LEN])]).unwrap()
+    }
+
+    #[test]
+    fn test_encrypt_decrypt_roundtrip() {
+        let cipher = cipher();
+        let secret = cipher.encrypt("s3cr3t!@#", b"instance-a").unwrap();
+
+        assert_eq!(secret.key_version, 2);
+        assert_ne!(secret.ciphertext, "s3cr3t!@#");
+        assert_eq!(cipher.decrypt(&secret, b"instance-a").unwrap(), "s3cr3t!@#");
+    }
+
+    #[test]
+    fn test_nonce_is_random() {
+        let cipher = cipher();
+        let first = cipher.encrypt("same", b"ctx").unwrap();
+        let second = cipher.encrypt("same", b"ctx").unwrap();
+        assert_ne!(first.ciphertext, second.ciphertext);
+    }
+
+    #[test]
+    fn test_wrong_context_fails() {
+        let cipher = cipher();
+        let secret = cipher.encrypt("password", b"instance-a").unwrap();
+        assert!(cipher.decrypt(&secret, b"instance-b").is_err());
+    }
+
+    #[test]
+    fn test_previous_key_still_decrypts() {
+        let old = CredentialCipher::new(1, &[1u8; KEY_LEN], vec![]).unwrap();
+        let secret = old.encrypt("password", b"ctx").unwrap();
+
+        assert_eq!(cipher().decrypt(&secret, b"ctx").unwrap(), "password");
+    }
+
+    #[test]
+    fn test_unknown_key_version() {
+        let secret = EncryptedSecret {
+            ciphertext: BASE64.encode([0u8; 32]),
+            key_version: 9,
+        };
+        assert!(matches!(
+            cipher().decrypt(&secret, b"ctx"),
+            Err(CredentialsError::UnknownKeyVersion(9))
+        ));
+    }
+
+    #[test]
+    fn test_decode_key_rejects_wrong_length() {
+        assert!(decode_key(&BASE64.encode([0u8; 16])).is_err());
+        assert!(decode_key("not base64!").is_err());
+        assert_eq!(decode_key(&BASE64.encode([5u8; KEY_LEN])).unwrap(), vec![5u8; KEY_LEN]);
+    }
+
+    #[test]
+    fn test_key_file_is_created_and_reused() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keys").join("master.key");
+
+        let created = load_or_create_key_file(&path).unwrap();
+        let loaded = load_or_create_key_file(&path).unwrap();
+
+        assert_eq!(created.len(), KEY_LEN);
+        assert_eq!(created, loaded);
+    }
+}
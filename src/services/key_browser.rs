@@ -0,0 +1,268 @@
+// Key browser: SCAN pages of keys with their type, TTL, size and a short preview
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::cell::Cell;
+use thiserror::Error;
+
+use crate::services::{sorted_set, stream};
+
+/// Elements shown in the preview of a list, set, sorted set, hash or stream
+pub const PREVIEW_ITEMS: usize = 10;
+
+/// Characters kept of a string value or collection element in a preview
+pub const PREVIEW_CHARS: usize = 256;
+
+/// Key types accepted by the `type` filter, as reported by TYPE
+pub const KEY_TYPES: &[&str] = &["string", "list", "set", "zset", "hash", "stream"];
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum KeyBrowserError {
+    #[error("Invalid cursor")]
+    InvalidCursor,
+
+    #[error("Unknown key type: {0}")]
+    UnknownType(String),
+}
+
+/// One key of a browser page
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct KeyInfo {
+    pub key: String,
+    #[serde(rename = "type")]
+    pub key_type: String,
+    /// Remaining time to live in milliseconds, `None` if the key does not expire
+    pub ttl_ms: Option<i64>,
+    /// Bytes used by the key and its value (MEMORY USAGE), if the API key may run MEMORY
+    pub memory_usage: Option<i64>,
+    /// Length of the string, or number of elements of a collection
+    pub length: Option<i64>,
+    pub preview: Value,
+    /// Whether `preview` shows only part of the value
+    pub truncated: bool,
+}
+
+/// Encode a SCAN cursor for clients; `None` once the scan is complete
+pub fn encode_cursor(cursor: u64) -> Option<String> {
+    (cursor != 0).then(|| URL_SAFE_NO_PAD.encode(cursor.to_string()))
+}
+
+/// Decode a cursor returned by `encode_cursor`
+pub fn decode_cursor(cursor: &str) -> Result<u64, KeyBrowserError> {
+    let bytes = URL_SAFE_NO_PAD
+        .decode(cursor)
+        .map_err(|_| KeyBrowserError::InvalidCursor)?;
+    std::str::from_utf8(&bytes)
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .ok_or(KeyBrowserError::InvalidCursor)
+}
+
+/// Validate a `type` filter
+pub fn parse_type(key_type: &str) -> Result<String, KeyBrowserError> {
+    let key_type = key_type.to_lowercase();
+    if KEY_TYPES.contains(&key_type.as_str()) {
+        Ok(key_type)
+    } else {
+        Err(KeyBrowserError::UnknownType(key_type))
+    }
+}
+
+/// Command returning the length of a key of this type, and the one fetching its preview
+pub fn preview_commands(key_type: &str) -> Option<(&'static str, &'static str)> {
+    match key_type {
+        "string" => Some(("STRLEN", "GETRANGE")),
+        "list" => Some(("LLEN", "LRANGE")),
+        "set" => Some(("SCARD", "SSCAN")),
+        "zset" => Some(("ZCARD", "ZRANGE")),
+        "hash" => Some(("HLEN", "HSCAN")),
+        "stream" => Some(("XLEN", "XRANGE")),
+        _ => None,
+    }
+}
+
+/// Arguments after the key for the preview command of a type
+pub fn preview_args(key_type: &str) -> Vec<String> {
+    let last = (PREVIEW_ITEMS - 1).to_string();
+    let count = PREVIEW_ITEMS.to_string();
+    let args: Vec<&str> = match key_type {
+        // Up to 4 bytes per UTF-8 character; GETRANGE includes the end offset
+        "string" => return vec!["0".to_string(), (PREVIEW_CHARS * 4).to_string()],
+        "list" => vec!["0", &last],
+        "set" | "hash" => vec!["0", "COUNT", &count],
+        "zset" => vec!["0", &last, "WITHSCORES"],
+        "stream" => vec!["-", "+", "COUNT", &count],
+        _ => vec![],
+    };
+    args.into_iter().map(str::to_string).collect()
+}
+
+fn text(value: &redis::Value) -> Option<String> {
+    match value {
+        redis::Value::Data(bytes) => Some(String::from_utf8_lossy(bytes).into_owned()),
+        redis::Value::Status(s) => Some(s.clone()),
+        redis::Value::Int(i) => Some(i.to_string()),
+        _ => None,
+    }
+}
+
+/// Shorten a string to `PREVIEW_CHARS` characters, returning whether it was cut
+fn truncate(text: String) -> (String, bool) {
+    match text.char_indices().nth(PREVIEW_CHARS) {
+        Some((end, _)) => (text[..end].to_string(), true),
+        None => (text, false),
+    }
+}
+
+/// The elements of an SSCAN/HSCAN reply (`[cursor, [elements...]]`)
+fn scan_elements(value: &redis::Value) -> Option<&[redis::Value]> {
+    match value {
+        redis::Value::Bulk(parts) => match parts.as_slice() {
+            [_, redis::Value::Bulk(elements)] => Some(elements),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Build the preview of a value from the reply of its preview command
+///
+/// Returns the preview and whether it is truncated; `length` is the element count (or the
+/// string length) reported by the length command.
+pub fn build_preview(key_type: &str, reply: &redis::Value, length: Option<i64>) -> (Value, bool) {
+    let cut = Cell::new(false);
+    let short = |text: String| -> String {
+        let (text, truncated) = truncate(text);
+        cut.set(cut.get() || truncated);
+        text
+    };
+    let element = |value: &redis::Value| text(value).map(|value| Value::String(short(value)));
+
+    let preview = match key_type {
+        "string" => element(reply),
+        "list" => match reply {
+            redis::Value::Bulk(items) => items.iter().map(element).collect(),
+            _ => None,
+        },
+        "set" => scan_elements(reply)
+            .and_then(|members| members.iter().take(PREVIEW_ITEMS).map(element).collect()),
+        "hash" => scan_elements(reply).and_then(|pairs| {
+            pairs
+                .chunks(2)
+                .take(PREVIEW_ITEMS)
+                .map(|pair| Some((short(text(pair.first()?)?), element(pair.get(1)?)?)))
+                .collect::<Option<Map<_, _>>>()
+                .map(Value::Object)
+        }),
+        "zset" => sorted_set::scored_members(reply).and_then(|members| {
+            let members: Vec<_> = members
+                .into_iter()
+                .map(|mut entry| {
+                    entry.member = short(entry.member);
+                    entry
+                })
+                .collect();
+            serde_json::to_value(members).ok()
+        }),
+        "stream" => stream::entries(reply).and_then(|entries| serde_json::to_value(entries).ok()),
+        _ => None,
+    };
+
+    // What the preview covers, in the unit of `length`
+    let shown = match (reply, &preview) {
+        (redis::Value::Data(bytes), _) if key_type == "string" => bytes.len() as i64,
+        (_, Some(Value::Array(items))) => items.len() as i64,
+        (_, Some(Value::Object(fields))) => fields.len() as i64,
+        _ => 0,
+    };
+    let truncated = cut.get() || length.is_some_and(|length| length > shown);
+
+    (preview.unwrap_or(Value::Null), truncated)
+}
+
+/// Interpret a PTTL reply: `None` for keys without expiry
+pub fn ttl_ms(reply: &redis::Value) -> Option<i64> {
+    match reply {
+        redis::Value::Int(ttl) if *ttl >= 0 => Some(*ttl),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn data(s: &str) -> redis::Value {
+        redis::Value::Data(s.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn test_cursor_round_trip() {
+        assert_eq!(encode_cursor(0), None);
+        let cursor = encode_cursor(1536).unwrap();
+        assert_eq!(decode_cursor(&cursor), Ok(1536));
+        assert_eq!(decode_cursor("not a cursor"), Err(KeyBrowserError::InvalidCursor));
+        assert_eq!(
+            decode_cursor(&URL_SAFE_NO_PAD.encode("abc")),
+            Err(KeyBrowserError::InvalidCursor)
+        );
+    }
+
+    #[test]
+    fn test_parse_type() {
+        assert_eq!(parse_type("ZSet"), Ok("zset".to_string()));
+        assert_eq!(parse_type("json"), Err(KeyBrowserError::UnknownType("json".to_string())));
+    }
+
+    #[test]
+    fn test_string_preview() {
+        assert_eq!(
+            build_preview("string", &data("hello"), Some(5)),
+            (json!("hello"), false)
+        );
+
+        let long = "x".repeat(PREVIEW_CHARS + 10);
+        let (preview, truncated) = build_preview("string", &data(&long), Some(long.len() as i64));
+        assert_eq!(preview.as_str().unwrap().len(), PREVIEW_CHARS);
+        assert!(truncated);
+    }
+
+    #[test]
+    fn test_collection_previews() {
+        let list = redis::Value::Bulk(vec![data("a"), data("b")]);
+        assert_eq!(build_preview("list", &list, Some(2)), (json!(["a", "b"]), false));
+        assert_eq!(build_preview("list", &list, Some(50)), (json!(["a", "b"]), true));
+
+        let hash = redis::Value::Bulk(vec![
+            data("0"),
+            redis::Value::Bulk(vec![data("name"), data("alice"), data("age"), data("30")]),
+        ]);
+        assert_eq!(
+            build_preview("hash", &hash, Some(2)),
+            (json!({"name": "alice", "age": "30"}), false)
+        );
+
+        let set = redis::Value::Bulk(vec![data("17"), redis::Value::Bulk(vec![data("m1")])]);
+        assert_eq!(build_preview("set", &set, Some(40)), (json!(["m1"]), true));
+
+        let zset = redis::Value::Bulk(vec![data("alice"), data("3")]);
+        assert_eq!(
+            build_preview("zset", &zset, Some(1)),
+            (json!([{"member": "alice", "score": 3.0}]), false)
+        );
+    }
+
+    #[test]
+    fn test_unknown_type_has_no_preview() {
+        assert_eq!(preview_commands("ReJSON-RL"), None);
+        assert_eq!(build_preview("ReJSON-RL", &redis::Value::Nil, None), (Value::Null, false));
+    }
+
+    #[test]
+    fn test_ttl() {
+        assert_eq!(ttl_ms(&redis::Value::Int(1500)), Some(1500));
+        assert_eq!(ttl_ms(&redis::Value::Int(-1)), None);
+    }
+}
@@ -0,0 +1,1459 @@
+// In-memory Redis backend, for tests and for running the gateway without a Redis server
+
+use futures::future::BoxFuture;
+use redis::aio::{ConnectionLike, PubSub};
+use redis::{Arg, Cmd, ErrorKind, Pipeline, RedisError, RedisFuture, RedisResult, Value};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::services::redis_backend::{BackendConnection, RedisBackend};
+use crate::services::redis_pool::RedisEndpoint;
+
+/// COUNT of SCAN, SSCAN and HSCAN when none is given
+const DEFAULT_SCAN_COUNT: usize = 10;
+
+/// Keeps data in process, one keyspace per endpoint (host, port and database)
+///
+/// Covers strings, hashes, lists, sets, sorted sets and expiry, plus MULTI/EXEC with WATCH.
+/// Blocking pops return at once instead of waiting. Pub/Sub, streams and scripting are not
+/// supported: their commands fail as unknown commands.
+#[derive(Clone, Default)]
+pub struct MemoryBackend {
+    keyspaces: Arc<Mutex<HashMap<String, Arc<Mutex<Keyspace>>>>>,
+}
+
+impl MemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Open a connection to the keyspace of an endpoint
+    pub fn connect(&self, endpoint: &RedisEndpoint) -> MemoryConnection {
+        let name = format!("{}:{}/{}", endpoint.host, endpoint.port, endpoint.database);
+        let keyspace = self
+            .keyspaces
+            .lock()
+            .expect("keyspace registry lock poisoned")
+            .entry(name)
+            .or_default()
+            .clone();
+
+        MemoryConnection {
+            keyspace,
+            queued: None,
+            watched: Vec::new(),
+            unread: VecDeque::new(),
+        }
+    }
+}
+
+impl RedisBackend for MemoryBackend {
+    fn connection<'a>(
+        &'a self,
+        _instance_id: Uuid,
+        endpoint: &'a RedisEndpoint,
+    ) -> BoxFuture<'a, Result<BackendConnection, String>> {
+        Box::pin(async move { Ok(BackendConnection::new(self.connect(endpoint))) })
+    }
+
+    fn dedicated_connection<'a>(&'a self, endpoint: &'a RedisEndpoint) -> BoxFuture<'a, Result<BackendConnection, String>> {
+        Box::pin(async move { Ok(BackendConnection::new(self.connect(endpoint))) })
+    }
+
+    fn pubsub<'a>(&'a self, _endpoint: &'a RedisEndpoint) -> BoxFuture<'a, Result<PubSub, String>> {
+        Box::pin(async { Err("Pub/Sub is not supported by the in-memory backend".to_string()) })
+    }
+
+    fn subscriber<'a>(&'a self, _endpoint: &'a RedisEndpoint) -> BoxFuture<'a, Result<BackendConnection, String>> {
+        Box::pin(async { Err("Pub/Sub is not supported by the in-memory backend".to_string()) })
+    }
+}
+
+/// A connection to one in-memory keyspace
+///
+/// Each connection has its own MULTI and WATCH state, like a connection to a server.
+pub struct MemoryConnection {
+    keyspace: Arc<Mutex<Keyspace>>,
+    /// Commands queued since MULTI
+    queued: Option<Vec<Vec<Vec<u8>>>>,
+    /// Keys watched since WATCH, with their version at the time
+    watched: Vec<(Vec<u8>, u64)>,
+    /// Replies to pipelined commands that were not read yet, as they would wait on a socket
+    unread: VecDeque<RedisResult<Value>>,
+}
+
+impl MemoryConnection {
+    /// Run a command, handling transaction commands here and the rest in the keyspace
+    fn run(&mut self, args: Vec<Vec<u8>>) -> RedisResult<Value> {
+        let name = args.first().map(|name| upper(name)).unwrap_or_default();
+
+        match (name.as_str(), self.queued.is_some()) {
+            ("MULTI", true) => Err(server_error("ERR MULTI calls can not be nested")),
+            ("MULTI", false) => {
+                self.queued = Some(Vec::new());
+                Ok(Value::Okay)
+            }
+            ("EXEC", false) => Err(server_error("ERR EXEC without MULTI")),
+            ("EXEC", true) => {
+                let commands = self.queued.take().unwrap_or_default();
+                self.exec(commands)
+            }
+            ("DISCARD", false) => Err(server_error("ERR DISCARD without MULTI")),
+            ("DISCARD", true) => {
+                self.queued = None;
+                self.watched.clear();
+                Ok(Value::Okay)
+            }
+            ("WATCH", true) => Err(server_error("ERR WATCH inside MULTI is not allowed")),
+            ("WATCH", false) => {
+                if args.len() < 2 {
+                    return Err(wrong_arity("watch"));
+                }
+                let keyspace = self.keyspace.lock().expect("keyspace lock poisoned");
+                for key in &args[1..] {
+                    self.watched.push((key.clone(), keyspace.version(key)));
+                }
+                Ok(Value::Okay)
+            }
+            ("UNWATCH", false) => {
+                self.watched.clear();
+                Ok(Value::Okay)
+            }
+            (_, true) => {
+                self.queued.get_or_insert_with(Vec::new).push(args);
+                Ok(Value::Status("QUEUED".to_string()))
+            }
+            (_, false) => self.keyspace.lock().expect("keyspace lock poisoned").execute(&args),
+        }
+    }
+
+    /// Run queued commands as one step, or reply nil if a watched key changed
+    fn exec(&mut self, commands: Vec<Vec<Vec<u8>>>) -> RedisResult<Value> {
+        let watched = std::mem::take(&mut self.watched);
+        let mut keyspace = self.keyspace.lock().expect("keyspace lock poisoned");
+        if watched.iter().any(|(key, version)| keyspace.version(key) != *version) {
+            return Ok(Value::Nil);
+        }
+
+        // Like Redis, the remaining commands still run after one fails; redis-rs then
+        // reports the first error for the whole EXEC reply
+        let mut replies = Vec::with_capacity(commands.len());
+        let mut first_error = None;
+        for args in &commands {
+            match keyspace.execute(args) {
+                Ok(reply) => replies.push(reply),
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(Value::Bulk(replies)),
+        }
+    }
+
+    /// Skip `offset` unread replies and return the next `count`, or the first error among
+    /// them, like a connection to a server
+    ///
+    /// Replies left unread stay for the next call, so commands can be written with a count
+    /// of 0 and their replies read one at a time with an empty pipeline.
+    fn read_replies(&mut self, offset: usize, count: usize) -> RedisResult<Vec<Value>> {
+        let mut replies = Vec::with_capacity(count);
+        let mut first_error = None;
+        for index in 0..offset + count {
+            let Some(reply) = self.unread.pop_front() else {
+                return Err(RedisError::from(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "No reply to read",
+                )));
+            };
+            match reply {
+                Ok(reply) if index >= offset => replies.push(reply),
+                Ok(_) => {}
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(replies),
+        }
+    }
+
+    /// MULTI, the commands and EXEC, as redis-rs sends an atomic pipeline
+    fn atomic(&mut self, commands: Vec<Vec<Vec<u8>>>) -> RedisResult<Value> {
+        self.run(vec![b"MULTI".to_vec()])?;
+        for args in commands {
+            self.run(args)?;
+        }
+        self.run(vec![b"EXEC".to_vec()])
+    }
+}
+
+impl ConnectionLike for MemoryConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        let result = self.run(command_args(cmd));
+        Box::pin(async move { result })
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        pipeline: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        let commands: Vec<Vec<Vec<u8>>> = pipeline.cmd_iter().map(command_args).collect();
+
+        // For an atomic pipeline redis-rs only reads the EXEC reply, which comes after
+        // MULTI and one QUEUED per command
+        let result = if offset == commands.len() + 1 && count == 1 {
+            self.atomic(commands).map(|reply| vec![reply])
+        } else {
+            for args in commands {
+                let reply = self.run(args);
+                self.unread.push_back(reply);
+            }
+            self.read_replies(offset, count)
+        };
+
+        Box::pin(async move { result })
+    }
+
+    fn get_db(&self) -> i64 {
+        0
+    }
+}
+
+fn command_args(cmd: &Cmd) -> Vec<Vec<u8>> {
+    cmd.args_iter()
+        .map(|arg| match arg {
+            Arg::Simple(bytes) => bytes.to_vec(),
+            Arg::Cursor => b"0".to_vec(),
+        })
+        .collect()
+}
+
+/// An error as Redis replies it, e.g. `WRONGTYPE Operation against a key...`
+///
+/// Parsed from an error reply, so its kind, code and detail are those of a server error.
+fn server_error(message: &str) -> RedisError {
+    match redis::parse_redis_value(format!("-{}\r\n", message).as_bytes()) {
+        Err(e) => e,
+        Ok(_) => RedisError::from((
+            ErrorKind::ResponseError,
+            "An error was signalled by the server",
+            message.to_string(),
+        )),
+    }
+}
+
+fn wrong_type() -> RedisError {
+    server_error("WRONGTYPE Operation against a key holding the wrong kind of value")
+}
+
+fn wrong_arity(command: &str) -> RedisError {
+    server_error(&format!("ERR wrong number of arguments for '{}' command", command.to_lowercase()))
+}
+
+fn syntax_error() -> RedisError {
+    server_error("ERR syntax error")
+}
+
+fn upper(arg: &[u8]) -> String {
+    String::from_utf8_lossy(arg).to_uppercase()
+}
+
+fn int(arg: &[u8]) -> RedisResult<i64> {
+    std::str::from_utf8(arg)
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| server_error("ERR value is not an integer or out of range"))
+}
+
+fn float(arg: &[u8]) -> RedisResult<f64> {
+    std::str::from_utf8(arg)
+        .ok()
+        .and_then(|s| s.parse::<f64>().ok())
+        .filter(|value| !value.is_nan())
+        .ok_or_else(|| server_error("ERR value is not a valid float"))
+}
+
+/// A score the way Redis prints it: `3`, `1.5`, `inf`, `-inf`
+fn format_score(score: f64) -> Vec<u8> {
+    let text = if score.is_infinite() {
+        if score > 0.0 { "inf".to_string() } else { "-inf".to_string() }
+    } else if score.fract() == 0.0 && score.abs() < 1e17 {
+        (score as i64).to_string()
+    } else {
+        score.to_string()
+    };
+    text.into_bytes()
+}
+
+fn bulk(bytes: &[u8]) -> Value {
+    Value::Data(bytes.to_vec())
+}
+
+/// Resolve a Redis index range (negative indexes count from the end) to inclusive bounds
+fn index_range(start: i64, stop: i64, len: usize) -> Option<(usize, usize)> {
+    let len = len as i64;
+    let start = if start < 0 { (len + start).max(0) } else { start };
+    let stop = if stop < 0 { len + stop } else { stop.min(len - 1) };
+    if start > stop || start >= len {
+        None
+    } else {
+        Some((start as usize, stop as usize))
+    }
+}
+
+/// One end of a score range: `1.5`, `(1.5` (exclusive), `-inf`, `+inf`
+fn score_bound(arg: &[u8]) -> RedisResult<(f64, bool)> {
+    let (exclusive, score) = match arg.strip_prefix(b"(") {
+        Some(score) => (true, score),
+        None => (false, arg),
+    };
+    float(score)
+        .map(|score| (score, exclusive))
+        .map_err(|_| server_error("ERR min or max is not a float"))
+}
+
+fn above(score: f64, (bound, exclusive): (f64, bool)) -> bool {
+    if exclusive { score > bound } else { score >= bound }
+}
+
+fn below(score: f64, (bound, exclusive): (f64, bool)) -> bool {
+    if exclusive { score < bound } else { score <= bound }
+}
+
+/// Match Redis glob-style patterns: `*`, `?`, `[abc]`, `[^a-z]` and `\` escapes
+fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) => (0..=text.len()).any(|skip| glob_match(rest, &text[skip..])),
+        Some((b'?', rest)) => !text.is_empty() && glob_match(rest, &text[1..]),
+        Some((b'[', rest)) => match text.split_first() {
+            None => false,
+            Some((c, text_rest)) => match class_match(rest, *c) {
+                Some((matched, after)) => matched && glob_match(after, text_rest),
+                // No closing bracket: the `[` is literal
+                None => *c == b'[' && glob_match(rest, text_rest),
+            },
+        },
+        Some((b'\\', [escaped, rest @ ..])) => text.first() == Some(escaped) && glob_match(rest, &text[1..]),
+        Some((c, rest)) => text.first() == Some(c) && glob_match(rest, &text[1..]),
+    }
+}
+
+/// Match a character against a class (the pattern after `[`), returning whether it
+/// matched and the pattern after the closing `]`
+fn class_match(class: &[u8], c: u8) -> Option<(bool, &[u8])> {
+    let (negate, mut class) = match class.split_first() {
+        Some((b'^', rest)) => (true, rest),
+        _ => (false, class),
+    };
+
+    let mut matched = false;
+    loop {
+        match class {
+            [] => return None,
+            [b']', rest @ ..] => return Some((matched != negate, rest)),
+            [b'\\', escaped, rest @ ..] => {
+                matched |= *escaped == c;
+                class = rest;
+            }
+            [low, b'-', high, rest @ ..] if *high != b']' => {
+                let (low, high) = if low <= high { (*low, *high) } else { (*high, *low) };
+                matched |= (low..=high).contains(&c);
+                class = rest;
+            }
+            [other, rest @ ..] => {
+                matched |= *other == c;
+                class = rest;
+            }
+        }
+    }
+}
+
+/// MATCH, COUNT and TYPE options of the SCAN family
+struct ScanOptions {
+    pattern: Option<Vec<u8>>,
+    count: usize,
+    key_type: Option<String>,
+}
+
+impl ScanOptions {
+    fn parse(args: &[Vec<u8>], allow_type: bool) -> RedisResult<Self> {
+        let mut options = Self {
+            pattern: None,
+            count: DEFAULT_SCAN_COUNT,
+            key_type: None,
+        };
+
+        let mut args = args.iter();
+        while let Some(option) = args.next() {
+            let value = args.next().ok_or_else(syntax_error)?;
+            match upper(option).as_str() {
+                "MATCH" => options.pattern = Some(value.clone()),
+                "COUNT" => {
+                    options.count = usize::try_from(int(value)?)
+                        .ok()
+                        .filter(|count| *count > 0)
+                        .ok_or_else(syntax_error)?;
+                }
+                "TYPE" if allow_type => options.key_type = Some(String::from_utf8_lossy(value).to_lowercase()),
+                _ => return Err(syntax_error()),
+            }
+        }
+        Ok(options)
+    }
+
+    fn matches(&self, text: &[u8]) -> bool {
+        match &self.pattern {
+            Some(pattern) => glob_match(pattern, text),
+            None => true,
+        }
+    }
+}
+
+fn scan_cursor(arg: &[u8]) -> RedisResult<usize> {
+    std::str::from_utf8(arg)
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| server_error("ERR invalid cursor"))
+}
+
+/// One page of a cursor scan over items in a stable order: the next cursor and the page
+fn scan_page<T>(items: Vec<T>, cursor: usize, count: usize) -> (usize, Vec<T>) {
+    let end = cursor.saturating_add(count);
+    let next = if end >= items.len() { 0 } else { end };
+    (next, items.into_iter().skip(cursor).take(count).collect())
+}
+
+fn scan_reply(next: usize, items: Vec<Value>) -> Value {
+    Value::Bulk(vec![bulk(next.to_string().as_bytes()), Value::Bulk(items)])
+}
+
+/// Members of a sorted set with their scores
+#[derive(Default)]
+struct SortedSet(HashMap<Vec<u8>, f64>);
+
+impl SortedSet {
+    /// Members ordered by score, then lexicographically
+    fn ordered(&self) -> Vec<(&Vec<u8>, f64)> {
+        let mut members: Vec<_> = self.0.iter().map(|(member, score)| (member, *score)).collect();
+        members.sort_by(|a, b| a.1.total_cmp(&b.1).then_with(|| a.0.cmp(b.0)));
+        members
+    }
+}
+
+enum Data {
+    String(Vec<u8>),
+    Hash(BTreeMap<Vec<u8>, Vec<u8>>),
+    List(VecDeque<Vec<u8>>),
+    Set(BTreeSet<Vec<u8>>),
+    SortedSet(SortedSet),
+}
+
+impl Data {
+    fn type_name(&self) -> &'static str {
+        match self {
+            Data::String(_) => "string",
+            Data::Hash(_) => "hash",
+            Data::List(_) => "list",
+            Data::Set(_) => "set",
+            Data::SortedSet(_) => "zset",
+        }
+    }
+
+    /// Collections are deleted once empty, as in Redis
+    fn is_empty(&self) -> bool {
+        match self {
+            Data::String(_) => false,
+            Data::Hash(hash) => hash.is_empty(),
+            Data::List(list) => list.is_empty(),
+            Data::Set(set) => set.is_empty(),
+            Data::SortedSet(set) => set.0.is_empty(),
+        }
+    }
+}
+
+/// Access to the value of a key of one type
+trait Kind: Default {
+    fn of(data: &mut Data) -> Option<&mut Self>;
+    fn into_data(self) -> Data;
+}
+
+impl Kind for Vec<u8> {
+    fn of(data: &mut Data) -> Option<&mut Self> {
+        match data {
+            Data::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn into_data(self) -> Data {
+        Data::String(self)
+    }
+}
+
+impl Kind for BTreeMap<Vec<u8>, Vec<u8>> {
+    fn of(data: &mut Data) -> Option<&mut Self> {
+        match data {
+            Data::Hash(hash) => Some(hash),
+            _ => None,
+        }
+    }
+
+    fn into_data(self) -> Data {
+        Data::Hash(self)
+    }
+}
+
+impl Kind for VecDeque<Vec<u8>> {
+    fn of(data: &mut Data) -> Option<&mut Self> {
+        match data {
+            Data::List(list) => Some(list),
+            _ => None,
+        }
+    }
+
+    fn into_data(self) -> Data {
+        Data::List(self)
+    }
+}
+
+impl Kind for BTreeSet<Vec<u8>> {
+    fn of(data: &mut Data) -> Option<&mut Self> {
+        match data {
+            Data::Set(set) => Some(set),
+            _ => None,
+        }
+    }
+
+    fn into_data(self) -> Data {
+        Data::Set(self)
+    }
+}
+
+impl Kind for SortedSet {
+    fn of(data: &mut Data) -> Option<&mut Self> {
+        match data {
+            Data::SortedSet(set) => Some(set),
+            _ => None,
+        }
+    }
+
+    fn into_data(self) -> Data {
+        Data::SortedSet(self)
+    }
+}
+
+type Hash = BTreeMap<Vec<u8>, Vec<u8>>;
+type List = VecDeque<Vec<u8>>;
+type Set = BTreeSet<Vec<u8>>;
+
+struct Entry {
+    data: Data,
+    expires_at: Option<Instant>,
+}
+
+impl Entry {
+    fn expired(&self) -> bool {
+        self.expires_at.is_some_and(|at| at <= Instant::now())
+    }
+}
+
+/// The keys of one database
+#[derive(Default)]
+struct Keyspace {
+    entries: HashMap<Vec<u8>, Entry>,
+    /// Version of each key written so far, for WATCH
+    versions: HashMap<Vec<u8>, u64>,
+    writes: u64,
+}
+
+impl Keyspace {
+    /// The entry of a key, dropping it first if it has expired
+    fn live(&mut self, key: &[u8]) -> Option<&mut Entry> {
+        if self.entries.get(key).is_some_and(Entry::expired) {
+            self.entries.remove(key);
+        }
+        self.entries.get_mut(key)
+    }
+
+    fn purge_expired(&mut self) {
+        self.entries.retain(|_, entry| !entry.expired());
+    }
+
+    /// The value of a key if it exists and has type `T`
+    fn get<T: Kind>(&mut self, key: &[u8]) -> RedisResult<Option<&mut T>> {
+        match self.live(key) {
+            None => Ok(None),
+            Some(entry) => T::of(&mut entry.data).map(Some).ok_or_else(wrong_type),
+        }
+    }
+
+    /// The value of a key, created empty if the key does not exist
+    fn get_or_default<T: Kind>(&mut self, key: &[u8]) -> RedisResult<&mut T> {
+        if self.get::<T>(key)?.is_none() {
+            let entry = Entry {
+                data: T::default().into_data(),
+                expires_at: None,
+            };
+            self.entries.insert(key.to_vec(), entry);
+        }
+        Ok(self.get::<T>(key)?.expect("key was just created"))
+    }
+
+    fn remove_if_empty(&mut self, key: &[u8]) {
+        if self.entries.get(key).is_some_and(|entry| entry.data.is_empty()) {
+            self.entries.remove(key);
+        }
+    }
+
+    /// Record a write to a key, failing transactions that watch it
+    fn modified(&mut self, key: &[u8]) {
+        self.writes += 1;
+        self.versions.insert(key.to_vec(), self.writes);
+    }
+
+    fn version(&self, key: &[u8]) -> u64 {
+        self.versions.get(key).copied().unwrap_or(0)
+    }
+
+    fn delete(&mut self, key: &[u8]) -> bool {
+        if self.live(key).is_none() {
+            return false;
+        }
+        self.entries.remove(key);
+        self.modified(key);
+        true
+    }
+
+    /// Sorted keys that have not expired
+    fn keys(&mut self) -> Vec<Vec<u8>> {
+        self.purge_expired();
+        let mut keys: Vec<_> = self.entries.keys().cloned().collect();
+        keys.sort();
+        keys
+    }
+
+    fn execute(&mut self, command: &[Vec<u8>]) -> RedisResult<Value> {
+        let Some((name, args)) = command.split_first() else {
+            return Err(server_error("ERR empty command"));
+        };
+        let name = upper(name);
+        let arity = |min: usize| {
+            if args.len() < min {
+                Err(wrong_arity(&name))
+            } else {
+                Ok(())
+            }
+        };
+
+        match name.as_str() {
+            // Connection and keyspace
+            "PING" => Ok(match args.first() {
+                Some(message) => bulk(message),
+                None => Value::Status("PONG".to_string()),
+            }),
+            "ECHO" => {
+                arity(1)?;
+                Ok(bulk(&args[0]))
+            }
+            "DBSIZE" => Ok(Value::Int(self.keys().len() as i64)),
+            "FLUSHDB" | "FLUSHALL" => {
+                for key in self.keys() {
+                    self.modified(&key);
+                }
+                self.entries.clear();
+                Ok(Value::Okay)
+            }
+            "DEL" | "UNLINK" => {
+                arity(1)?;
+                Ok(Value::Int(args.iter().filter(|key| self.delete(key)).count() as i64))
+            }
+            "EXISTS" => {
+                arity(1)?;
+                Ok(Value::Int(args.iter().filter(|key| self.live(key).is_some()).count() as i64))
+            }
+            "TYPE" => {
+                arity(1)?;
+                let name = self.live(&args[0]).map_or("none", |entry| entry.data.type_name());
+                Ok(Value::Status(name.to_string()))
+            }
+            "EXPIRE" | "PEXPIRE" => {
+                arity(2)?;
+                let amount = int(&args[1])?;
+                let millis = if name == "EXPIRE" { amount.saturating_mul(1000) } else { amount };
+                if self.live(&args[0]).is_none() {
+                    return Ok(Value::Int(0));
+                }
+                if millis <= 0 {
+                    self.delete(&args[0]);
+                } else if let Some(entry) = self.live(&args[0]) {
+                    entry.expires_at = Some(Instant::now() + Duration::from_millis(millis as u64));
+                    self.modified(&args[0]);
+                }
+                Ok(Value::Int(1))
+            }
+            "TTL" | "PTTL" => {
+                arity(1)?;
+                let ttl = match self.live(&args[0]) {
+                    None => -2,
+                    Some(Entry { expires_at: None, .. }) => -1,
+                    Some(Entry {
+                        expires_at: Some(at), ..
+                    }) => {
+                        let millis = at.saturating_duration_since(Instant::now()).as_millis() as i64;
+                        if name == "TTL" { (millis + 500) / 1000 } else { millis }
+                    }
+                };
+                Ok(Value::Int(ttl))
+            }
+            "PERSIST" => {
+                arity(1)?;
+                let persisted = match self.live(&args[0]) {
+                    Some(entry) => entry.expires_at.take().is_some(),
+                    None => false,
+                };
+                if persisted {
+                    self.modified(&args[0]);
+                }
+                Ok(Value::Int(persisted as i64))
+            }
+            "KEYS" => {
+                arity(1)?;
+                let keys = self.keys().into_iter().filter(|key| glob_match(&args[0], key));
+                Ok(Value::Bulk(keys.map(Value::Data).collect()))
+            }
+            "SCAN" => {
+                arity(1)?;
+                let cursor = scan_cursor(&args[0])?;
+                let options = ScanOptions::parse(&args[1..], true)?;
+                let (next, keys) = scan_page(self.keys(), cursor, options.count);
+                let mut page = Vec::new();
+                for key in keys {
+                    let type_matches = match &options.key_type {
+                        Some(key_type) => self.live(&key).is_some_and(|entry| entry.data.type_name() == key_type.as_str()),
+                        None => true,
+                    };
+                    if type_matches && options.matches(&key) {
+                        page.push(Value::Data(key));
+                    }
+                }
+                Ok(scan_reply(next, page))
+            }
+
+            // Strings
+            "GET" => {
+                arity(1)?;
+                Ok(self.get::<Vec<u8>>(&args[0])?.map_or(Value::Nil, |value| bulk(value)))
+            }
+            "MGET" => {
+                arity(1)?;
+                let values = args
+                    .iter()
+                    .map(|key| match self.get::<Vec<u8>>(key) {
+                        Ok(Some(value)) => bulk(value),
+                        _ => Value::Nil,
+                    })
+                    .collect();
+                Ok(Value::Bulk(values))
+            }
+            "SET" => {
+                arity(2)?;
+                self.set(&args[0], &args[1], &args[2..])
+            }
+            "SETEX" | "PSETEX" => {
+                arity(3)?;
+                if int(&args[1])? <= 0 {
+                    return Err(server_error(&format!(
+                        "ERR invalid expire time in '{}' command",
+                        name.to_lowercase()
+                    )));
+                }
+                let unit: &[u8] = if name == "SETEX" { b"EX" } else { b"PX" };
+                self.set(&args[0], &args[2], &[unit.to_vec(), args[1].clone()])
+            }
+            "MSET" => {
+                if args.is_empty() || args.len() % 2 != 0 {
+                    return Err(wrong_arity(&name));
+                }
+                for pair in args.chunks(2) {
+                    self.entries.insert(
+                        pair[0].clone(),
+                        Entry {
+                            data: Data::String(pair[1].clone()),
+                            expires_at: None,
+                        },
+                    );
+                    self.modified(&pair[0]);
+                }
+                Ok(Value::Okay)
+            }
+            "GETDEL" => {
+                arity(1)?;
+                let value = self.get::<Vec<u8>>(&args[0])?.cloned();
+                if value.is_some() {
+                    self.delete(&args[0]);
+                }
+                Ok(value.map_or(Value::Nil, Value::Data))
+            }
+            "INCR" | "DECR" => {
+                arity(1)?;
+                self.incr_by(&args[0], if name == "INCR" { 1 } else { -1 })
+            }
+            "INCRBY" | "DECRBY" => {
+                arity(2)?;
+                let by = int(&args[1])?;
+                let by = if name == "INCRBY" {
+                    by
+                } else {
+                    by.checked_neg()
+                        .ok_or_else(|| server_error("ERR decrement would overflow"))?
+                };
+                self.incr_by(&args[0], by)
+            }
+            "APPEND" => {
+                arity(2)?;
+                let value = self.get_or_default::<Vec<u8>>(&args[0])?;
+                value.extend_from_slice(&args[1]);
+                let len = value.len();
+                self.modified(&args[0]);
+                Ok(Value::Int(len as i64))
+            }
+            "STRLEN" => {
+                arity(1)?;
+                Ok(Value::Int(self.get::<Vec<u8>>(&args[0])?.map_or(0, |value| value.len()) as i64))
+            }
+            "GETRANGE" => {
+                arity(3)?;
+                let (start, end) = (int(&args[1])?, int(&args[2])?);
+                let value = self.get::<Vec<u8>>(&args[0])?.map(|value| value.as_slice()).unwrap_or_default();
+                Ok(match index_range(start, end, value.len()) {
+                    Some((start, end)) => bulk(&value[start..=end]),
+                    None => bulk(b""),
+                })
+            }
+
+            // Hashes
+            "HSET" | "HMSET" => {
+                if args.len() < 3 || args.len() % 2 == 0 {
+                    return Err(wrong_arity(&name));
+                }
+                let hash = self.get_or_default::<Hash>(&args[0])?;
+                let added = args[1..]
+                    .chunks(2)
+                    .filter(|pair| hash.insert(pair[0].clone(), pair[1].clone()).is_none())
+                    .count();
+                self.modified(&args[0]);
+                Ok(if name == "HSET" { Value::Int(added as i64) } else { Value::Okay })
+            }
+            "HGET" => {
+                arity(2)?;
+                let value = self.get::<Hash>(&args[0])?.and_then(|hash| hash.get(&args[1]));
+                Ok(value.map_or(Value::Nil, |value| bulk(value)))
+            }
+            "HMGET" => {
+                arity(2)?;
+                let hash = self.get::<Hash>(&args[0])?;
+                let values = args[1..]
+                    .iter()
+                    .map(|field| {
+                        hash.as_ref()
+                            .and_then(|hash| hash.get(field))
+                            .map_or(Value::Nil, |value| bulk(value))
+                    })
+                    .collect();
+                Ok(Value::Bulk(values))
+            }
+            "HDEL" => {
+                arity(2)?;
+                let removed = match self.get::<Hash>(&args[0])? {
+                    Some(hash) => args[1..].iter().filter(|field| hash.remove(*field).is_some()).count(),
+                    None => 0,
+                };
+                if removed > 0 {
+                    self.remove_if_empty(&args[0]);
+                    self.modified(&args[0]);
+                }
+                Ok(Value::Int(removed as i64))
+            }
+            "HEXISTS" => {
+                arity(2)?;
+                let exists = self.get::<Hash>(&args[0])?.is_some_and(|hash| hash.contains_key(&args[1]));
+                Ok(Value::Int(exists as i64))
+            }
+            "HLEN" => {
+                arity(1)?;
+                Ok(Value::Int(self.get::<Hash>(&args[0])?.map_or(0, |hash| hash.len()) as i64))
+            }
+            "HGETALL" | "HKEYS" | "HVALS" => {
+                arity(1)?;
+                let Some(hash) = self.get::<Hash>(&args[0])? else {
+                    return Ok(Value::Bulk(vec![]));
+                };
+                let items = hash.iter().flat_map(|(field, value)| match name.as_str() {
+                    "HGETALL" => vec![bulk(field), bulk(value)],
+                    "HKEYS" => vec![bulk(field)],
+                    _ => vec![bulk(value)],
+                });
+                Ok(Value::Bulk(items.collect()))
+            }
+            "HINCRBY" => {
+                arity(3)?;
+                let by = int(&args[2])?;
+                let hash = self.get_or_default::<Hash>(&args[0])?;
+                let current = match hash.get(&args[1]) {
+                    Some(value) => int(value).map_err(|_| server_error("ERR hash value is not an integer"))?,
+                    None => 0,
+                };
+                let next = current
+                    .checked_add(by)
+                    .ok_or_else(|| server_error("ERR increment or decrement would overflow"))?;
+                hash.insert(args[1].clone(), next.to_string().into_bytes());
+                self.modified(&args[0]);
+                Ok(Value::Int(next))
+            }
+            "HSCAN" => {
+                arity(2)?;
+                let cursor = scan_cursor(&args[1])?;
+                let options = ScanOptions::parse(&args[2..], false)?;
+                let fields: Vec<(Vec<u8>, Vec<u8>)> = match self.get::<Hash>(&args[0])? {
+                    Some(hash) => hash.iter().map(|(f, v)| (f.clone(), v.clone())).collect(),
+                    None => vec![],
+                };
+                let (next, page) = scan_page(fields, cursor, options.count);
+                let items = page
+                    .into_iter()
+                    .filter(|(field, _)| options.matches(field))
+                    .flat_map(|(field, value)| [Value::Data(field), Value::Data(value)]);
+                Ok(scan_reply(next, items.collect()))
+            }
+
+            // Lists
+            "LPUSH" | "RPUSH" => {
+                arity(2)?;
+                let list = self.get_or_default::<List>(&args[0])?;
+                for value in &args[1..] {
+                    if name == "LPUSH" {
+                        list.push_front(value.clone());
+                    } else {
+                        list.push_back(value.clone());
+                    }
+                }
+                let len = list.len();
+                self.modified(&args[0]);
+                Ok(Value::Int(len as i64))
+            }
+            "LPOP" | "RPOP" => {
+                arity(1)?;
+                let count = args.get(1).map(|count| int(count)).transpose()?;
+                if count.is_some_and(|count| count < 0) {
+                    return Err(server_error("ERR value is out of range, must be positive"));
+                }
+                let Some(list) = self.get::<List>(&args[0])? else {
+                    return Ok(Value::Nil);
+                };
+                let mut popped = Vec::new();
+                for _ in 0..count.unwrap_or(1) {
+                    let value = if name == "LPOP" { list.pop_front() } else { list.pop_back() };
+                    match value {
+                        Some(value) => popped.push(Value::Data(value)),
+                        None => break,
+                    }
+                }
+                self.remove_if_empty(&args[0]);
+                if !popped.is_empty() {
+                    self.modified(&args[0]);
+                }
+                Ok(match count {
+                    Some(_) => Value::Bulk(popped),
+                    None => popped.pop().unwrap_or(Value::Nil),
+                })
+            }
+            // Nothing else can push while a command runs, so these return at once
+            "BLPOP" | "BRPOP" => {
+                arity(2)?;
+                for key in &args[..args.len() - 1] {
+                    let pop = if name == "BLPOP" { "LPOP" } else { "RPOP" };
+                    if let Value::Data(value) = self.execute(&[pop.as_bytes().to_vec(), key.clone()])? {
+                        return Ok(Value::Bulk(vec![bulk(key), Value::Data(value)]));
+                    }
+                }
+                Ok(Value::Nil)
+            }
+            "LLEN" => {
+                arity(1)?;
+                Ok(Value::Int(self.get::<List>(&args[0])?.map_or(0, |list| list.len()) as i64))
+            }
+            "LRANGE" => {
+                arity(3)?;
+                let (start, stop) = (int(&args[1])?, int(&args[2])?);
+                let Some(list) = self.get::<List>(&args[0])? else {
+                    return Ok(Value::Bulk(vec![]));
+                };
+                let values = match index_range(start, stop, list.len()) {
+                    Some((start, stop)) => list.range(start..=stop).map(|value| bulk(value)).collect(),
+                    None => vec![],
+                };
+                Ok(Value::Bulk(values))
+            }
+            "LINDEX" => {
+                arity(2)?;
+                let index = int(&args[1])?;
+                let Some(list) = self.get::<List>(&args[0])? else {
+                    return Ok(Value::Nil);
+                };
+                let index = if index < 0 { list.len() as i64 + index } else { index };
+                let value = usize::try_from(index).ok().and_then(|index| list.get(index));
+                Ok(value.map_or(Value::Nil, |value| bulk(value)))
+            }
+
+            // Sets
+            "SADD" => {
+                arity(2)?;
+                let set = self.get_or_default::<Set>(&args[0])?;
+                let added = args[1..].iter().filter(|member| set.insert((*member).clone())).count();
+                self.modified(&args[0]);
+                Ok(Value::Int(added as i64))
+            }
+            "SREM" => {
+                arity(2)?;
+                let removed = match self.get::<Set>(&args[0])? {
+                    Some(set) => args[1..].iter().filter(|member| set.remove(*member)).count(),
+                    None => 0,
+                };
+                if removed > 0 {
+                    self.remove_if_empty(&args[0]);
+                    self.modified(&args[0]);
+                }
+                Ok(Value::Int(removed as i64))
+            }
+            "SMEMBERS" => {
+                arity(1)?;
+                let members = self.get::<Set>(&args[0])?.map_or_else(Vec::new, |set| set.iter().map(|m| bulk(m)).collect());
+                Ok(Value::Bulk(members))
+            }
+            "SISMEMBER" => {
+                arity(2)?;
+                let member = self.get::<Set>(&args[0])?.is_some_and(|set| set.contains(&args[1]));
+                Ok(Value::Int(member as i64))
+            }
+            "SCARD" => {
+                arity(1)?;
+                Ok(Value::Int(self.get::<Set>(&args[0])?.map_or(0, |set| set.len()) as i64))
+            }
+            "SSCAN" => {
+                arity(2)?;
+                let cursor = scan_cursor(&args[1])?;
+                let options = ScanOptions::parse(&args[2..], false)?;
+                let members: Vec<Vec<u8>> = self.get::<Set>(&args[0])?.map_or_else(Vec::new, |set| set.iter().cloned().collect());
+                let (next, page) = scan_page(members, cursor, options.count);
+                let items = page.into_iter().filter(|member| options.matches(member)).map(Value::Data);
+                Ok(scan_reply(next, items.collect()))
+            }
+
+            // Sorted sets
+            "ZADD" => {
+                arity(3)?;
+                self.zadd(&args[0], &args[1..])
+            }
+            "ZINCRBY" => {
+                arity(3)?;
+                self.zadd(&args[0], &[b"INCR".to_vec(), args[1].clone(), args[2].clone()])
+            }
+            "ZSCORE" => {
+                arity(2)?;
+                let score = self.get::<SortedSet>(&args[0])?.and_then(|set| set.0.get(&args[1]).copied());
+                Ok(score.map_or(Value::Nil, |score| Value::Data(format_score(score))))
+            }
+            "ZCARD" => {
+                arity(1)?;
+                Ok(Value::Int(self.get::<SortedSet>(&args[0])?.map_or(0, |set| set.0.len()) as i64))
+            }
+            "ZREM" => {
+                arity(2)?;
+                let removed = match self.get::<SortedSet>(&args[0])? {
+                    Some(set) => args[1..].iter().filter(|member| set.0.remove(*member).is_some()).count(),
+                    None => 0,
+                };
+                if removed > 0 {
+                    self.remove_if_empty(&args[0]);
+                    self.modified(&args[0]);
+                }
+                Ok(Value::Int(removed as i64))
+            }
+            "ZRANGE" | "ZREVRANGE" | "ZRANGEBYSCORE" | "ZREVRANGEBYSCORE" => {
+                arity(3)?;
+                self.zrange(&name, &args[0], &args[1], &args[2], &args[3..])
+            }
+            "ZRANK" | "ZREVRANK" => {
+                arity(2)?;
+                let with_score = match args.get(2) {
+                    Some(option) if upper(option) == "WITHSCORE" => true,
+                    Some(_) => return Err(syntax_error()),
+                    None => false,
+                };
+                let Some(set) = self.get::<SortedSet>(&args[0])? else {
+                    return Ok(Value::Nil);
+                };
+                let mut ordered = set.ordered();
+                if name == "ZREVRANK" {
+                    ordered.reverse();
+                }
+                let Some(rank) = ordered.iter().position(|(member, _)| **member == args[1]) else {
+                    return Ok(Value::Nil);
+                };
+                Ok(if with_score {
+                    Value::Bulk(vec![Value::Int(rank as i64), Value::Data(format_score(ordered[rank].1))])
+                } else {
+                    Value::Int(rank as i64)
+                })
+            }
+
+            _ => {
+                let start: String = args
+                    .iter()
+                    .take(3)
+                    .map(|arg| format!("'{}' ", String::from_utf8_lossy(arg)))
+                    .collect();
+                Err(server_error(&format!(
+                    "ERR unknown command '{}', with args beginning with: {}",
+                    name.to_lowercase(),
+                    start
+                )))
+            }
+        }
+    }
+
+    fn set(&mut self, key: &[u8], value: &[u8], options: &[Vec<u8>]) -> RedisResult<Value> {
+        let (mut nx, mut xx, mut get, mut keep_ttl) = (false, false, false, false);
+        let mut expires_at = None;
+
+        let mut options = options.iter();
+        while let Some(option) = options.next() {
+            match upper(option).as_str() {
+                "NX" => nx = true,
+                "XX" => xx = true,
+                "GET" => get = true,
+                "KEEPTTL" => keep_ttl = true,
+                unit @ ("EX" | "PX") => {
+                    let amount = int(options.next().ok_or_else(syntax_error)?)?;
+                    if amount <= 0 || expires_at.is_some() {
+                        return Err(server_error("ERR invalid expire time in 'set' command"));
+                    }
+                    let millis = if unit == "EX" { amount.saturating_mul(1000) } else { amount };
+                    expires_at = Some(Instant::now() + Duration::from_millis(millis as u64));
+                }
+                _ => return Err(syntax_error()),
+            }
+        }
+        if (nx && xx) || (keep_ttl && expires_at.is_some()) {
+            return Err(syntax_error());
+        }
+
+        // SET replaces a value of any type, but SET ... GET needs the old one to be a string
+        let old = match self.get::<Vec<u8>>(key) {
+            Ok(old) => old.cloned(),
+            Err(e) if get => return Err(e),
+            Err(_) => None,
+        };
+        let old_reply = || old.clone().map_or(Value::Nil, Value::Data);
+
+        let exists = self.live(key).is_some();
+        if (nx && exists) || (xx && !exists) {
+            return Ok(if get { old_reply() } else { Value::Nil });
+        }
+
+        let expires_at = if keep_ttl {
+            self.live(key).and_then(|entry| entry.expires_at)
+        } else {
+            expires_at
+        };
+        self.entries.insert(
+            key.to_vec(),
+            Entry {
+                data: Data::String(value.to_vec()),
+                expires_at,
+            },
+        );
+        self.modified(key);
+
+        Ok(if get { old_reply() } else { Value::Okay })
+    }
+
+    fn incr_by(&mut self, key: &[u8], by: i64) -> RedisResult<Value> {
+        let current = match self.get::<Vec<u8>>(key)? {
+            Some(value) => int(value)?,
+            None => 0,
+        };
+        let next = current
+            .checked_add(by)
+            .ok_or_else(|| server_error("ERR increment or decrement would overflow"))?;
+
+        *self.get_or_default::<Vec<u8>>(key)? = next.to_string().into_bytes();
+        self.modified(key);
+        Ok(Value::Int(next))
+    }
+
+    /// ZADD key [NX|XX] [CH] [INCR] score member [score member ...]
+    fn zadd(&mut self, key: &[u8], args: &[Vec<u8>]) -> RedisResult<Value> {
+        let (mut nx, mut xx, mut ch, mut incr) = (false, false, false, false);
+        let mut rest = args;
+        while let Some((option, tail)) = rest.split_first() {
+            match upper(option).as_str() {
+                "NX" => nx = true,
+                "XX" => xx = true,
+                "CH" => ch = true,
+                "INCR" => incr = true,
+                _ => break,
+            }
+            rest = tail;
+        }
+
+        if rest.is_empty() || !rest.len().is_multiple_of(2) || (nx && xx) {
+            return Err(syntax_error());
+        }
+        if incr && rest.len() != 2 {
+            return Err(server_error("ERR INCR option supports a single increment-element pair"));
+        }
+        let pairs = rest
+            .chunks(2)
+            .map(|pair| float(&pair[0]).map(|score| (score, &pair[1])))
+            .collect::<RedisResult<Vec<_>>>()?;
+
+        let set = self.get_or_default::<SortedSet>(key)?;
+        let (mut added, mut changed) = (0, 0);
+        let mut incremented = Value::Nil;
+        for (score, member) in pairs {
+            let current = set.0.get(member).copied();
+            if (nx && current.is_some()) || (xx && current.is_none()) {
+                continue;
+            }
+            let score = if incr { current.unwrap_or(0.0) + score } else { score };
+            if score.is_nan() {
+                return Err(server_error("ERR resulting score is not a number (NaN)"));
+            }
+            match current {
+                None => added += 1,
+                Some(current) if current != score => changed += 1,
+                Some(_) => {}
+            }
+            set.0.insert(member.clone(), score);
+            incremented = Value::Data(format_score(score));
+        }
+
+        self.remove_if_empty(key);
+        if added + changed > 0 {
+            self.modified(key);
+        }
+
+        Ok(if incr {
+            incremented
+        } else {
+            Value::Int(added + if ch { changed } else { 0 })
+        })
+    }
+
+    /// ZRANGE (by index, or with BYSCORE/REV/LIMIT), ZREVRANGE, ZRANGEBYSCORE and ZREVRANGEBYSCORE
+    fn zrange(&mut self, name: &str, key: &[u8], start: &[u8], stop: &[u8], options: &[Vec<u8>]) -> RedisResult<Value> {
+        let mut by_score = name.ends_with("BYSCORE");
+        let mut rev = name.starts_with("ZREV");
+        let mut with_scores = false;
+        let mut limit = None;
+
+        let mut options = options.iter();
+        while let Some(option) = options.next() {
+            match upper(option).as_str() {
+                "WITHSCORES" => with_scores = true,
+                "BYSCORE" if name == "ZRANGE" => by_score = true,
+                "REV" if name == "ZRANGE" => rev = true,
+                "LIMIT" => {
+                    let offset = int(options.next().ok_or_else(syntax_error)?)?;
+                    let count = int(options.next().ok_or_else(syntax_error)?)?;
+                    limit = Some((offset, count));
+                }
+                _ => return Err(syntax_error()),
+            }
+        }
+        if limit.is_some() && !by_score {
+            return Err(server_error(
+                "ERR syntax error, LIMIT is only supported in combination with either BYSCORE or BYLEX",
+            ));
+        }
+
+        let Some(set) = self.get::<SortedSet>(key)? else {
+            return Ok(Value::Bulk(vec![]));
+        };
+        let mut members = set.ordered();
+        if rev {
+            members.reverse();
+        }
+
+        let members: Vec<(&Vec<u8>, f64)> = if by_score {
+            // Reversed ranges give the bounds high first: ZREVRANGEBYSCORE key max min
+            let (min, max) = if rev {
+                (score_bound(stop)?, score_bound(start)?)
+            } else {
+                (score_bound(start)?, score_bound(stop)?)
+            };
+            let in_range = members
+                .into_iter()
+                .filter(|(_, score)| above(*score, min) && below(*score, max));
+            match limit {
+                Some((offset, _)) if offset < 0 => Vec::new(),
+                Some((offset, count)) => in_range
+                    .skip(offset as usize)
+                    .take(usize::try_from(count).unwrap_or(usize::MAX))
+                    .collect(),
+                None => in_range.collect(),
+            }
+        } else {
+            match index_range(int(start)?, int(stop)?, members.len()) {
+                Some((start, stop)) => members[start..=stop].to_vec(),
+                None => vec![],
+            }
+        };
+
+        let reply = members.into_iter().flat_map(|(member, score)| {
+            let mut items = vec![bulk(member)];
+            if with_scores {
+                items.push(Value::Data(format_score(score)));
+            }
+            items
+        });
+        Ok(Value::Bulk(reply.collect()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(keyspace: &mut Keyspace, command: &[&str]) -> RedisResult<Value> {
+        let args: Vec<Vec<u8>> = command.iter().map(|arg| arg.as_bytes().to_vec()).collect();
+        keyspace.execute(&args)
+    }
+
+    fn data(s: &str) -> Value {
+        Value::Data(s.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match(b"user:*", b"user:42"));
+        assert!(glob_match(b"h?llo", b"hello"));
+        assert!(glob_match(b"h[ae]llo", b"hallo"));
+        assert!(!glob_match(b"h[^e]llo", b"hello"));
+        assert!(glob_match(b"h[a-c]llo", b"hbllo"));
+        assert!(glob_match(b"star\\*", b"star*"));
+        assert!(!glob_match(b"star\\*", b"start"));
+        assert!(!glob_match(b"user:*", b"session:1"));
+    }
+
+    #[test]
+    fn test_index_range() {
+        assert_eq!(index_range(0, -1, 3), Some((0, 2)));
+        assert_eq!(index_range(-2, 10, 3), Some((1, 2)));
+        assert_eq!(index_range(2, 1, 3), None);
+        assert_eq!(index_range(0, -1, 0), None);
+    }
+
+    #[test]
+    fn test_errors_match_server_errors() {
+        let mut keyspace = Keyspace::default();
+        run(&mut keyspace, &["LPUSH", "list", "a"]).unwrap();
+
+        let e = run(&mut keyspace, &["GET", "list"]).unwrap_err();
+        assert_eq!(e.code(), Some("WRONGTYPE"));
+
+        let e = run(&mut keyspace, &["INCR"]).unwrap_err();
+        assert_eq!(e.code(), Some("ERR"));
+        assert_eq!(e.detail(), Some("wrong number of arguments for 'incr' command"));
+    }
+
+    #[test]
+    fn test_set_options() {
+        let mut keyspace = Keyspace::default();
+        assert_eq!(run(&mut keyspace, &["SET", "k", "1", "NX"]).unwrap(), Value::Okay);
+        assert_eq!(run(&mut keyspace, &["SET", "k", "2", "NX"]).unwrap(), Value::Nil);
+        assert_eq!(run(&mut keyspace, &["SET", "k", "3", "XX", "GET"]).unwrap(), data("1"));
+        assert_eq!(run(&mut keyspace, &["SET", "k", "4", "EX", "100"]).unwrap(), Value::Okay);
+        assert_eq!(run(&mut keyspace, &["TTL", "k"]).unwrap(), Value::Int(100));
+        assert!(run(&mut keyspace, &["SET", "k", "5", "EX", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_expired_keys_disappear() {
+        let mut keyspace = Keyspace::default();
+        run(&mut keyspace, &["SET", "k", "v"]).unwrap();
+        keyspace.live(b"k").unwrap().expires_at = Some(Instant::now() - Duration::from_millis(1));
+
+        assert_eq!(run(&mut keyspace, &["GET", "k"]).unwrap(), Value::Nil);
+        assert_eq!(run(&mut keyspace, &["TTL", "k"]).unwrap(), Value::Int(-2));
+        assert_eq!(run(&mut keyspace, &["DBSIZE"]).unwrap(), Value::Int(0));
+    }
+
+    #[test]
+    fn test_sorted_set_order() {
+        let mut keyspace = Keyspace::default();
+        run(&mut keyspace, &["ZADD", "z", "2", "b", "1", "c", "2", "a"]).unwrap();
+
+        assert_eq!(
+            run(&mut keyspace, &["ZRANGE", "z", "0", "-1"]).unwrap(),
+            Value::Bulk(vec![data("c"), data("a"), data("b")])
+        );
+        assert_eq!(
+            run(&mut keyspace, &["ZREVRANGEBYSCORE", "z", "+inf", "(1", "WITHSCORES"]).unwrap(),
+            Value::Bulk(vec![data("b"), data("2"), data("a"), data("2")])
+        );
+        assert_eq!(run(&mut keyspace, &["ZINCRBY", "z", "0.5", "c"]).unwrap(), data("1.5"));
+        assert_eq!(run(&mut keyspace, &["ZREVRANK", "z", "c"]).unwrap(), Value::Int(2));
+    }
+
+    #[test]
+    fn test_watch_aborts_exec() {
+        let backend = MemoryBackend::new();
+        let endpoint = RedisEndpoint::local_development(6379, 1);
+        let mut first = backend.connect(&endpoint);
+        let mut second = backend.connect(&endpoint);
+
+        first.run(vec![b"WATCH".to_vec(), b"k".to_vec()]).unwrap();
+        second.run(vec![b"SET".to_vec(), b"k".to_vec(), b"v".to_vec()]).unwrap();
+        assert_eq!(first.atomic(vec![vec![b"INCR".to_vec(), b"n".to_vec()]]).unwrap(), Value::Nil);
+        assert_eq!(
+            first.atomic(vec![vec![b"INCR".to_vec(), b"n".to_vec()]]).unwrap(),
+            Value::Bulk(vec![Value::Int(1)])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_replies_read_one_at_a_time() {
+        let mut conn = MemoryBackend::new().connect(&RedisEndpoint::local_development(6379, 1));
+        let mut pipe = redis::pipe();
+        pipe.cmd("SET").arg("k").arg("v").cmd("INCR").arg("k").cmd("GET").arg("k");
+
+        assert_eq!(conn.req_packed_commands(&pipe, 0, 0).await.unwrap(), vec![]);
+        let next_reply = redis::pipe();
+        assert_eq!(conn.req_packed_commands(&next_reply, 0, 1).await.unwrap(), vec![Value::Okay]);
+        assert!(conn.req_packed_commands(&next_reply, 0, 1).await.is_err());
+        assert_eq!(conn.req_packed_commands(&next_reply, 0, 1).await.unwrap(), vec![data("v")]);
+        assert!(conn.req_packed_commands(&next_reply, 0, 1).await.unwrap_err().is_io_error());
+    }
+
+    #[test]
+    fn test_keyspaces_are_separate() {
+        let backend = MemoryBackend::new();
+        let mut first = backend.connect(&RedisEndpoint::local_development(6379, 1));
+        let mut second = backend.connect(&RedisEndpoint::local_development(6379, 2));
+
+        first.run(vec![b"SET".to_vec(), b"k".to_vec(), b"v".to_vec()]).unwrap();
+        assert_eq!(second.run(vec![b"GET".to_vec(), b"k".to_vec()]).unwrap(), Value::Nil);
+    }
+}
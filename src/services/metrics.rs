@@ -1,7 +1,10 @@
 use metrics::{counter, gauge, histogram, describe_counter, describe_gauge, describe_histogram};
 use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+use std::sync::OnceLock;
 use std::time::Instant;
 
+static RECORDER: OnceLock<PrometheusHandle> = OnceLock::new();
+
 /// Metrics service for tracking application performance
 pub struct MetricsService {
     handle: PrometheusHandle,
@@ -9,18 +12,27 @@ pub struct MetricsService {
 
 impl MetricsService {
     /// Initialize metrics service with Prometheus exporter
+    ///
+    /// The recorder is global, so it is installed once and shared by every service created
+    /// afterwards (e.g. by several `AppState`s in one test process).
     pub fn new() -> Self {
-        let handle = PrometheusBuilder::new()
-            .set_buckets_for_metric(
-                Matcher::Full("http_request_duration_seconds".to_string()),
-                &[0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0],
-            )
-            .unwrap()
-            .install_recorder()
-            .expect("Failed to install Prometheus recorder");
-
-        // Describe all metrics
-        Self::describe_metrics();
+        let handle = RECORDER
+            .get_or_init(|| {
+                let handle = PrometheusBuilder::new()
+                    .set_buckets_for_metric(
+                        Matcher::Full("http_request_duration_seconds".to_string()),
+                        &[0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0],
+                    )
+                    .unwrap()
+                    .install_recorder()
+                    .expect("Failed to install Prometheus recorder");
+
+                // Describe all metrics
+                Self::describe_metrics();
+
+                handle
+            })
+            .clone();
 
         Self { handle }
     }
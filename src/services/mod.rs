@@ -5,3 +5,19 @@ pub mod rate_limiter;
 pub mod health;
 
 
+pub mod command_scope;
+pub mod api_key_cache;
+pub mod resp;
+pub mod credentials;
+pub mod audit;
+pub mod command_policy;
+pub mod path_command;
+pub mod sorted_set;
+pub mod stream;
+pub mod blocking;
+pub mod scripts;
+pub mod key_browser;
+pub mod redis_backend;
+pub mod memory_backend;
+pub mod cluster;
+pub mod sentinel;
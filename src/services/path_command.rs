@@ -0,0 +1,132 @@
+// Upstash-style commands encoded in a URL path (`/set/foo/bar/EX/100`)
+
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum PathCommandError {
+    #[error("Empty command")]
+    EmptyCommand,
+
+    #[error("Invalid percent-encoding in path segment: {0}")]
+    InvalidEncoding(String),
+}
+
+/// Build a command from a request path, query options and body
+///
+/// Every path segment is one argument, percent-decoded, so `/` and other reserved
+/// characters can appear in keys and values as `%2F` etc. A non-empty body follows the
+/// path arguments (it is usually the value), then the options in order: `("EX", "100")`
+/// becomes `EX 100`, and an option with an empty value (`?NX`) is a flag.
+pub fn parse_path_command(
+    path: &str,
+    options: &[(String, String)],
+    body: &[u8],
+) -> Result<Vec<Vec<u8>>, PathCommandError> {
+    let path = path.trim_matches('/');
+    if path.is_empty() {
+        return Err(PathCommandError::EmptyCommand);
+    }
+
+    let mut args = path
+        .split('/')
+        .map(|segment| {
+            percent_decode(segment).ok_or_else(|| PathCommandError::InvalidEncoding(segment.to_string()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if !body.is_empty() {
+        args.push(body.to_vec());
+    }
+
+    for (name, value) in options {
+        args.push(name.as_bytes().to_vec());
+        if !value.is_empty() {
+            args.push(value.as_bytes().to_vec());
+        }
+    }
+
+    Ok(args)
+}
+
+/// Decode `%XX` escapes; `+` is left as is since it has no special meaning in paths
+fn percent_decode(segment: &str) -> Option<Vec<u8>> {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = bytes.get(i + 1..i + 3)?;
+            if !hex.iter().all(u8::is_ascii_hexdigit) {
+                return None;
+            }
+            decoded.push(u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    Some(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(values: &[&str]) -> Vec<Vec<u8>> {
+        values.iter().map(|v| v.as_bytes().to_vec()).collect()
+    }
+
+    fn options(values: &[(&str, &str)]) -> Vec<(String, String)> {
+        values.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_path_segments() {
+        assert_eq!(
+            parse_path_command("set/foo/bar/EX/100", &[], b"").unwrap(),
+            args(&["set", "foo", "bar", "EX", "100"])
+        );
+        assert_eq!(parse_path_command("/dbsize/", &[], b"").unwrap(), args(&["dbsize"]));
+    }
+
+    #[test]
+    fn test_percent_decoding() {
+        assert_eq!(
+            parse_path_command("set/user%3A1%2Fname/a%20b+c", &[], b"").unwrap(),
+            args(&["set", "user:1/name", "a b+c"])
+        );
+        assert_eq!(
+            parse_path_command("set/bin/%00%FF", &[], b"").unwrap()[2],
+            vec![0x00, 0xff]
+        );
+        assert!(matches!(
+            parse_path_command("get/bad%G1", &[], b""),
+            Err(PathCommandError::InvalidEncoding(_))
+        ));
+        assert!(matches!(
+            parse_path_command("get/bad%+1", &[], b""),
+            Err(PathCommandError::InvalidEncoding(_))
+        ));
+        assert!(matches!(
+            parse_path_command("get/bad%4", &[], b""),
+            Err(PathCommandError::InvalidEncoding(_))
+        ));
+    }
+
+    #[test]
+    fn test_options_and_body() {
+        assert_eq!(
+            parse_path_command("set/foo", &options(&[("EX", "100"), ("NX", "")]), b"bar").unwrap(),
+            args(&["set", "foo", "bar", "EX", "100", "NX"])
+        );
+    }
+
+    #[test]
+    fn test_empty_command() {
+        assert_eq!(parse_path_command("", &[], b""), Err(PathCommandError::EmptyCommand));
+        assert_eq!(parse_path_command("/", &[], b"value"), Err(PathCommandError::EmptyCommand));
+    }
+}
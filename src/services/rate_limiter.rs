@@ -1,5 +1,6 @@
 use governor::{
-    clock::DefaultClock,
+    clock::{Clock, DefaultClock},
+    middleware::StateInformationMiddleware,
     state::{InMemoryState, NotKeyed},
     Quota, RateLimiter as GovernorRateLimiter,
 };
@@ -7,17 +8,47 @@ use nonzero_ext::nonzero;
 use std::collections::HashMap;
 use std::num::NonZeroU32;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
 use tracing::{info, warn};
 
+/// Direct limiter that reports its remaining capacity on every check
+type Limiter = GovernorRateLimiter<NotKeyed, InMemoryState, DefaultClock, StateInformationMiddleware>;
+
+/// Outcome of a rate limit check, with the numbers needed for rate-limit response headers
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimitDecision {
+    /// Whether the request may proceed
+    pub allowed: bool,
+    /// Maximum burst of requests allowed
+    pub limit: u32,
+    /// Requests that can still be made right now
+    pub remaining: u32,
+    /// How long until another request will be allowed (zero if one is allowed now)
+    pub retry_after: Duration,
+}
+
+impl RateLimitDecision {
+    /// `retry_after` rounded up to whole seconds, as used by the `Retry-After` header
+    pub fn retry_after_secs(&self) -> u64 {
+        let secs = self.retry_after.as_secs();
+        if self.retry_after.subsec_nanos() > 0 {
+            secs + 1
+        } else {
+            secs
+        }
+    }
+}
+
 /// Rate limiter for API keys
 pub struct RateLimiter {
     /// Default rate limiter for non-API key requests
-    default_limiter: Arc<GovernorRateLimiter<NotKeyed, InMemoryState, DefaultClock>>,
+    default_limiter: Arc<Limiter>,
     /// Per-API-key rate limiters
-    api_key_limiters: Arc<RwLock<HashMap<String, Arc<GovernorRateLimiter<NotKeyed, InMemoryState, DefaultClock>>>>>,
+    api_key_limiters: Arc<RwLock<HashMap<String, Arc<Limiter>>>>,
     /// Default quota (requests per second)
     default_quota: Quota,
+    clock: DefaultClock,
 }
 
 impl RateLimiter {
@@ -26,21 +57,44 @@ impl RateLimiter {
     /// # Arguments
     /// * `default_rps` - Default requests per second for non-API-key requests
     pub fn new(default_rps: u32) -> Self {
-        let default_quota = Quota::per_second(NonZeroU32::new(default_rps).unwrap_or(nonzero!(100u32)));
-        let default_limiter = Arc::new(GovernorRateLimiter::direct(default_quota));
+        Self::with_burst(default_rps, default_rps)
+    }
 
-        info!("Rate limiter initialized with {} requests/second", default_rps);
+    /// Create a new rate limiter with an explicit burst size
+    ///
+    /// # Arguments
+    /// * `default_rps` - Default requests per second
+    /// * `burst_size` - Maximum number of requests allowed at once before throttling
+    pub fn with_burst(default_rps: u32, burst_size: u32) -> Self {
+        let default_quota = Quota::per_second(NonZeroU32::new(default_rps).unwrap_or(nonzero!(100u32)))
+            .allow_burst(NonZeroU32::new(burst_size).unwrap_or(nonzero!(20u32)));
+        let default_limiter = Arc::new(Self::build_limiter(default_quota));
+
+        info!(
+            "Rate limiter initialized with {} requests/second (burst: {})",
+            default_rps, burst_size
+        );
 
         Self {
             default_limiter,
             api_key_limiters: Arc::new(RwLock::new(HashMap::new())),
             default_quota,
+            clock: DefaultClock::default(),
         }
     }
 
+    fn build_limiter(quota: Quota) -> Limiter {
+        GovernorRateLimiter::direct(quota).with_middleware::<StateInformationMiddleware>()
+    }
+
     /// Check if a request is allowed for the default limiter
     pub async fn check_default(&self) -> bool {
-        self.default_limiter.check().is_ok()
+        self.check_default_detailed().await.allowed
+    }
+
+    /// Check the default limiter and return remaining capacity information
+    pub async fn check_default_detailed(&self) -> RateLimitDecision {
+        self.decide(&self.default_limiter)
     }
 
     /// Check if a request is allowed for a specific API key
@@ -49,6 +103,15 @@ impl RateLimiter {
     /// * `api_key` - The API key to check
     /// * `custom_quota` - Optional custom quota for this API key (requests per second)
     pub async fn check_api_key(&self, api_key: &str, custom_quota: Option<u32>) -> bool {
+        self.check_api_key_detailed(api_key, custom_quota).await.allowed
+    }
+
+    /// Check a specific API key and return remaining capacity information
+    ///
+    /// # Arguments
+    /// * `api_key` - The API key (or API key id) to check
+    /// * `custom_quota` - Optional custom quota for this API key (requests per second)
+    pub async fn check_api_key_detailed(&self, api_key: &str, custom_quota: Option<u32>) -> RateLimitDecision {
         // Get or create limiter for this API key
         let limiter = {
             let read_guard = self.api_key_limiters.read().await;
@@ -64,7 +127,7 @@ impl RateLimiter {
                     self.default_quota
                 };
 
-                let new_limiter = Arc::new(GovernorRateLimiter::direct(quota));
+                let new_limiter = Arc::new(Self::build_limiter(quota));
 
                 let mut write_guard = self.api_key_limiters.write().await;
                 write_guard.insert(api_key.to_string(), new_limiter.clone());
@@ -76,13 +139,38 @@ impl RateLimiter {
             }
         };
 
-        let allowed = limiter.check().is_ok();
+        let decision = self.decide(&limiter);
 
-        if !allowed {
+        if !decision.allowed {
             warn!("Rate limit exceeded for API key: {}...", &api_key[..8.min(api_key.len())]);
         }
 
-        allowed
+        decision
+    }
+
+    fn decide(&self, limiter: &Limiter) -> RateLimitDecision {
+        match limiter.check() {
+            Ok(snapshot) => {
+                let quota = snapshot.quota();
+                let remaining = snapshot.remaining_burst_capacity();
+                RateLimitDecision {
+                    allowed: true,
+                    limit: quota.burst_size().get(),
+                    remaining,
+                    retry_after: if remaining == 0 {
+                        quota.replenish_interval()
+                    } else {
+                        Duration::ZERO
+                    },
+                }
+            }
+            Err(not_until) => RateLimitDecision {
+                allowed: false,
+                limit: not_until.quota().burst_size().get(),
+                remaining: 0,
+                retry_after: not_until.wait_time_from(self.clock.now()),
+            },
+        }
     }
 
     /// Remove a rate limiter for an API key (e.g., when key is deleted)
@@ -180,6 +268,36 @@ mod tests {
         assert_eq!(limiter.tracked_keys_count().await, 0);
     }
 
+    #[tokio::test]
+    async fn test_burst_is_enforced() {
+        let limiter = RateLimiter::with_burst(1, 2);
+
+        let first = limiter.check_api_key_detailed("burst_key", None).await;
+        assert!(first.allowed);
+        assert_eq!(first.limit, 2);
+        assert_eq!(first.remaining, 1);
+
+        let second = limiter.check_api_key_detailed("burst_key", None).await;
+        assert!(second.allowed);
+        assert_eq!(second.remaining, 0);
+
+        let third = limiter.check_api_key_detailed("burst_key", None).await;
+        assert!(!third.allowed);
+        assert!(third.retry_after > Duration::ZERO);
+        assert!(third.retry_after_secs() >= 1);
+    }
+
+    #[test]
+    fn test_retry_after_secs_rounds_up() {
+        let decision = RateLimitDecision {
+            allowed: false,
+            limit: 10,
+            remaining: 0,
+            retry_after: Duration::from_millis(1200),
+        };
+        assert_eq!(decision.retry_after_secs(), 2);
+    }
+
     #[tokio::test]
     async fn test_custom_quota() {
         let limiter = RateLimiter::new(100);
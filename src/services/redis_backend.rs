@@ -0,0 +1,129 @@
+// Abstraction over how the data plane reaches Redis, so command paths can run without a server
+
+use futures::future::BoxFuture;
+use redis::aio::{ConnectionLike, PubSub};
+use redis::{Cmd, Pipeline, RedisFuture, Value};
+use uuid::Uuid;
+
+use crate::services::cluster::Topology;
+use crate::services::redis_pool::{DedicatedMasterConnection, RedisEndpoint, RedisPool};
+
+/// A connection handed out by a `RedisBackend`
+///
+/// Implements `ConnectionLike`, so `redis::cmd(..).query_async`, pipelines and
+/// `AsyncCommands` work on it whatever the backend.
+pub struct BackendConnection(Box<dyn ConnectionLike + Send + Sync>);
+
+impl BackendConnection {
+    pub fn new(connection: impl ConnectionLike + Send + Sync + 'static) -> Self {
+        Self(Box::new(connection))
+    }
+}
+
+impl ConnectionLike for BackendConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        self.0.req_packed_command(cmd)
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        self.0.req_packed_commands(cmd, offset, count)
+    }
+
+    fn get_db(&self) -> i64 {
+        self.0.get_db()
+    }
+}
+
+/// Where data-plane handlers get their Redis connections from
+///
+/// `RedisPool` talks to real servers; `MemoryBackend` keeps data in process for tests and
+/// local runs without Redis.
+pub trait RedisBackend: Send + Sync {
+    /// Connection shared by all requests to an instance, for commands that do not change
+    /// connection state
+    fn connection<'a>(
+        &'a self,
+        instance_id: Uuid,
+        endpoint: &'a RedisEndpoint,
+    ) -> BoxFuture<'a, Result<BackendConnection, String>>;
+
+    /// Connection used by a single request (WATCH/MULTI, blocking commands, pipelines, the
+    /// RESP proxy)
+    ///
+    /// For a standalone instance it is a plain connection, whose pipeline replies can be
+    /// read one at a time like those of `subscriber`.
+    fn dedicated_connection<'a>(&'a self, endpoint: &'a RedisEndpoint) -> BoxFuture<'a, Result<BackendConnection, String>>;
+
+    /// Connection for subscribing to channels and patterns
+    fn pubsub<'a>(&'a self, endpoint: &'a RedisEndpoint) -> BoxFuture<'a, Result<PubSub, String>>;
+
+    /// Plain standalone connection for a subscriber that changes its subscriptions while
+    /// it reads messages: commands are written with `req_packed_commands(.., 0, 0)` and
+    /// pushes are read one at a time with an empty pipeline
+    fn subscriber<'a>(&'a self, endpoint: &'a RedisEndpoint) -> BoxFuture<'a, Result<BackendConnection, String>>;
+}
+
+impl RedisPool {
+    /// Dedicated standalone connection, watching for failovers when Sentinel manages the instance
+    async fn dedicated_backend_connection(&self, endpoint: &RedisEndpoint) -> Result<BackendConnection, String> {
+        let conn = RedisPool::dedicated_connection(self, endpoint).await?;
+        Ok(match &endpoint.sentinel {
+            Some(config) => BackendConnection::new(DedicatedMasterConnection::new(self.clone(), config.clone(), conn)),
+            None => BackendConnection::new(conn),
+        })
+    }
+}
+
+impl RedisBackend for RedisPool {
+    fn connection<'a>(
+        &'a self,
+        instance_id: Uuid,
+        endpoint: &'a RedisEndpoint,
+    ) -> BoxFuture<'a, Result<BackendConnection, String>> {
+        Box::pin(async move {
+            let instance_id = instance_id.to_string();
+            match endpoint.topology {
+                Topology::Standalone if endpoint.sentinel.is_some() => self
+                    .get_failover_connection(&instance_id, endpoint)
+                    .await
+                    .map(BackendConnection::new),
+                Topology::Standalone => self.get_connection(&instance_id, endpoint).await.map(BackendConnection::new),
+                Topology::Cluster => self
+                    .get_cluster_connection(&instance_id, endpoint)
+                    .await
+                    .map(BackendConnection::new),
+            }
+        })
+    }
+
+    fn dedicated_connection<'a>(&'a self, endpoint: &'a RedisEndpoint) -> BoxFuture<'a, Result<BackendConnection, String>> {
+        Box::pin(async move {
+            match endpoint.topology {
+                Topology::Standalone => self.dedicated_backend_connection(endpoint).await,
+                Topology::Cluster => self
+                    .dedicated_cluster_connection(endpoint)
+                    .await
+                    .map(BackendConnection::new),
+            }
+        })
+    }
+
+    fn pubsub<'a>(&'a self, endpoint: &'a RedisEndpoint) -> BoxFuture<'a, Result<PubSub, String>> {
+        // A cluster forwards published messages to every node, so subscribing on the seed
+        // node receives all of them
+        Box::pin(async move {
+            RedisPool::dedicated_connection(self, endpoint)
+                .await
+                .map(|connection| connection.into_pubsub())
+        })
+    }
+
+    fn subscriber<'a>(&'a self, endpoint: &'a RedisEndpoint) -> BoxFuture<'a, Result<BackendConnection, String>> {
+        Box::pin(self.dedicated_backend_connection(endpoint))
+    }
+}
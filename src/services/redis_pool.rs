@@ -1,25 +1,265 @@
-use redis::Client;
+use redis::aio::{Connection, ConnectionLike, ConnectionManager};
+use redis::cluster::ClusterClient;
+use redis::cluster_async::ClusterConnection;
+use redis::{Client, Cmd, Pipeline, RedisError, RedisFuture, Value};
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::RwLock;
-use tokio::time::sleep;
+use tokio::time::{sleep, timeout};
 use tracing::{error, info, warn};
 
-const MAX_RETRY_ATTEMPTS: u32 = 3;
-const RETRY_DELAY_MS: u64 = 1000;
+use crate::config::RedisConfig;
+use crate::models::RedisInstance;
+use crate::services::cluster::Topology;
+use crate::services::sentinel::{self, SentinelConfig};
+
+/// Reconnect backoff used by the connection managers (exponent base, factor in ms).
+/// Initial connection retries are handled by the pool itself.
+const RECONNECT_BACKOFF_BASE: u64 = 2;
+const RECONNECT_BACKOFF_FACTOR_MS: u64 = 100;
+
+/// `deployment_mode` of instances deployed to Kubernetes
+pub const KUBERNETES_MODE: &str = "kubernetes";
+/// `deployment_mode` of instances created without Kubernetes, each served by a database of
+/// the shared local Redis
+pub const DEVELOPMENT_MODE: &str = "development";
+
+/// Commands that reach beyond the current logical database, which would let a development
+/// instance see or destroy the databases of other development instances. Lua scripts can
+/// call any of these, and MONITOR and PUBSUB report on the whole server.
+const CROSS_DATABASE_COMMANDS: &[&str] = &[
+    "SELECT", "SWAPDB", "MOVE", "COPY", "FLUSHALL", "MONITOR",
+    "EVAL", "EVALSHA", "EVAL_RO", "EVALSHA_RO", "FCALL", "FCALL_RO", "SCRIPT", "FUNCTION",
+    "PUBSUB",
+];
+
+/// Commands whose first argument is a Pub/Sub channel
+const PUBLISH_COMMANDS: &[&str] = &["PUBLISH", "SPUBLISH"];
+
+/// Commands whose arguments are all Pub/Sub channels or patterns
+const SUBSCRIBE_COMMANDS: &[&str] = &[
+    "SUBSCRIBE", "PSUBSCRIBE", "SSUBSCRIBE", "UNSUBSCRIBE", "PUNSUBSCRIBE", "SUNSUBSCRIBE",
+];
+
+/// A command argument a channel namespace can be put in front of
+pub trait ChannelArg {
+    fn with_prefix(&self, prefix: &str) -> Self;
+}
+
+impl ChannelArg for String {
+    fn with_prefix(&self, prefix: &str) -> Self {
+        format!("{}{}", prefix, self)
+    }
+}
+
+impl ChannelArg for Vec<u8> {
+    fn with_prefix(&self, prefix: &str) -> Self {
+        [prefix.as_bytes(), self.as_slice()].concat()
+    }
+}
+
+/// Connections held for a single Redis instance
+#[derive(Clone)]
+struct PooledInstance {
+    client: Client,
+    /// Multiplexed connection shared by all requests; reconnects automatically
+    manager: ConnectionManager,
+    /// URL the connection was opened with, used to detect endpoint or password changes
+    url: String,
+}
+
+/// Connection held for a Redis Cluster instance
+#[derive(Clone)]
+struct PooledCluster {
+    /// Routes each command to the node owning its slot and follows MOVED/ASK redirects
+    connection: ClusterConnection,
+    url: String,
+}
 
 /// Redis connection pool for managing multiple Redis instances
+///
+/// Each instance gets one multiplexed `ConnectionManager` that is shared by all
+/// requests, so handlers never open a connection per request or block the runtime.
+/// Redis Cluster instances get one `ClusterConnection` instead.
 #[derive(Clone)]
 pub struct RedisPool {
-    connections: Arc<RwLock<HashMap<String, Client>>>,
+    connections: Arc<RwLock<HashMap<String, PooledInstance>>>,
+    clusters: Arc<RwLock<HashMap<String, PooledCluster>>>,
+    /// Last master reported by Sentinel, per Sentinel setup
+    masters: Arc<RwLock<HashMap<SentinelConfig, (String, u16)>>>,
+    connect_timeout: Duration,
+    max_retries: u32,
+    retry_delay: Duration,
+}
+
+/// Resolved network location and credentials of a Redis instance
+#[derive(Debug, Clone, PartialEq)]
+pub struct RedisEndpoint {
+    pub host: String,
+    pub port: u16,
+    pub password: Option<String>,
+    /// Logical database; only development instances use one other than 0, as they share the
+    /// local Redis
+    pub database: i32,
+    /// Cluster endpoints are a seed node; the rest of the cluster is discovered from it
+    pub topology: Topology,
+    /// When set, `host` and `port` are replaced by the master Sentinel reports
+    pub sentinel: Option<SentinelConfig>,
+}
+
+impl RedisEndpoint {
+    /// Work out where to reach an instance, given its decrypted password
+    ///
+    /// Development instances (see `is_local_development`) are served by the local Redis
+    /// without a password.
+    /// Use `CredentialsService::endpoint` rather than calling this directly, as it also
+    /// assigns development instances their database.
+    pub fn for_instance(instance: &RedisInstance, password: Option<String>) -> Self {
+        let port = instance.port.unwrap_or(6379) as u16;
+
+        if Self::is_local_development(instance) {
+            Self::local_development(port, 0)
+        } else {
+            Self {
+                host: instance_host(instance),
+                port,
+                password,
+                database: 0,
+                topology: Topology::from_column(&instance.topology),
+                sentinel: SentinelConfig::from_instance(instance),
+            }
+        }
+    }
+
+    /// Endpoint of a database of the shared local Redis
+    pub fn local_development(port: u16, database: i32) -> Self {
+        Self {
+            host: "127.0.0.1".to_string(),
+            port,
+            password: None,
+            database,
+            topology: Topology::Standalone,
+            sentinel: None,
+        }
+    }
+
+    /// Whether an instance is a development instance served by the shared local Redis
+    ///
+    /// Only instances created in development mode count, whatever their domain; every other
+    /// instance keeps its own host and password.
+    pub fn is_local_development(instance: &RedisInstance) -> bool {
+        instance.deployment_mode == DEVELOPMENT_MODE
+    }
+
+    /// Whether a command must be refused on a development instance, to keep it in its database
+    pub fn crosses_databases(command: &str) -> bool {
+        CROSS_DATABASE_COMMANDS.contains(&command.to_uppercase().as_str())
+    }
+
+    /// Whether a command publishes to, or subscribes to, Pub/Sub channels
+    pub fn uses_channels(command: &str) -> bool {
+        let command = command.to_uppercase();
+        PUBLISH_COMMANDS.contains(&command.as_str()) || SUBSCRIBE_COMMANDS.contains(&command.as_str())
+    }
+
+    /// Prefix of a development instance's Pub/Sub channels
+    ///
+    /// Channels are shared by every database of a server, so the development instances of
+    /// the local Redis each publish and subscribe under their own prefix. Clients never see
+    /// it: channel arguments get it added and published messages get it removed.
+    pub fn channel_namespace(instance: &RedisInstance) -> Option<String> {
+        Self::is_local_development(instance).then(|| format!("{}:", instance.id))
+    }
+
+    /// Put the instance's channel namespace in front of the channels and patterns of a
+    /// Pub/Sub command, given its arguments without the command name
+    pub fn namespace_channels<T: ChannelArg>(instance: &RedisInstance, command: &str, args: &mut [T]) {
+        let Some(namespace) = Self::channel_namespace(instance) else {
+            return;
+        };
+        let command = command.to_uppercase();
+        let channels = if PUBLISH_COMMANDS.contains(&command.as_str()) {
+            args.iter_mut().take(1)
+        } else if SUBSCRIBE_COMMANDS.contains(&command.as_str()) {
+            args.iter_mut().take(usize::MAX)
+        } else {
+            return;
+        };
+        for arg in channels {
+            *arg = arg.with_prefix(&namespace);
+        }
+    }
+
+    /// Connection URL for this endpoint
+    pub fn url(&self) -> String {
+        self.with_database(build_redis_url("redis", &self.host, self.port, self.password.as_deref()))
+    }
+
+    /// Connection URL using TLS (`rediss://`)
+    pub fn tls_url(&self) -> String {
+        self.with_database(build_redis_url("rediss", &self.host, self.port, self.password.as_deref()))
+    }
+
+    fn with_database(&self, url: String) -> String {
+        if self.database == 0 {
+            url
+        } else {
+            format!("{}/{}", url, self.database)
+        }
+    }
+}
+
+fn instance_host(instance: &RedisInstance) -> String {
+    if let Some(domain) = &instance.domain {
+        domain.clone()
+    } else if let Some(service_name) = &instance.service_name {
+        service_name.clone()
+    } else if let Some(public_ip) = &instance.public_ip_address {
+        public_ip.ip().to_string()
+    } else if let Some(private_ip) = &instance.private_ip_address {
+        private_ip.ip().to_string()
+    } else {
+        // If no connection info at all, default to localhost for development
+        warn!("No connection info found for instance {}, defaulting to localhost", instance.id);
+        "localhost".to_string()
+    }
+}
+
+fn build_redis_url(scheme: &str, host: &str, port: u16, password: Option<&str>) -> String {
+    if let Some(pwd) = password {
+        format!("{}://:{}@{}:{}", scheme, percent_encode(pwd), host, port)
+    } else {
+        format!("{}://{}:{}", scheme, host, port)
+    }
+}
+
+/// Percent-encode a URL userinfo component (generated passwords contain `@`, `#`, `%`, ...)
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
 }
 
 impl RedisPool {
-    /// Create a new Redis connection pool
+    /// Create a new Redis connection pool with default settings
     pub fn new() -> Self {
+        Self::with_config(&RedisConfig::default())
+    }
+
+    /// Create a new Redis connection pool using the given timeouts and retry settings
+    pub fn with_config(config: &RedisConfig) -> Self {
         Self {
             connections: Arc::new(RwLock::new(HashMap::new())),
+            clusters: Arc::new(RwLock::new(HashMap::new())),
+            masters: Arc::new(RwLock::new(HashMap::new())),
+            connect_timeout: Duration::from_millis(config.default_timeout_ms),
+            max_retries: config.max_retries.max(1),
+            retry_delay: Duration::from_millis(config.retry_delay_ms),
         }
     }
 
@@ -31,11 +271,7 @@ impl RedisPool {
         port: u16,
         password: Option<&str>,
     ) -> Result<(), String> {
-        let redis_url = if let Some(pwd) = password {
-            format!("redis://:{}@{}:{}", pwd, host, port)
-        } else {
-            format!("redis://{}:{}", host, port)
-        };
+        let redis_url = build_redis_url("redis", host, port, password);
 
         info!(
             instance_id = %instance_id,
@@ -44,17 +280,228 @@ impl RedisPool {
             "Attempting to connect to Redis instance"
         );
 
-        // Retry logic
+        let pooled = self.open(instance_id, &redis_url, self.max_retries).await?;
+
+        // Store connection in pool
+        self.connections
+            .write()
+            .await
+            .insert(instance_id.to_string(), pooled);
+
+        Ok(())
+    }
+
+    /// Get the shared connection for an instance, connecting on first use
+    ///
+    /// If the instance's endpoint or password changed since the connection was opened,
+    /// a new connection replaces the old one. That includes Sentinel reporting a new master.
+    pub async fn get_connection(&self, instance_id: &str, endpoint: &RedisEndpoint) -> Result<ConnectionManager, String> {
+        let instance_id = instance_id.to_string();
+        let url = self.current_master(endpoint).await?.url();
+
+        if let Some(pooled) = self.connections.read().await.get(&instance_id) {
+            if pooled.url == url {
+                return Ok(pooled.manager.clone());
+            }
+        }
+
+        // Requests should fail fast when Redis is down, so only one attempt here
+        let pooled = self.open(&instance_id, &url, 1).await?;
+
+        let mut connections = self.connections.write().await;
+        match connections.get(&instance_id) {
+            // Another request connected concurrently; keep its connection
+            Some(existing) if existing.url == url => Ok(existing.manager.clone()),
+            _ => {
+                let manager = pooled.manager.clone();
+                connections.insert(instance_id, pooled);
+                Ok(manager)
+            }
+        }
+    }
+
+    /// Open a connection that is not shared with other requests
+    ///
+    /// Needed for commands that change connection state (WATCH/MULTI, blocking pops,
+    /// Pub/Sub), which must not be interleaved with other requests' commands.
+    ///
+    /// On a Sentinel-managed instance the remembered master may have gone away or been
+    /// demoted to a replica by a failover. Either way it is forgotten and the connection is
+    /// opened once more on the master Sentinel reports now.
+    pub async fn dedicated_connection(&self, endpoint: &RedisEndpoint) -> Result<Connection, String> {
+        let Some(config) = &endpoint.sentinel else {
+            return self.open_dedicated(endpoint).await;
+        };
+
+        match self.open_dedicated(endpoint).await {
+            Ok(conn) => Ok(conn),
+            Err(e) => {
+                warn!(master_name = %config.master_name, "Dedicated connection to the Redis master failed: {}", e);
+                self.forget_master(config).await;
+                self.open_dedicated(endpoint).await
+            }
+        }
+    }
+
+    /// Connect to the current master, checking with ROLE that it still is one when it was
+    /// found through Sentinel
+    async fn open_dedicated(&self, endpoint: &RedisEndpoint) -> Result<Connection, String> {
+        let url = self.current_master(endpoint).await?.url();
+        let client = Client::open(url.as_str()).map_err(|e| format!("Invalid URL: {}", e))?;
+
+        let connect = async {
+            let mut conn = client.get_async_connection().await?;
+            if endpoint.sentinel.is_some() {
+                let role: Value = redis::cmd("ROLE").query_async(&mut conn).await?;
+                if !is_master_role(&role) {
+                    return Err(RedisError::from((
+                        redis::ErrorKind::ReadOnly,
+                        "Server is no longer the master",
+                    )));
+                }
+            }
+            Ok(conn)
+        };
+
+        match timeout(self.connect_timeout, connect).await {
+            Ok(Ok(conn)) => Ok(conn),
+            Ok(Err(e)) => Err(format!("Connection failed: {}", e)),
+            Err(_) => Err(format!(
+                "Connection failed: timed out after {}ms",
+                self.connect_timeout.as_millis()
+            )),
+        }
+    }
+
+    /// Get the shared connection for a Sentinel-managed instance, which follows failovers
+    pub async fn get_failover_connection(
+        &self,
+        instance_id: &str,
+        endpoint: &RedisEndpoint,
+    ) -> Result<FailoverConnection, String> {
+        let manager = self.get_connection(instance_id, endpoint).await?;
+        Ok(FailoverConnection {
+            pool: self.clone(),
+            instance_id: instance_id.to_string(),
+            endpoint: endpoint.clone(),
+            manager,
+        })
+    }
+
+    /// Endpoint of the current master: `endpoint` itself, or the master its sentinels report
+    ///
+    /// The master is remembered until `forget_master` is called after a connection error or
+    /// a READONLY reply.
+    pub async fn current_master(&self, endpoint: &RedisEndpoint) -> Result<RedisEndpoint, String> {
+        let Some(config) = &endpoint.sentinel else {
+            return Ok(endpoint.clone());
+        };
+
+        let cached = self.masters.read().await.get(config).cloned();
+        let (host, port) = match cached {
+            Some(master) => master,
+            None => self.refresh_master(config).await?,
+        };
+
+        Ok(RedisEndpoint {
+            host,
+            port,
+            ..endpoint.clone()
+        })
+    }
+
+    /// Ask Sentinel for the current master now, and remember the answer
+    pub async fn refresh_master(&self, config: &SentinelConfig) -> Result<(String, u16), String> {
+        let master = sentinel::resolve_master(config, self.connect_timeout)
+            .await
+            .map_err(|e| format!("Sentinel lookup failed: {}", e))?;
+
+        let previous = self.masters.write().await.insert(config.clone(), master.clone());
+        if previous.as_ref() != Some(&master) {
+            info!(
+                master_name = %config.master_name,
+                master = %sentinel::format_address(&master.0, master.1),
+                "Resolved Redis master through Sentinel"
+            );
+        }
+        Ok(master)
+    }
+
+    /// Ask Sentinel for the master again on next use
+    pub async fn forget_master(&self, config: &SentinelConfig) {
+        if self.masters.write().await.remove(config).is_some() {
+            info!(master_name = %config.master_name, "Forgot Redis master; Sentinel will be asked again");
+        }
+    }
+
+    /// Get the shared connection for a Redis Cluster instance, connecting on first use
+    pub async fn get_cluster_connection(
+        &self,
+        instance_id: &str,
+        endpoint: &RedisEndpoint,
+    ) -> Result<ClusterConnection, String> {
+        let instance_id = instance_id.to_string();
+        let url = endpoint.url();
+
+        if let Some(pooled) = self.clusters.read().await.get(&instance_id) {
+            if pooled.url == url {
+                return Ok(pooled.connection.clone());
+            }
+        }
+
+        let connection = self.dedicated_cluster_connection(endpoint).await?;
+        info!(instance_id = %instance_id, "Connected to Redis Cluster");
+
+        let mut clusters = self.clusters.write().await;
+        match clusters.get(&instance_id) {
+            Some(existing) if existing.url == url => Ok(existing.connection.clone()),
+            _ => {
+                clusters.insert(
+                    instance_id,
+                    PooledCluster {
+                        connection: connection.clone(),
+                        url,
+                    },
+                );
+                Ok(connection)
+            }
+        }
+    }
+
+    /// Open a cluster connection that is not shared with other requests
+    ///
+    /// The slot map is fetched from the seed node at `endpoint` while connecting.
+    pub async fn dedicated_cluster_connection(&self, endpoint: &RedisEndpoint) -> Result<ClusterConnection, String> {
+        let client = ClusterClient::new(vec![endpoint.url()]).map_err(|e| format!("Invalid URL: {}", e))?;
+
+        match timeout(self.connect_timeout, client.get_async_connection()).await {
+            Ok(Ok(conn)) => Ok(conn),
+            Ok(Err(e)) => Err(format!("Connection failed: {}", e)),
+            Err(_) => Err(format!(
+                "Connection failed: timed out after {}ms",
+                self.connect_timeout.as_millis()
+            )),
+        }
+    }
+
+    /// Open and verify a connection, retrying up to `attempts` times
+    async fn open(&self, instance_id: &str, redis_url: &str, attempts: u32) -> Result<PooledInstance, String> {
         let mut last_error = String::new();
 
-        for attempt in 1..=MAX_RETRY_ATTEMPTS {
-            match Client::open(redis_url.as_str()) {
+        for attempt in 1..=attempts {
+            match Client::open(redis_url) {
                 Ok(client) => {
-                    // Test connection with PING
-                    match client.get_connection() {
-                        Ok(mut conn) => {
+                    let connect = ConnectionManager::new_with_backoff(
+                        client.clone(),
+                        RECONNECT_BACKOFF_BASE,
+                        RECONNECT_BACKOFF_FACTOR_MS,
+                        0,
+                    );
+
+                    match timeout(self.connect_timeout, connect).await {
+                        Ok(Ok(mut manager)) => {
                             // Verify with PING
-                            match redis::cmd("PING").query::<String>(&mut conn) {
+                            match redis::cmd("PING").query_async::<_, String>(&mut manager).await {
                                 Ok(response) => {
                                     info!(
                                         instance_id = %instance_id,
@@ -63,13 +510,11 @@ impl RedisPool {
                                         "Successfully connected and verified with PING"
                                     );
 
-                                    // Store client in pool
-                                    self.connections
-                                        .write()
-                                        .await
-                                        .insert(instance_id.to_string(), client);
-
-                                    return Ok(());
+                                    return Ok(PooledInstance {
+                                        client,
+                                        manager,
+                                        url: redis_url.to_string(),
+                                    });
                                 }
                                 Err(e) => {
                                     last_error = format!("PING failed: {}", e);
@@ -82,7 +527,7 @@ impl RedisPool {
                                 }
                             }
                         }
-                        Err(e) => {
+                        Ok(Err(e)) => {
                             last_error = format!("Connection failed: {}", e);
                             warn!(
                                 instance_id = %instance_id,
@@ -91,6 +536,17 @@ impl RedisPool {
                                 "Failed to establish connection"
                             );
                         }
+                        Err(_) => {
+                            last_error = format!(
+                                "Connection failed: timed out after {}ms",
+                                self.connect_timeout.as_millis()
+                            );
+                            warn!(
+                                instance_id = %instance_id,
+                                attempt = %attempt,
+                                "Connection attempt timed out"
+                            );
+                        }
                     }
                 }
                 Err(e) => {
@@ -105,26 +561,26 @@ impl RedisPool {
             }
 
             // Wait before retry (except on last attempt)
-            if attempt < MAX_RETRY_ATTEMPTS {
+            if attempt < attempts {
                 warn!(
                     instance_id = %instance_id,
                     attempt = %attempt,
                     "Retrying connection in {}ms...",
-                    RETRY_DELAY_MS
+                    self.retry_delay.as_millis()
                 );
-                sleep(Duration::from_millis(RETRY_DELAY_MS)).await;
+                sleep(self.retry_delay).await;
             }
         }
 
         error!(
             instance_id = %instance_id,
             "Failed to connect after {} attempts",
-            MAX_RETRY_ATTEMPTS
+            attempts
         );
 
         Err(format!(
             "Failed to connect after {} attempts. Last error: {}",
-            MAX_RETRY_ATTEMPTS, last_error
+            attempts, last_error
         ))
     }
 
@@ -134,7 +590,7 @@ impl RedisPool {
             .read()
             .await
             .get(instance_id)
-            .cloned()
+            .map(|pooled| pooled.client.clone())
             .ok_or_else(|| {
                 warn!(instance_id = %instance_id, "No connection found in pool");
                 format!("No connection found for instance {}", instance_id)
@@ -143,24 +599,25 @@ impl RedisPool {
 
     /// Health check for a specific instance
     pub async fn health_check(&self, instance_id: &str) -> Result<String, String> {
-        let client = self.get_client(instance_id).await?;
-
-        match client.get_connection() {
-            Ok(mut conn) => {
-                match redis::cmd("PING").query::<String>(&mut conn) {
-                    Ok(response) => {
-                        info!(instance_id = %instance_id, "Health check passed");
-                        Ok(response)
-                    }
-                    Err(e) => {
-                        error!(instance_id = %instance_id, "Health check failed: {}", e);
-                        Err(format!("PING failed: {}", e))
-                    }
-                }
+        let mut manager = self
+            .connections
+            .read()
+            .await
+            .get(instance_id)
+            .map(|pooled| pooled.manager.clone())
+            .ok_or_else(|| {
+                warn!(instance_id = %instance_id, "No connection found in pool");
+                format!("No connection found for instance {}", instance_id)
+            })?;
+
+        match redis::cmd("PING").query_async::<_, String>(&mut manager).await {
+            Ok(response) => {
+                info!(instance_id = %instance_id, "Health check passed");
+                Ok(response)
             }
             Err(e) => {
-                error!(instance_id = %instance_id, "Failed to get connection: {}", e);
-                Err(format!("Connection error: {}", e))
+                error!(instance_id = %instance_id, "Health check failed: {}", e);
+                Err(format!("PING failed: {}", e))
             }
         }
     }
@@ -168,12 +625,13 @@ impl RedisPool {
     /// Remove an instance from the pool
     pub async fn remove_instance(&self, instance_id: &str) {
         self.connections.write().await.remove(instance_id);
+        self.clusters.write().await.remove(instance_id);
         info!(instance_id = %instance_id, "Instance removed from pool");
     }
 
     /// Get count of active connections
     pub async fn connection_count(&self) -> usize {
-        self.connections.read().await.len()
+        self.connections.read().await.len() + self.clusters.read().await.len()
     }
 
     /// Check if an instance exists in the pool
@@ -197,6 +655,157 @@ impl RedisPool {
     }
 }
 
+/// Shared connection to the master of a Sentinel-managed instance
+///
+/// A connection error makes the next request ask Sentinel for the master again. A READONLY
+/// reply means a failover demoted the server to a replica and the command was not applied,
+/// so it is retried once on the master Sentinel reports now.
+pub struct FailoverConnection {
+    pool: RedisPool,
+    instance_id: String,
+    endpoint: RedisEndpoint,
+    manager: ConnectionManager,
+}
+
+impl FailoverConnection {
+    /// Forget the master after `e` if it says the master moved; true when the command may be retried
+    async fn handle_error(&mut self, e: &RedisError) -> bool {
+        let Some(config) = &self.endpoint.sentinel else {
+            return false;
+        };
+
+        if e.code() == Some("READONLY") {
+            warn!(instance_id = %self.instance_id, "Redis master became read-only; looking up the new master");
+            self.pool.forget_master(config).await;
+            return match self.pool.get_connection(&self.instance_id, &self.endpoint).await {
+                Ok(manager) => {
+                    self.manager = manager;
+                    true
+                }
+                Err(error) => {
+                    error!(instance_id = %self.instance_id, "Failed to reach the new master: {}", error);
+                    false
+                }
+            };
+        }
+
+        if e.is_io_error() || e.is_connection_dropped() || e.is_connection_refusal() || e.is_timeout() {
+            warn!(instance_id = %self.instance_id, "Redis master connection failed: {}", e);
+            self.pool.forget_master(config).await;
+        }
+        false
+    }
+}
+
+impl ConnectionLike for FailoverConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        Box::pin(async move {
+            match self.manager.req_packed_command(cmd).await {
+                Err(e) => {
+                    if self.handle_error(&e).await {
+                        self.manager.req_packed_command(cmd).await
+                    } else {
+                        Err(e)
+                    }
+                }
+                result => result,
+            }
+        })
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        Box::pin(async move {
+            match self.manager.req_packed_commands(cmd, offset, count).await {
+                Err(e) => {
+                    if self.handle_error(&e).await {
+                        self.manager.req_packed_commands(cmd, offset, count).await
+                    } else {
+                        Err(e)
+                    }
+                }
+                result => result,
+            }
+        })
+    }
+
+    fn get_db(&self) -> i64 {
+        self.manager.get_db()
+    }
+}
+
+/// Whether a ROLE reply is that of a master: `["master", offset, replicas]`
+fn is_master_role(role: &Value) -> bool {
+    match role {
+        Value::Bulk(items) => matches!(items.first(), Some(Value::Data(name)) if name == b"master"),
+        _ => false,
+    }
+}
+
+/// Dedicated connection to the master of a Sentinel-managed instance
+///
+/// Unlike `FailoverConnection` it never retries: WATCH, MULTI or subscriptions on the
+/// connection would be lost by moving to another server. A READONLY reply or a connection
+/// error only makes the pool forget the master, so the next connection asks Sentinel.
+pub struct DedicatedMasterConnection {
+    pool: RedisPool,
+    config: SentinelConfig,
+    conn: Connection,
+}
+
+impl DedicatedMasterConnection {
+    pub fn new(pool: RedisPool, config: SentinelConfig, conn: Connection) -> Self {
+        Self { pool, config, conn }
+    }
+
+    async fn handle_error(&self, e: &RedisError) {
+        if e.code() == Some("READONLY")
+            || e.is_io_error()
+            || e.is_connection_dropped()
+            || e.is_connection_refusal()
+            || e.is_timeout()
+        {
+            warn!(master_name = %self.config.master_name, "Dedicated Redis master connection failed: {}", e);
+            self.pool.forget_master(&self.config).await;
+        }
+    }
+}
+
+impl ConnectionLike for DedicatedMasterConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        Box::pin(async move {
+            let result = self.conn.req_packed_command(cmd).await;
+            if let Err(e) = &result {
+                self.handle_error(e).await;
+            }
+            result
+        })
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        Box::pin(async move {
+            let result = self.conn.req_packed_commands(cmd, offset, count).await;
+            if let Err(e) = &result {
+                self.handle_error(e).await;
+            }
+            result
+        })
+    }
+
+    fn get_db(&self) -> i64 {
+        self.conn.get_db()
+    }
+}
+
 impl Default for RedisPool {
     fn default() -> Self {
         Self::new()
@@ -258,6 +867,117 @@ mod tests {
         assert!(result.is_err());
         assert!(result.unwrap_err().contains("Connection failed"));
     }
+
+    #[test]
+    fn test_build_redis_url() {
+        assert_eq!(build_redis_url("redis", "10.0.0.5", 6380, None), "redis://10.0.0.5:6380");
+        assert_eq!(
+            build_redis_url("redis", "redis.internal", 6379, Some("secret")),
+            "redis://:secret@redis.internal:6379"
+        );
+        assert_eq!(
+            build_redis_url("redis", "redis.internal", 6379, Some("p@ss#1%")),
+            "redis://:p%40ss%231%25@redis.internal:6379"
+        );
+        assert_eq!(
+            build_redis_url("rediss", "redis.internal", 6379, Some("secret")),
+            "rediss://:secret@redis.internal:6379"
+        );
+    }
+
+    #[test]
+    fn test_development_endpoint_url() {
+        assert_eq!(RedisEndpoint::local_development(6379, 0).url(), "redis://127.0.0.1:6379");
+        assert_eq!(RedisEndpoint::local_development(6379, 7).url(), "redis://127.0.0.1:6379/7");
+    }
+
+    #[test]
+    fn test_development_endpoint_is_standalone() {
+        assert_eq!(RedisEndpoint::local_development(6379, 3).topology, Topology::Standalone);
+    }
+
+    #[tokio::test]
+    async fn test_current_master() {
+        let pool = RedisPool::new();
+        let mut endpoint = RedisEndpoint {
+            host: "redis.internal".to_string(),
+            port: 6379,
+            password: Some("secret".to_string()),
+            database: 0,
+            topology: Topology::Standalone,
+            sentinel: None,
+        };
+        assert_eq!(pool.current_master(&endpoint).await.unwrap(), endpoint);
+
+        // A remembered master is used without asking Sentinel
+        let config = SentinelConfig::new("mymaster", &["127.0.0.1:1".to_string()]).unwrap();
+        endpoint.sentinel = Some(config.clone());
+        pool.masters
+            .write()
+            .await
+            .insert(config.clone(), ("10.0.0.9".to_string(), 6380));
+        let master = pool.current_master(&endpoint).await.unwrap();
+        assert_eq!(master.url(), "redis://:secret@10.0.0.9:6380");
+
+        // Once forgotten, the unreachable sentinel is asked again
+        pool.forget_master(&config).await;
+        assert!(pool.current_master(&endpoint).await.unwrap_err().contains("Sentinel lookup failed"));
+    }
+
+    #[test]
+    fn test_master_role() {
+        let master = Value::Bulk(vec![
+            Value::Data(b"master".to_vec()),
+            Value::Int(3129659),
+            Value::Bulk(vec![]),
+        ]);
+        assert!(is_master_role(&master));
+
+        let replica = Value::Bulk(vec![
+            Value::Data(b"slave".to_vec()),
+            Value::Data(b"10.0.0.9".to_vec()),
+            Value::Int(6380),
+            Value::Data(b"connected".to_vec()),
+            Value::Int(3167038),
+        ]);
+        assert!(!is_master_role(&replica));
+    }
+
+    #[tokio::test]
+    async fn test_dedicated_connection_asks_sentinel_again() {
+        let pool = RedisPool::new();
+        let config = SentinelConfig::new("mymaster", &["127.0.0.1:1".to_string()]).unwrap();
+        let endpoint = RedisEndpoint {
+            host: "redis.internal".to_string(),
+            port: 6379,
+            password: None,
+            database: 0,
+            topology: Topology::Standalone,
+            sentinel: Some(config.clone()),
+        };
+
+        // The remembered master is unreachable, so it is forgotten and Sentinel asked again
+        pool.masters
+            .write()
+            .await
+            .insert(config.clone(), ("127.0.0.1".to_string(), 1));
+        let Err(error) = pool.dedicated_connection(&endpoint).await else {
+            panic!("connected to an unreachable master");
+        };
+        assert!(error.contains("Sentinel lookup failed"), "{}", error);
+        assert!(!pool.masters.read().await.contains_key(&config));
+    }
+
+    #[test]
+    fn test_crosses_databases() {
+        assert!(RedisEndpoint::crosses_databases("select"));
+        assert!(RedisEndpoint::crosses_databases("FLUSHALL"));
+        assert!(RedisEndpoint::crosses_databases("eval"));
+        assert!(RedisEndpoint::crosses_databases("PUBSUB"));
+        assert!(!RedisEndpoint::crosses_databases("PUBLISH"));
+        assert!(!RedisEndpoint::crosses_databases("FLUSHDB"));
+        assert!(!RedisEndpoint::crosses_databases("GET"));
+    }
 }
 
 
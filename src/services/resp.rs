@@ -0,0 +1,265 @@
+// RESP2 wire format: parsing client commands and encoding replies
+
+use redis::Value;
+use thiserror::Error;
+
+/// Size limits applied to a client's commands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Largest number of arguments in one command
+    pub max_args: usize,
+    /// Largest bulk string
+    pub max_bulk_len: usize,
+    /// Largest inline command
+    pub max_inline_len: usize,
+}
+
+impl Limits {
+    /// Limits for an authenticated client (Redis' own defaults, e.g. proto-max-bulk-len)
+    pub const AUTHENTICATED: Limits = Limits {
+        max_args: 1024 * 1024,
+        max_bulk_len: 512 * 1024 * 1024,
+        max_inline_len: 64 * 1024,
+    };
+
+    /// Limits before `AUTH` succeeds, as Redis applies them, so an anonymous client cannot
+    /// make the gateway buffer large requests
+    pub const UNAUTHENTICATED: Limits = Limits {
+        max_args: 10,
+        max_bulk_len: 16 * 1024,
+        max_inline_len: 16 * 1024,
+    };
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum RespError {
+    #[error("Protocol error: {0}")]
+    Protocol(String),
+}
+
+/// A parsed command and the bytes it took, or `None` if more data is needed
+pub type ParseResult = Result<Option<(Vec<Vec<u8>>, usize)>, RespError>;
+
+/// Parse one command from the front of `buf`
+///
+/// Accepts both RESP arrays of bulk strings (what client libraries send) and inline
+/// commands (what `telnet`/`nc` users type). Returns the command arguments and the
+/// number of bytes consumed, or `None` if more data is needed.
+pub fn parse_command(buf: &[u8], limits: Limits) -> ParseResult {
+    match buf.first() {
+        None => Ok(None),
+        Some(b'*') => parse_array(buf, limits),
+        Some(_) => parse_inline(buf, limits),
+    }
+}
+
+fn parse_array(buf: &[u8], limits: Limits) -> ParseResult {
+    let Some((count, mut pos)) = read_line(buf, 1)? else {
+        return Ok(None);
+    };
+    let count = parse_length(count, "multibulk length")?;
+    if count > limits.max_args {
+        return Err(RespError::Protocol("invalid multibulk length".to_string()));
+    }
+
+    // Not preallocated from `count`: the arguments may never arrive
+    let mut args = Vec::new();
+    for _ in 0..count {
+        match buf.get(pos) {
+            None => return Ok(None),
+            Some(b'$') => {}
+            Some(&other) => {
+                return Err(RespError::Protocol(format!(
+                    "expected '$', got '{}'",
+                    other as char
+                )))
+            }
+        }
+
+        let Some((len, start)) = read_line(buf, pos + 1)? else {
+            return Ok(None);
+        };
+        let len = parse_length(len, "bulk length")?;
+        if len > limits.max_bulk_len {
+            return Err(RespError::Protocol("invalid bulk length".to_string()));
+        }
+
+        let end = start + len;
+        if buf.len() < end + 2 {
+            return Ok(None);
+        }
+        if &buf[end..end + 2] != b"\r\n" {
+            return Err(RespError::Protocol("bulk string not terminated by CRLF".to_string()));
+        }
+
+        args.push(buf[start..end].to_vec());
+        pos = end + 2;
+    }
+
+    Ok(Some((args, pos)))
+}
+
+fn parse_inline(buf: &[u8], limits: Limits) -> ParseResult {
+    let Some(newline) = buf.iter().position(|&b| b == b'\n') else {
+        if buf.len() > limits.max_inline_len {
+            return Err(RespError::Protocol("too big inline request".to_string()));
+        }
+        return Ok(None);
+    };
+
+    let line = buf[..newline].strip_suffix(b"\r").unwrap_or(&buf[..newline]);
+    let args = line
+        .split(|b| b.is_ascii_whitespace())
+        .filter(|part| !part.is_empty())
+        .map(|part| part.to_vec())
+        .collect();
+
+    Ok(Some((args, newline + 1)))
+}
+
+/// Read a CRLF-terminated line starting at `start`, returning it and the position after the CRLF
+fn read_line(buf: &[u8], start: usize) -> Result<Option<(&[u8], usize)>, RespError> {
+    let Some(rest) = buf.get(start..) else {
+        return Ok(None);
+    };
+    match rest.windows(2).position(|w| w == b"\r\n") {
+        Some(offset) => Ok(Some((&rest[..offset], start + offset + 2))),
+        None if rest.len() > 32 => Err(RespError::Protocol("length line too long".to_string())),
+        None => Ok(None),
+    }
+}
+
+fn parse_length(line: &[u8], what: &str) -> Result<usize, RespError> {
+    std::str::from_utf8(line)
+        .ok()
+        .and_then(|s| s.parse::<usize>().ok())
+        .ok_or_else(|| RespError::Protocol(format!("invalid {}", what)))
+}
+
+/// Encode a reply value
+pub fn encode_value(value: &Value, out: &mut Vec<u8>) {
+    match value {
+        Value::Nil => out.extend_from_slice(b"$-1\r\n"),
+        Value::Int(i) => out.extend_from_slice(format!(":{}\r\n", i).as_bytes()),
+        Value::Data(bytes) => {
+            out.extend_from_slice(format!("${}\r\n", bytes.len()).as_bytes());
+            out.extend_from_slice(bytes);
+            out.extend_from_slice(b"\r\n");
+        }
+        Value::Bulk(values) => {
+            out.extend_from_slice(format!("*{}\r\n", values.len()).as_bytes());
+            for value in values {
+                encode_value(value, out);
+            }
+        }
+        Value::Status(status) => encode_status(status, out),
+        Value::Okay => encode_status("OK", out),
+    }
+}
+
+/// Encode a simple string reply (`+OK`)
+pub fn encode_status(status: &str, out: &mut Vec<u8>) {
+    out.push(b'+');
+    out.extend_from_slice(single_line(status).as_bytes());
+    out.extend_from_slice(b"\r\n");
+}
+
+/// Encode an error reply; `message` should start with an error code such as `ERR`
+pub fn encode_error(message: &str, out: &mut Vec<u8>) {
+    out.push(b'-');
+    out.extend_from_slice(single_line(message).as_bytes());
+    out.extend_from_slice(b"\r\n");
+}
+
+/// Simple strings and errors cannot contain line breaks
+fn single_line(s: &str) -> String {
+    s.replace(['\r', '\n'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(values: &[&str]) -> Vec<Vec<u8>> {
+        values.iter().map(|v| v.as_bytes().to_vec()).collect()
+    }
+
+    #[test]
+    fn test_parse_array_command() {
+        let buf = b"*2\r\n$3\r\nGET\r\n$3\r\nfoo\r\n";
+        let (parsed, consumed) = parse_command(buf, Limits::AUTHENTICATED).unwrap().unwrap();
+        assert_eq!(parsed, args(&["GET", "foo"]));
+        assert_eq!(consumed, buf.len());
+    }
+
+    #[test]
+    fn test_parse_binary_argument() {
+        let buf = b"*3\r\n$3\r\nSET\r\n$1\r\nk\r\n$4\r\n\x00\r\n\xff\r\n";
+        let (parsed, _) = parse_command(buf, Limits::AUTHENTICATED).unwrap().unwrap();
+        assert_eq!(parsed[2], b"\x00\r\n\xff".to_vec());
+    }
+
+    #[test]
+    fn test_parse_incomplete_command() {
+        assert_eq!(parse_command(b"", Limits::AUTHENTICATED).unwrap(), None);
+        assert_eq!(parse_command(b"*2\r\n$3\r\nGET\r\n", Limits::AUTHENTICATED).unwrap(), None);
+        assert_eq!(parse_command(b"*2\r\n$3\r\nGET\r\n$3\r\nfo", Limits::AUTHENTICATED).unwrap(), None);
+    }
+
+    #[test]
+    fn test_parse_pipelined_commands() {
+        let buf = b"*1\r\n$4\r\nPING\r\n*1\r\n$4\r\nPING\r\n";
+        let (_, consumed) = parse_command(buf, Limits::AUTHENTICATED).unwrap().unwrap();
+        let (parsed, _) = parse_command(&buf[consumed..], Limits::AUTHENTICATED).unwrap().unwrap();
+        assert_eq!(parsed, args(&["PING"]));
+    }
+
+    #[test]
+    fn test_parse_inline_command() {
+        let (parsed, consumed) = parse_command(b"SET  foo bar\r\n", Limits::AUTHENTICATED).unwrap().unwrap();
+        assert_eq!(parsed, args(&["SET", "foo", "bar"]));
+        assert_eq!(consumed, 14);
+    }
+
+    #[test]
+    fn test_parse_invalid_length() {
+        assert!(parse_command(b"*x\r\n", Limits::AUTHENTICATED).is_err());
+        assert!(parse_command(b"*1\r\n+PING\r\n", Limits::AUTHENTICATED).is_err());
+    }
+
+    #[test]
+    fn test_unauthenticated_limits() {
+        let limits = Limits::UNAUTHENTICATED;
+        assert!(parse_command(b"*2\r\n$4\r\nAUTH\r\n$3\r\nkey\r\n", limits).unwrap().is_some());
+
+        // Rejected from the length alone, before any of the data arrives
+        assert!(parse_command(b"*11\r\n", limits).is_err());
+        assert!(parse_command(b"*1000000\r\n", limits).is_err());
+        assert!(parse_command(b"*2\r\n$4\r\nAUTH\r\n$16385\r\n", limits).is_err());
+        assert!(parse_command(&[b'A'; 16 * 1024 + 1], limits).is_err());
+
+        assert_eq!(parse_command(b"*1000000\r\n", Limits::AUTHENTICATED).unwrap(), None);
+    }
+
+    #[test]
+    fn test_encode_values() {
+        let mut out = Vec::new();
+        encode_value(
+            &Value::Bulk(vec![
+                Value::Okay,
+                Value::Int(7),
+                Value::Data(b"hi".to_vec()),
+                Value::Nil,
+            ]),
+            &mut out,
+        );
+        assert_eq!(out, b"*4\r\n+OK\r\n:7\r\n$2\r\nhi\r\n$-1\r\n".to_vec());
+    }
+
+    #[test]
+    fn test_encode_error_is_single_line() {
+        let mut out = Vec::new();
+        encode_error("ERR bad\r\nthing", &mut out);
+        assert_eq!(out, b"-ERR bad  thing\r\n".to_vec());
+    }
+}
@@ -0,0 +1,487 @@
+// Registry of named Lua scripts per instance, stored in Postgres
+
+use chrono::{DateTime, Utc};
+use moka::future::Cache;
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::services::command_scope::{classify_command, CommandClass};
+
+/// How long a script is trusted before re-reading the database
+const SCRIPT_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// Maximum number of scripts kept in memory
+const MAX_CACHED_SCRIPTS: u64 = 10_000;
+
+/// Longest accepted script name
+const MAX_SCRIPT_NAME_LEN: usize = 100;
+
+/// Largest accepted script source, in bytes
+const MAX_SCRIPT_SIZE: usize = 64 * 1024;
+
+/// Tables that give Lua access to Redis (`server` is the Redis 7 name)
+const COMMAND_TABLES: &[&str] = &["redis", "server"];
+
+/// Functions of those tables that run Redis commands
+const CALL_FUNCTIONS: &[&str] = &["call", "pcall"];
+
+/// Other members of those tables, none of which can run a command
+const HARMLESS_MEMBERS: &[&str] = &[
+    "log", "LOG_DEBUG", "LOG_VERBOSE", "LOG_NOTICE", "LOG_WARNING", "sha1hex", "error_reply",
+    "status_reply", "setresp", "set_repl", "replicate_commands", "REPL_ALL", "REPL_AOF",
+    "REPL_REPLICA", "REPL_SLAVE", "REPL_NONE", "breakpoint", "debug", "acl_check_cmd", "REDIS_VERSION",
+    "REDIS_VERSION_NUM",
+];
+
+/// Globals that can reach the command tables without naming them, e.g. `_G["redis"]`
+const INDIRECT_ACCESS: &[&str] = &[
+    "_G", "_ENV", "getfenv", "setfenv", "rawget", "load", "loadstring", "loadfile", "dofile", "require",
+];
+
+#[derive(Debug, Error)]
+pub enum ScriptError {
+    #[error("Invalid script name: {0:?}")]
+    InvalidName(String),
+
+    #[error("Script source must be between 1 and {} bytes", MAX_SCRIPT_SIZE)]
+    InvalidSource,
+
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+/// Redis commands a script calls, found by scanning its source
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ScriptCommands {
+    /// Command names passed as string literals, upper case, sorted and de-duplicated
+    pub commands: Vec<String>,
+    /// Whether some calls compute the command name at run time (or `redis.call` is passed
+    /// around as a value), so `commands` may be incomplete
+    pub dynamic: bool,
+}
+
+impl ScriptCommands {
+    /// Access class needed to run the script: that of its most privileged command
+    ///
+    /// Scripts whose commands cannot all be known in advance could run anything, so they
+    /// count as dangerous.
+    pub fn class(&self) -> CommandClass {
+        if self.dynamic {
+            return CommandClass::Dangerous;
+        }
+        self.commands
+            .iter()
+            .map(|command| classify_command(command))
+            .max()
+            .unwrap_or(CommandClass::Read)
+    }
+}
+
+/// Find the commands a Lua script calls
+///
+/// Only `redis.call("NAME", ...)` with a plain string literal counts as a known command.
+/// This fails closed: any other use of `redis` or `server` outside strings and comments
+/// (variables, concatenation, escapes, `redis["call"]`, `redis .call`, aliasing the table or
+/// the function), or of a global that can reach them indirectly, marks the script as dynamic.
+pub fn scan_commands(source: &str) -> ScriptCommands {
+    let mut found = ScriptCommands::default();
+    let bytes = source.as_bytes();
+    // Last byte of code seen, to tell `t.redis` (a field) from `redis` itself
+    let mut previous: Option<u8> = None;
+    let mut before_previous: Option<u8> = None;
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        let byte = bytes[pos];
+        if byte.is_ascii_whitespace() {
+            pos += 1;
+            continue;
+        }
+        if source[pos..].starts_with("--") {
+            pos = skip_comment(source, pos + 2);
+            continue;
+        }
+        if byte == b'"' || byte == b'\'' {
+            pos = skip_quoted(bytes, pos);
+            (before_previous, previous) = (previous, Some(byte));
+            continue;
+        }
+        if let Some(end) = long_bracket_end(source, pos) {
+            pos = end;
+            (before_previous, previous) = (previous, Some(b']'));
+            continue;
+        }
+        if byte.is_ascii_digit() {
+            pos = identifier_end(bytes, pos);
+            (before_previous, previous) = (previous, Some(b'0'));
+            continue;
+        }
+        if !(byte.is_ascii_alphabetic() || byte == b'_') {
+            pos += 1;
+            (before_previous, previous) = (previous, Some(byte));
+            continue;
+        }
+
+        let end = identifier_end(bytes, pos);
+        let name = &source[pos..end];
+        let is_field = matches!(previous, Some(b':'))
+            || (previous == Some(b'.') && before_previous != Some(b'.'));
+        pos = end;
+        (before_previous, previous) = (previous, Some(b'a'));
+        if is_field {
+            continue;
+        }
+
+        if INDIRECT_ACCESS.contains(&name) {
+            found.dynamic = true;
+        } else if COMMAND_TABLES.contains(&name) {
+            // Only `redis.member`, with nothing in between, is understood
+            let member_end = if bytes.get(pos) == Some(&b'.') {
+                identifier_end(bytes, pos + 1)
+            } else {
+                pos
+            };
+            let member = &source[(pos + 1).min(member_end)..member_end];
+            if CALL_FUNCTIONS.contains(&member) {
+                match literal_command(&source[member_end..]) {
+                    Some(command) => found.commands.push(command.to_uppercase()),
+                    None => found.dynamic = true,
+                }
+            } else if !HARMLESS_MEMBERS.contains(&member) {
+                found.dynamic = true;
+            }
+            pos = member_end;
+        }
+    }
+
+    found.commands.sort();
+    found.commands.dedup();
+    found
+}
+
+/// End of the identifier (or number) starting at `start`
+fn identifier_end(bytes: &[u8], start: usize) -> usize {
+    bytes[start..]
+        .iter()
+        .position(|b| !(b.is_ascii_alphanumeric() || *b == b'_'))
+        .map_or(bytes.len(), |offset| start + offset)
+}
+
+/// End of a quoted string starting at `start`, or of the source if it is unterminated
+fn skip_quoted(bytes: &[u8], start: usize) -> usize {
+    let quote = bytes[start];
+    let mut pos = start + 1;
+    while pos < bytes.len() {
+        match bytes[pos] {
+            b'\\' => pos += 2,
+            b if b == quote => return pos + 1,
+            _ => pos += 1,
+        }
+    }
+    bytes.len()
+}
+
+/// End of a long string or comment body (`[[...]]`, `[==[...]==]`) starting at `start`
+fn long_bracket_end(source: &str, start: usize) -> Option<usize> {
+    let rest = source[start..].strip_prefix('[')?;
+    let level = rest.bytes().take_while(|b| *b == b'=').count();
+    rest[level..].strip_prefix('[')?;
+    let close = format!("]{}]", "=".repeat(level));
+    let body = start + level + 2;
+    Some(source[body..].find(&close).map_or(source.len(), |offset| body + offset + close.len()))
+}
+
+/// End of a comment whose `--` ends just before `start`
+fn skip_comment(source: &str, start: usize) -> usize {
+    if let Some(end) = long_bracket_end(source, start) {
+        return end;
+    }
+    source[start..].find('\n').map_or(source.len(), |offset| start + offset + 1)
+}
+
+/// The command name of a call if it is a plain string literal: `("GET", KEYS[1])` -> `GET`
+fn literal_command(call: &str) -> Option<&str> {
+    let call = call.trim_start().strip_prefix('(')?.trim_start();
+    let quote = call.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let body = &call[1..];
+    let end = body.find(quote)?;
+    let name = &body[..end];
+    if name.is_empty() || name.contains('\\') {
+        return None;
+    }
+
+    let after = body[end + 1..].trim_start();
+    if after.starts_with(',') || after.starts_with(')') {
+        Some(name)
+    } else {
+        None
+    }
+}
+
+fn validate_name(name: &str) -> Result<(), ScriptError> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_SCRIPT_NAME_LEN
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.');
+    if valid {
+        Ok(())
+    } else {
+        Err(ScriptError::InvalidName(name.to_string()))
+    }
+}
+
+/// SHA1 of a script, as Redis computes it for EVALSHA
+pub fn script_sha1(source: &str) -> String {
+    redis::Script::new(source).get_hash().to_string()
+}
+
+/// A script as stored, with the commands found in it
+#[derive(Debug, Clone)]
+pub struct RegisteredScript {
+    pub instance_id: Uuid,
+    pub name: String,
+    pub source: String,
+    pub sha1: String,
+    pub commands: ScriptCommands,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl RegisteredScript {
+    fn from_row((instance_id, name, source, sha1, updated_at): ScriptRow) -> Self {
+        let commands = scan_commands(&source);
+        Self {
+            instance_id,
+            name,
+            source,
+            sha1,
+            commands,
+            updated_at,
+        }
+    }
+}
+
+type ScriptRow = (Uuid, String, String, String, DateTime<Utc>);
+
+/// Loads scripts from the `redis_instance_scripts` table and caches them
+pub struct ScriptService {
+    db_pool: PgPool,
+    /// (instance_id, name) -> script
+    scripts: Cache<(Uuid, String), Arc<RegisteredScript>>,
+}
+
+impl ScriptService {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self {
+            db_pool,
+            scripts: Cache::builder()
+                .max_capacity(MAX_CACHED_SCRIPTS)
+                .time_to_live(SCRIPT_CACHE_TTL)
+                .build(),
+        }
+    }
+
+    /// A script by name, or `None` if the instance has no script with that name
+    pub async fn script(&self, instance_id: Uuid, name: &str) -> Result<Option<Arc<RegisteredScript>>, sqlx::Error> {
+        let key = (instance_id, name.to_string());
+        if let Some(script) = self.scripts.get(&key).await {
+            return Ok(Some(script));
+        }
+
+        let row: Option<ScriptRow> = sqlx::query_as(
+            r#"
+            SELECT instance_id, name, source, sha1, updated_at
+            FROM redis_instance_scripts
+            WHERE instance_id = $1 AND name = $2
+            "#,
+        )
+        .bind(instance_id)
+        .bind(name)
+        .fetch_optional(&self.db_pool)
+        .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+
+        let script = Arc::new(RegisteredScript::from_row(row));
+        self.scripts.insert(key, script.clone()).await;
+        Ok(Some(script))
+    }
+
+    /// All scripts of an instance, ordered by name
+    pub async fn list(&self, instance_id: Uuid) -> Result<Vec<RegisteredScript>, sqlx::Error> {
+        let rows: Vec<ScriptRow> = sqlx::query_as(
+            r#"
+            SELECT instance_id, name, source, sha1, updated_at
+            FROM redis_instance_scripts
+            WHERE instance_id = $1
+            ORDER BY name
+            "#,
+        )
+        .bind(instance_id)
+        .fetch_all(&self.db_pool)
+        .await?;
+
+        Ok(rows.into_iter().map(RegisteredScript::from_row).collect())
+    }
+
+    /// Create or replace a script
+    pub async fn put(
+        &self,
+        instance_id: Uuid,
+        name: &str,
+        source: String,
+        updated_by: Uuid,
+    ) -> Result<RegisteredScript, ScriptError> {
+        validate_name(name)?;
+        if source.trim().is_empty() || source.len() > MAX_SCRIPT_SIZE {
+            return Err(ScriptError::InvalidSource);
+        }
+
+        let row: ScriptRow = sqlx::query_as(
+            r#"
+            INSERT INTO redis_instance_scripts (instance_id, name, source, sha1, updated_by)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (instance_id, name) DO UPDATE
+            SET source = EXCLUDED.source,
+                sha1 = EXCLUDED.sha1,
+                updated_by = EXCLUDED.updated_by,
+                updated_at = NOW()
+            RETURNING instance_id, name, source, sha1, updated_at
+            "#,
+        )
+        .bind(instance_id)
+        .bind(name)
+        .bind(&source)
+        .bind(script_sha1(&source))
+        .bind(updated_by)
+        .fetch_one(&self.db_pool)
+        .await?;
+
+        let script = RegisteredScript::from_row(row);
+        self.scripts
+            .insert((instance_id, name.to_string()), Arc::new(script.clone()))
+            .await;
+        info!("Script {} of instance {} stored", name, instance_id);
+        Ok(script)
+    }
+
+    /// Delete a script, returning whether it existed
+    pub async fn delete(&self, instance_id: Uuid, name: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM redis_instance_scripts WHERE instance_id = $1 AND name = $2")
+            .bind(instance_id)
+            .bind(name)
+            .execute(&self.db_pool)
+            .await?;
+
+        self.scripts.invalidate(&(instance_id, name.to_string())).await;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn test_scan_literal_commands() {
+        let source = r#"
+            local current = redis.call('GET', KEYS[1])
+            if current then
+                redis.call("incrby", KEYS[1], ARGV[1])
+            end
+            return redis.pcall( 'GET' , KEYS[1])
+        "#;
+        let found = scan_commands(source);
+        assert_eq!(found.commands, names(&["GET", "INCRBY"]));
+        assert!(!found.dynamic);
+        assert_eq!(found.class(), CommandClass::Write);
+    }
+
+    #[test]
+    fn test_scan_read_only_script() {
+        let found = scan_commands("return server.call('HGETALL', KEYS[1])");
+        assert_eq!(found.commands, names(&["HGETALL"]));
+        assert_eq!(found.class(), CommandClass::Read);
+    }
+
+    #[test]
+    fn test_scan_dynamic_commands() {
+        for source in [
+            "return redis.call(ARGV[1], KEYS[1])",
+            "return redis.call('FLUSH' .. 'ALL')",
+            "local call = redis.call\nreturn call('FLUSHALL')",
+            "return redis.call('GE\\84', KEYS[1])",
+        ] {
+            let found = scan_commands(source);
+            assert!(found.dynamic, "{} should be dynamic", source);
+            assert_eq!(found.class(), CommandClass::Dangerous);
+        }
+    }
+
+    #[test]
+    fn test_scan_ignores_other_identifiers() {
+        let source = r#"
+            local handler = myredis.callx
+            -- redis.call('FLUSHALL')
+            --[[ server.call('FLUSHALL') ]]
+            local label = "redis" .. [[server]] .. config.redis
+            redis.log(redis.LOG_NOTICE, label)
+            return redis.call('PING', 'server')
+        "#;
+        let found = scan_commands(source);
+        assert_eq!(found.commands, names(&["PING"]));
+        assert!(!found.dynamic);
+    }
+
+    #[test]
+    fn test_scan_fails_closed() {
+        for source in [
+            "return redis[\"call\"](\"FLUSHALL\")",
+            "return redis .call('FLUSHALL')",
+            "return redis. call('FLUSHALL')",
+            "local r = redis; return r.call('FLUSHALL')",
+            "local r = {redis}; return r[1].call('SELECT', 0)",
+            "return redis.callback",
+            "return redis:call('FLUSHALL')",
+            "return redis.call'FLUSHALL'",
+            "return pcall(redis.call, 'FLUSHALL')",
+            "return _G.redis.call('FLUSHALL')",
+            "return rawget(_G, 'redis').call('FLUSHALL')",
+            "return loadstring('return redis.call(\"FLUSHALL\")')()",
+            "return 'x' .. redis.call('GET', KEYS[1]) .. redis",
+        ] {
+            let found = scan_commands(source);
+            assert!(found.dynamic, "{} should be dynamic", source);
+            assert_eq!(found.class(), CommandClass::Dangerous);
+        }
+    }
+
+    #[test]
+    fn test_class_of_dangerous_commands() {
+        let found = scan_commands("return redis.call('FLUSHDB')");
+        assert_eq!(found.class(), CommandClass::Dangerous);
+        assert_eq!(scan_commands("return 1").class(), CommandClass::Read);
+    }
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("rate-limit.v2").is_ok());
+        assert!(validate_name("").is_err());
+        assert!(validate_name("has space").is_err());
+        assert!(validate_name(&"a".repeat(101)).is_err());
+    }
+
+    #[test]
+    fn test_script_sha1() {
+        // SHA1 of "return 1", as returned by SCRIPT LOAD
+        assert_eq!(script_sha1("return 1"), "e0e1f9fabfc9d4800c877a703b823ac0578ff8db");
+    }
+}
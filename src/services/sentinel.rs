@@ -0,0 +1,248 @@
+// Redis Sentinel: finding the current master of an instance run with Sentinel for failover
+
+use redis::Client;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::time::timeout;
+use tracing::{debug, warn};
+
+use crate::models::RedisInstance;
+
+/// Port Sentinel listens on when an endpoint does not name one
+pub const DEFAULT_SENTINEL_PORT: u16 = 26379;
+
+/// Most sentinels an instance may list
+const MAX_SENTINELS: usize = 16;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum SentinelError {
+    #[error("Sentinel master name must not be empty")]
+    EmptyMasterName,
+    #[error("At least one sentinel endpoint is required")]
+    NoSentinels,
+    #[error("At most {0} sentinel endpoints are allowed")]
+    TooManySentinels(usize),
+    #[error("Invalid sentinel endpoint '{0}', expected host:port")]
+    InvalidEndpoint(String),
+    #[error("No sentinel knows a master named '{0}'")]
+    UnknownMaster(String),
+    #[error("No sentinel could be reached: {0}")]
+    Unreachable(String),
+}
+
+/// Sentinels watching an instance and the name they know its master by
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SentinelConfig {
+    pub master_name: String,
+    pub sentinels: Vec<(String, u16)>,
+}
+
+impl SentinelConfig {
+    /// Validate a master name and a list of `host:port` sentinel endpoints
+    pub fn new(master_name: &str, endpoints: &[String]) -> Result<Self, SentinelError> {
+        let master_name = master_name.trim();
+        if master_name.is_empty() {
+            return Err(SentinelError::EmptyMasterName);
+        }
+        if endpoints.is_empty() {
+            return Err(SentinelError::NoSentinels);
+        }
+        if endpoints.len() > MAX_SENTINELS {
+            return Err(SentinelError::TooManySentinels(MAX_SENTINELS));
+        }
+
+        let mut sentinels = Vec::with_capacity(endpoints.len());
+        for endpoint in endpoints {
+            let node = parse_endpoint(endpoint)?;
+            if !sentinels.contains(&node) {
+                sentinels.push(node);
+            }
+        }
+
+        Ok(Self {
+            master_name: master_name.to_string(),
+            sentinels,
+        })
+    }
+
+    /// Sentinel settings stored for an instance, if it has any
+    ///
+    /// Rows are validated when written, so invalid entries are only logged and skipped.
+    pub fn from_instance(instance: &RedisInstance) -> Option<Self> {
+        let master_name = instance.sentinel_master_name.as_deref()?;
+        let sentinels: Vec<(String, u16)> = instance
+            .sentinel_endpoints
+            .iter()
+            .filter_map(|endpoint| match parse_endpoint(endpoint) {
+                Ok(node) => Some(node),
+                Err(e) => {
+                    warn!("Ignoring sentinel endpoint of instance {}: {}", instance.id, e);
+                    None
+                }
+            })
+            .collect();
+
+        if sentinels.is_empty() {
+            warn!("Instance {} names Sentinel master {} but has no sentinels", instance.id, master_name);
+            return None;
+        }
+
+        Some(Self {
+            master_name: master_name.to_string(),
+            sentinels,
+        })
+    }
+
+    /// Endpoints in the `host:port` form they are stored in
+    pub fn endpoints(&self) -> Vec<String> {
+        self.sentinels
+            .iter()
+            .map(|(host, port)| format_address(host, *port))
+            .collect()
+    }
+}
+
+/// `host:port` of an address, with brackets around IPv6 hosts
+pub fn format_address(host: &str, port: u16) -> String {
+    if host.contains(':') {
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    }
+}
+
+/// Parse `host`, `host:port` or `[ipv6]:port`
+pub fn parse_endpoint(endpoint: &str) -> Result<(String, u16), SentinelError> {
+    let invalid = || SentinelError::InvalidEndpoint(endpoint.to_string());
+    let endpoint = endpoint.trim();
+
+    let (host, port) = if let Some(rest) = endpoint.strip_prefix('[') {
+        let (host, rest) = rest.split_once(']').ok_or_else(invalid)?;
+        match rest {
+            "" => (host, None),
+            _ => (host, Some(rest.strip_prefix(':').ok_or_else(invalid)?)),
+        }
+    } else {
+        match endpoint.split_once(':') {
+            Some((_, port)) if port.contains(':') => return Err(invalid()),
+            Some((host, port)) => (host, Some(port)),
+            None => (endpoint, None),
+        }
+    };
+
+    if host.is_empty() || host.contains(char::is_whitespace) {
+        return Err(invalid());
+    }
+    let port = match port {
+        Some(port) => port.parse::<u16>().ok().filter(|port| *port > 0).ok_or_else(invalid)?,
+        None => DEFAULT_SENTINEL_PORT,
+    };
+    Ok((host.to_string(), port))
+}
+
+/// Ask the sentinels, in order, where the current master is
+///
+/// The first sentinel that knows the master answers. Sentinels are reached without a password.
+pub async fn resolve_master(config: &SentinelConfig, connect_timeout: Duration) -> Result<(String, u16), SentinelError> {
+    let mut errors = Vec::new();
+    let mut unknown = false;
+
+    for (host, port) in &config.sentinels {
+        let address = format_address(host, *port);
+        match ask_sentinel(&address, &config.master_name, connect_timeout).await {
+            Ok(Some(master)) => {
+                debug!("Sentinel {} reports master {} at {}:{}", address, config.master_name, master.0, master.1);
+                return Ok(master);
+            }
+            Ok(None) => unknown = true,
+            Err(e) => {
+                warn!("Sentinel {} did not answer: {}", address, e);
+                errors.push(format!("{}: {}", address, e));
+            }
+        }
+    }
+
+    if unknown {
+        Err(SentinelError::UnknownMaster(config.master_name.clone()))
+    } else {
+        Err(SentinelError::Unreachable(errors.join("; ")))
+    }
+}
+
+async fn ask_sentinel(address: &str, master_name: &str, connect_timeout: Duration) -> Result<Option<(String, u16)>, String> {
+    let client = Client::open(format!("redis://{}", address)).map_err(|e| e.to_string())?;
+    let query = async {
+        let mut conn = client.get_async_connection().await?;
+        redis::cmd("SENTINEL")
+            .arg("get-master-addr-by-name")
+            .arg(master_name)
+            .query_async::<_, Option<(String, String)>>(&mut conn)
+            .await
+    };
+
+    match timeout(connect_timeout, query).await {
+        Ok(Ok(reply)) => parse_master_reply(reply),
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(_) => Err(format!("timed out after {}ms", connect_timeout.as_millis())),
+    }
+}
+
+/// Read a `SENTINEL get-master-addr-by-name` reply: nil, or the master's host and port
+fn parse_master_reply(reply: Option<(String, String)>) -> Result<Option<(String, u16)>, String> {
+    match reply {
+        Some((host, port)) => port
+            .parse::<u16>()
+            .map(|port| Some((host, port)))
+            .map_err(|_| format!("invalid master port '{}'", port)),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_endpoint() {
+        assert_eq!(parse_endpoint("sentinel-0.redis:26380"), Ok(("sentinel-0.redis".to_string(), 26380)));
+        assert_eq!(parse_endpoint("10.0.0.7"), Ok(("10.0.0.7".to_string(), DEFAULT_SENTINEL_PORT)));
+        assert_eq!(parse_endpoint("[fd00::7]:26379"), Ok(("fd00::7".to_string(), 26379)));
+        assert!(parse_endpoint("host:0").is_err());
+        assert!(parse_endpoint("host:port").is_err());
+        assert!(parse_endpoint(":26379").is_err());
+        assert!(parse_endpoint("fd00::7").is_err());
+    }
+
+    #[test]
+    fn test_config_validation() {
+        let endpoints = vec!["s1:26379".to_string(), "s2".to_string(), "s1:26379".to_string()];
+        let config = SentinelConfig::new(" mymaster ", &endpoints).unwrap();
+        assert_eq!(config.master_name, "mymaster");
+        assert_eq!(config.endpoints(), vec!["s1:26379", "s2:26379"]);
+
+        assert_eq!(SentinelConfig::new("", &endpoints), Err(SentinelError::EmptyMasterName));
+        assert_eq!(SentinelConfig::new("mymaster", &[]), Err(SentinelError::NoSentinels));
+        let many: Vec<String> = (0..=MAX_SENTINELS).map(|i| format!("s{}", i)).collect();
+        assert_eq!(
+            SentinelConfig::new("mymaster", &many),
+            Err(SentinelError::TooManySentinels(MAX_SENTINELS))
+        );
+    }
+
+    #[test]
+    fn test_parse_master_reply() {
+        assert_eq!(
+            parse_master_reply(Some(("10.0.0.5".to_string(), "6379".to_string()))),
+            Ok(Some(("10.0.0.5".to_string(), 6379)))
+        );
+        assert_eq!(parse_master_reply(None), Ok(None));
+        assert!(parse_master_reply(Some(("10.0.0.5".to_string(), "x".to_string()))).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_sentinels() {
+        let config = SentinelConfig::new("mymaster", &["127.0.0.1:1".to_string()]).unwrap();
+        let result = resolve_master(&config, Duration::from_millis(500)).await;
+        assert!(matches!(result, Err(SentinelError::Unreachable(_))));
+    }
+}
@@ -0,0 +1,234 @@
+// Sorted set replies shaped as `{member, score}` objects instead of flat arrays
+
+use serde::{Serialize, Serializer};
+use serde_json::{json, Value};
+
+/// A sorted set member with its score
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScoredMember {
+    pub member: String,
+    #[serde(serialize_with = "serialize_score")]
+    pub score: f64,
+}
+
+/// A leaderboard entry; `rank` starts at 1 for the highest score
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RankedMember {
+    pub rank: u64,
+    pub member: String,
+    #[serde(serialize_with = "serialize_score")]
+    pub score: f64,
+}
+
+/// Scores can be `inf`/`-inf`, which JSON numbers cannot represent, so those are sent as strings
+fn serialize_score<S: Serializer>(score: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+    score_to_json(*score).serialize(serializer)
+}
+
+pub fn score_to_json(score: f64) -> Value {
+    if score.is_finite() {
+        json!(score)
+    } else if score > 0.0 {
+        json!("inf")
+    } else {
+        json!("-inf")
+    }
+}
+
+/// Minimum number of arguments of the sorted set commands with structured replies, and
+/// what they are (for error messages)
+pub fn required_args(command: &str) -> Option<(usize, &'static str)> {
+    match command {
+        "ZADD" => Some((3, "key, score and member")),
+        "ZINCRBY" => Some((3, "key, increment and member")),
+        "ZRANGE" => Some((3, "key, start and stop")),
+        "ZRANGEBYSCORE" => Some((3, "key, min and max")),
+        "ZRANK" | "ZREM" => Some((2, "key and member")),
+        _ => None,
+    }
+}
+
+/// Parse a score as Redis formats it (`"1.5"`, `"inf"`, `"-inf"`)
+pub fn parse_score(value: &redis::Value) -> Option<f64> {
+    match value {
+        redis::Value::Data(bytes) => std::str::from_utf8(bytes).ok()?.parse().ok(),
+        redis::Value::Status(s) => s.parse().ok(),
+        redis::Value::Int(i) => Some(*i as f64),
+        _ => None,
+    }
+}
+
+/// Pair up a `WITHSCORES` reply (`[member, score, member, score, ...]`)
+pub fn scored_members(value: &redis::Value) -> Option<Vec<ScoredMember>> {
+    let redis::Value::Bulk(items) = value else {
+        return None;
+    };
+    if items.len() % 2 != 0 {
+        return None;
+    }
+
+    items
+        .chunks(2)
+        .map(|pair| {
+            let redis::Value::Data(member) = &pair[0] else {
+                return None;
+            };
+            Some(ScoredMember {
+                member: String::from_utf8_lossy(member).into_owned(),
+                score: parse_score(&pair[1])?,
+            })
+        })
+        .collect()
+}
+
+/// Number leaderboard entries starting from rank `first_rank`
+pub fn ranked(members: Vec<ScoredMember>, first_rank: u64) -> Vec<RankedMember> {
+    members
+        .into_iter()
+        .zip(first_rank..)
+        .map(|(entry, rank)| RankedMember {
+            rank,
+            member: entry.member,
+            score: entry.score,
+        })
+        .collect()
+}
+
+/// Whether `flag` appears among the options after the first `skip` arguments
+fn has_option(args: &[String], skip: usize, flag: &str) -> bool {
+    args.iter().skip(skip).any(|arg| arg.eq_ignore_ascii_case(flag))
+}
+
+/// Whether a ZADD uses the `INCR` option
+///
+/// Options come before the first score, so scanning stops there; a member that happens to
+/// be called "incr" is not mistaken for the option.
+fn zadd_increments(args: &[String]) -> bool {
+    args.iter()
+        .skip(1)
+        .take_while(|arg| ["NX", "XX", "GT", "LT", "CH", "INCR"].iter().any(|o| arg.eq_ignore_ascii_case(o)))
+        .any(|arg| arg.eq_ignore_ascii_case("INCR"))
+}
+
+/// Shape the reply of a sorted set command
+///
+/// Returns `None` when the plain conversion already fits, e.g. integer replies of ZADD and
+/// ZREM or ranges requested without scores.
+pub fn structured_reply(command: &str, args: &[String], value: &redis::Value) -> Option<Value> {
+    match command {
+        "ZADD" if zadd_increments(args) => Some(match value {
+            // NX/XX/GT/LT can prevent the update, in which case the reply is nil
+            redis::Value::Nil => Value::Null,
+            _ => score_to_json(parse_score(value)?),
+        }),
+        "ZINCRBY" => Some(score_to_json(parse_score(value)?)),
+        "ZRANGE" | "ZRANGEBYSCORE" if has_option(args, 3, "WITHSCORES") => {
+            serde_json::to_value(scored_members(value)?).ok()
+        }
+        "ZRANK" if has_option(args, 2, "WITHSCORE") => match value {
+            redis::Value::Nil => Some(Value::Null),
+            redis::Value::Bulk(items) => match items.as_slice() {
+                [redis::Value::Int(rank), score] => {
+                    Some(json!({"rank": rank, "score": score_to_json(parse_score(score)?)}))
+                }
+                _ => None,
+            },
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(s: &str) -> redis::Value {
+        redis::Value::Data(s.as_bytes().to_vec())
+    }
+
+    fn args(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_score() {
+        assert_eq!(parse_score(&data("1.5")), Some(1.5));
+        assert_eq!(parse_score(&data("inf")), Some(f64::INFINITY));
+        assert_eq!(parse_score(&data("-inf")), Some(f64::NEG_INFINITY));
+        assert_eq!(parse_score(&redis::Value::Int(3)), Some(3.0));
+        assert_eq!(parse_score(&data("abc")), None);
+        assert_eq!(parse_score(&redis::Value::Nil), None);
+    }
+
+    #[test]
+    fn test_scored_members() {
+        let reply = redis::Value::Bulk(vec![data("alice"), data("10"), data("bob"), data("7.5")]);
+        assert_eq!(
+            scored_members(&reply).unwrap(),
+            vec![
+                ScoredMember { member: "alice".to_string(), score: 10.0 },
+                ScoredMember { member: "bob".to_string(), score: 7.5 },
+            ]
+        );
+        assert!(scored_members(&redis::Value::Bulk(vec![data("alice")])).is_none());
+        assert!(scored_members(&redis::Value::Int(1)).is_none());
+    }
+
+    #[test]
+    fn test_ranked_and_serialization() {
+        let entries = ranked(
+            vec![
+                ScoredMember { member: "alice".to_string(), score: f64::INFINITY },
+                ScoredMember { member: "bob".to_string(), score: 2.0 },
+            ],
+            1,
+        );
+        assert_eq!(
+            serde_json::to_value(entries).unwrap(),
+            json!([
+                {"rank": 1, "member": "alice", "score": "inf"},
+                {"rank": 2, "member": "bob", "score": 2.0},
+            ])
+        );
+    }
+
+    #[test]
+    fn test_structured_reply_ranges() {
+        let reply = redis::Value::Bulk(vec![data("alice"), data("10")]);
+        assert_eq!(
+            structured_reply("ZRANGE", &args(&["board", "0", "-1", "withscores"]), &reply),
+            Some(json!([{"member": "alice", "score": 10.0}]))
+        );
+        // A member named WITHSCORES in the key/range positions is not the option
+        assert_eq!(
+            structured_reply("ZRANGEBYSCORE", &args(&["WITHSCORES", "0", "10"]), &reply),
+            None
+        );
+    }
+
+    #[test]
+    fn test_structured_reply_scores_and_ranks() {
+        assert_eq!(
+            structured_reply("ZINCRBY", &args(&["board", "5", "alice"]), &data("15")),
+            Some(json!(15.0))
+        );
+        assert_eq!(
+            structured_reply("ZADD", &args(&["board", "XX", "INCR", "1", "alice"]), &redis::Value::Nil),
+            Some(Value::Null)
+        );
+        assert_eq!(
+            structured_reply("ZADD", &args(&["board", "1", "incr"]), &redis::Value::Int(1)),
+            None
+        );
+        assert_eq!(
+            structured_reply(
+                "ZRANK",
+                &args(&["board", "alice", "WITHSCORE"]),
+                &redis::Value::Bulk(vec![redis::Value::Int(0), data("10")])
+            ),
+            Some(json!({"rank": 0, "score": 10.0}))
+        );
+        assert_eq!(structured_reply("ZRANK", &args(&["board", "alice"]), &redis::Value::Int(0)), None);
+    }
+}
@@ -0,0 +1,204 @@
+// Redis Streams replies parsed into entries with ids and field maps
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// One stream entry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StreamEntry {
+    pub id: String,
+    /// `None` when the entry is still pending but was deleted from the stream (XDEL/XTRIM)
+    pub fields: Option<BTreeMap<String, String>>,
+}
+
+/// Result of claiming idle pending entries with XAUTOCLAIM
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ClaimedEntries {
+    /// Id to pass as `start` to continue scanning the pending list; "0-0" once it is exhausted
+    pub next_id: String,
+    pub entries: Vec<StreamEntry>,
+    /// Pending ids that no longer exist in the stream and were removed from the pending list
+    pub deleted_ids: Vec<String>,
+}
+
+/// Clamp a requested block time in milliseconds to `max`
+///
+/// `BLOCK 0` means "forever" to Redis, so callers must not send BLOCK for a zero duration.
+pub fn clamp_block(block_ms: u64, max: Duration) -> Duration {
+    Duration::from_millis(block_ms).min(max)
+}
+
+fn string(value: &redis::Value) -> Option<String> {
+    match value {
+        redis::Value::Data(bytes) => Some(String::from_utf8_lossy(bytes).into_owned()),
+        redis::Value::Status(s) => Some(s.clone()),
+        _ => None,
+    }
+}
+
+/// Parse one `[id, [field, value, ...]]` entry
+fn entry(value: &redis::Value) -> Option<StreamEntry> {
+    let redis::Value::Bulk(parts) = value else {
+        return None;
+    };
+    let [id, fields] = parts.as_slice() else {
+        return None;
+    };
+
+    let fields = match fields {
+        redis::Value::Nil => None,
+        redis::Value::Bulk(items) if items.len() % 2 == 0 => Some(
+            items
+                .chunks(2)
+                .map(|pair| Some((string(&pair[0])?, string(&pair[1])?)))
+                .collect::<Option<BTreeMap<_, _>>>()?,
+        ),
+        _ => return None,
+    };
+
+    Some(StreamEntry { id: string(id)?, fields })
+}
+
+/// Parse a list of entries, as returned by XRANGE
+pub fn entries(value: &redis::Value) -> Option<Vec<StreamEntry>> {
+    match value {
+        redis::Value::Bulk(items) => items.iter().map(entry).collect(),
+        redis::Value::Nil => Some(Vec::new()),
+        _ => None,
+    }
+}
+
+/// Parse an XREAD/XREADGROUP reply for a single stream
+///
+/// The reply is `[[key, [entries...]]]`, or nil when the read timed out.
+pub fn read_entries(value: &redis::Value) -> Option<Vec<StreamEntry>> {
+    match value {
+        redis::Value::Nil => Some(Vec::new()),
+        redis::Value::Bulk(streams) => {
+            let mut all = Vec::new();
+            for stream in streams {
+                let redis::Value::Bulk(parts) = stream else {
+                    return None;
+                };
+                let [_key, stream_entries] = parts.as_slice() else {
+                    return None;
+                };
+                all.extend(entries(stream_entries)?);
+            }
+            Some(all)
+        }
+        _ => None,
+    }
+}
+
+/// Parse an XAUTOCLAIM reply (`[next_id, [entries...]]`, plus deleted ids since Redis 7)
+pub fn claimed_entries(value: &redis::Value) -> Option<ClaimedEntries> {
+    let redis::Value::Bulk(parts) = value else {
+        return None;
+    };
+
+    let (next_id, claimed, deleted) = match parts.as_slice() {
+        [next_id, claimed] => (next_id, claimed, None),
+        [next_id, claimed, deleted] => (next_id, claimed, Some(deleted)),
+        _ => return None,
+    };
+
+    let deleted_ids = match deleted {
+        Some(redis::Value::Bulk(ids)) => ids.iter().map(string).collect::<Option<Vec<_>>>()?,
+        Some(_) => return None,
+        None => Vec::new(),
+    };
+
+    Some(ClaimedEntries {
+        next_id: string(next_id)?,
+        entries: entries(claimed)?,
+        deleted_ids,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(s: &str) -> redis::Value {
+        redis::Value::Data(s.as_bytes().to_vec())
+    }
+
+    fn raw_entry(id: &str, fields: &[&str]) -> redis::Value {
+        redis::Value::Bulk(vec![
+            data(id),
+            redis::Value::Bulk(fields.iter().map(|f| data(f)).collect()),
+        ])
+    }
+
+    fn stream_entry(id: &str, fields: &[(&str, &str)]) -> StreamEntry {
+        StreamEntry {
+            id: id.to_string(),
+            fields: Some(fields.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()),
+        }
+    }
+
+    #[test]
+    fn test_entries() {
+        let reply = redis::Value::Bulk(vec![
+            raw_entry("1-0", &["event", "signup", "user", "42"]),
+            raw_entry("2-0", &[]),
+        ]);
+        assert_eq!(
+            entries(&reply).unwrap(),
+            vec![
+                stream_entry("1-0", &[("event", "signup"), ("user", "42")]),
+                stream_entry("2-0", &[]),
+            ]
+        );
+        assert!(entries(&redis::Value::Bulk(vec![raw_entry("1-0", &["odd"])])).is_none());
+    }
+
+    #[test]
+    fn test_read_entries() {
+        let reply = redis::Value::Bulk(vec![redis::Value::Bulk(vec![
+            data("jobs"),
+            redis::Value::Bulk(vec![
+                raw_entry("1-0", &["task", "a"]),
+                redis::Value::Bulk(vec![data("2-0"), redis::Value::Nil]),
+            ]),
+        ])]);
+        assert_eq!(
+            read_entries(&reply).unwrap(),
+            vec![
+                stream_entry("1-0", &[("task", "a")]),
+                StreamEntry { id: "2-0".to_string(), fields: None },
+            ]
+        );
+        assert_eq!(read_entries(&redis::Value::Nil).unwrap(), Vec::new());
+    }
+
+    #[test]
+    fn test_claimed_entries() {
+        let reply = redis::Value::Bulk(vec![
+            data("0-0"),
+            redis::Value::Bulk(vec![raw_entry("1-0", &["task", "a"])]),
+            redis::Value::Bulk(vec![data("3-0")]),
+        ]);
+        assert_eq!(
+            claimed_entries(&reply).unwrap(),
+            ClaimedEntries {
+                next_id: "0-0".to_string(),
+                entries: vec![stream_entry("1-0", &[("task", "a")])],
+                deleted_ids: vec!["3-0".to_string()],
+            }
+        );
+
+        // Redis 6.2 has no deleted ids
+        let reply = redis::Value::Bulk(vec![data("5-0"), redis::Value::Bulk(vec![])]);
+        assert_eq!(claimed_entries(&reply).unwrap().deleted_ids, Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_clamp_block() {
+        let max = Duration::from_secs(29);
+        assert_eq!(clamp_block(500, max), Duration::from_millis(500));
+        assert_eq!(clamp_block(10 * 60 * 1000, max), max);
+    }
+}
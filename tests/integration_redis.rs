@@ -1,252 +1,363 @@
-/// Integration tests with real Redis instance
-use redis::{Client, Commands};
-use std::env;
-
-fn get_redis_url() -> String {
-    env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1:6379".to_string())
+/// Integration tests of Redis command paths, run against the in-memory backend so they
+/// need no Redis server
+use redis::AsyncCommands;
+use redisgate::services::memory_backend::MemoryBackend;
+use redisgate::services::redis_backend::{BackendConnection, RedisBackend};
+use redisgate::services::redis_pool::RedisEndpoint;
+use uuid::Uuid;
+
+async fn connect(backend: &MemoryBackend, database: i32) -> BackendConnection {
+    let endpoint = RedisEndpoint::local_development(6379, database);
+    backend.connection(Uuid::new_v4(), &endpoint).await.unwrap()
 }
 
-fn setup_redis() -> Result<redis::Connection, redis::RedisError> {
-    let client = Client::open(get_redis_url().as_str())?;
-    client.get_connection()
+async fn setup_redis() -> BackendConnection {
+    connect(&MemoryBackend::new(), 1).await
 }
 
 #[cfg(test)]
 mod integration_tests {
     use super::*;
+    use std::time::Duration;
 
-    #[test]
-    fn test_redis_ping() {
-        let conn = setup_redis();
-        if conn.is_err() {
-            println!("⚠️  Redis not available, skipping test");
-            return;
-        }
-
-        let mut conn = conn.unwrap();
-        let result: String = redis::cmd("PING").query(&mut conn).unwrap();
+    #[tokio::test]
+    async fn test_redis_ping() {
+        let mut conn = setup_redis().await;
+        let result: String = redis::cmd("PING").query_async(&mut conn).await.unwrap();
         assert_eq!(result, "PONG");
-        println!("✓ PING test passed");
     }
 
-    #[test]
-    fn test_redis_set_get() {
-        let conn = setup_redis();
-        if conn.is_err() {
-            println!("⚠️  Redis not available, skipping test");
-            return;
-        }
-
-        let mut conn = conn.unwrap();
+    #[tokio::test]
+    async fn test_redis_set_get() {
+        let mut conn = setup_redis().await;
 
         // SET
-        let _: () = conn.set("test_key", "test_value").unwrap();
+        let _: () = conn.set("test_key", "test_value").await.unwrap();
 
         // GET
-        let result: String = conn.get("test_key").unwrap();
+        let result: String = conn.get("test_key").await.unwrap();
         assert_eq!(result, "test_value");
 
-        // Cleanup
-        let _: () = conn.del("test_key").unwrap();
-        println!("✓ SET/GET test passed");
+        // Missing keys read as nil
+        let missing: Option<String> = conn.get("missing_key").await.unwrap();
+        assert_eq!(missing, None);
     }
 
-    #[test]
-    fn test_redis_incr_decr() {
-        let conn = setup_redis();
-        if conn.is_err() {
-            println!("⚠️  Redis not available, skipping test");
-            return;
-        }
-
-        let mut conn = conn.unwrap();
+    #[tokio::test]
+    async fn test_redis_incr_decr() {
+        let mut conn = setup_redis().await;
 
         // SET initial value
-        let _: () = conn.set("counter", 0).unwrap();
+        let _: () = conn.set("counter", 0).await.unwrap();
 
         // INCR
-        let result: i64 = conn.incr("counter", 1).unwrap();
+        let result: i64 = conn.incr("counter", 1).await.unwrap();
         assert_eq!(result, 1);
 
-        let result: i64 = conn.incr("counter", 1).unwrap();
+        let result: i64 = conn.incr("counter", 1).await.unwrap();
         assert_eq!(result, 2);
 
         // DECR
-        let result: i64 = conn.decr("counter", 1).unwrap();
+        let result: i64 = conn.decr("counter", 1).await.unwrap();
         assert_eq!(result, 1);
 
-        // Cleanup
-        let _: () = conn.del("counter").unwrap();
-        println!("✓ INCR/DECR test passed");
+        // Non-numeric values cannot be incremented
+        let _: () = conn.set("name", "alice").await.unwrap();
+        let err = conn.incr::<_, _, i64>("name", 1).await.unwrap_err();
+        assert_eq!(err.code(), Some("ERR"));
     }
 
-    #[test]
-    fn test_redis_expire_ttl() {
-        let conn = setup_redis();
-        if conn.is_err() {
-            println!("⚠️  Redis not available, skipping test");
-            return;
-        }
-
-        let mut conn = conn.unwrap();
+    #[tokio::test]
+    async fn test_redis_expire_ttl() {
+        let mut conn = setup_redis().await;
 
         // SET key
-        let _: () = conn.set("temp_key", "temp_value").unwrap();
+        let _: () = conn.set("temp_key", "temp_value").await.unwrap();
+
+        // No expiry yet
+        let ttl: i64 = conn.ttl("temp_key").await.unwrap();
+        assert_eq!(ttl, -1);
 
         // EXPIRE (10 seconds)
         let result: i32 = redis::cmd("EXPIRE")
             .arg("temp_key")
             .arg(10)
-            .query(&mut conn)
+            .query_async(&mut conn)
+            .await
             .unwrap();
         assert_eq!(result, 1); // 1 means expiration was set
 
         // TTL
-        let ttl: i64 = redis::cmd("TTL")
-            .arg("temp_key")
-            .query(&mut conn)
-            .unwrap();
+        let ttl: i64 = conn.ttl("temp_key").await.unwrap();
         assert!(ttl > 0 && ttl <= 10);
 
-        // Cleanup
-        let _: () = conn.del("temp_key").unwrap();
-        println!("✓ EXPIRE/TTL test passed");
+        // PERSIST removes the expiry
+        let persisted: i32 = conn.persist("temp_key").await.unwrap();
+        assert_eq!(persisted, 1);
+        let ttl: i64 = conn.ttl("temp_key").await.unwrap();
+        assert_eq!(ttl, -1);
     }
 
-    #[test]
-    fn test_redis_exists() {
-        let conn = setup_redis();
-        if conn.is_err() {
-            println!("⚠️  Redis not available, skipping test");
-            return;
-        }
+    #[tokio::test]
+    async fn test_redis_keys_expire() {
+        let mut conn = setup_redis().await;
+
+        let _: () = redis::cmd("SET")
+            .arg("session")
+            .arg("data")
+            .arg("PX")
+            .arg(50)
+            .query_async(&mut conn)
+            .await
+            .unwrap();
+        let _: () = conn.hset("profile", "name", "Alice").await.unwrap();
+        let _: i32 = conn.pexpire("profile", 50).await.unwrap();
+
+        tokio::time::sleep(Duration::from_millis(80)).await;
 
-        let mut conn = conn.unwrap();
+        let value: Option<String> = conn.get("session").await.unwrap();
+        assert_eq!(value, None);
+        let exists: i32 = conn.exists("profile").await.unwrap();
+        assert_eq!(exists, 0);
+        let ttl: i64 = conn.pttl("session").await.unwrap();
+        assert_eq!(ttl, -2);
+    }
+
+    #[tokio::test]
+    async fn test_redis_exists() {
+        let mut conn = setup_redis().await;
 
         // SET key
-        let _: () = conn.set("exists_key", "value").unwrap();
+        let _: () = conn.set("exists_key", "value").await.unwrap();
 
         // EXISTS - should return 1
         let result: i32 = redis::cmd("EXISTS")
             .arg("exists_key")
-            .query(&mut conn)
+            .query_async(&mut conn)
+            .await
             .unwrap();
         assert_eq!(result, 1);
 
         // DELETE
-        let _: () = conn.del("exists_key").unwrap();
+        let _: () = conn.del("exists_key").await.unwrap();
 
         // EXISTS - should return 0
         let result: i32 = redis::cmd("EXISTS")
             .arg("exists_key")
-            .query(&mut conn)
+            .query_async(&mut conn)
+            .await
             .unwrap();
         assert_eq!(result, 0);
-
-        println!("✓ EXISTS test passed");
     }
 
-    #[test]
-    fn test_redis_hset_hget() {
-        let conn = setup_redis();
-        if conn.is_err() {
-            println!("⚠️  Redis not available, skipping test");
-            return;
-        }
-
-        let mut conn = conn.unwrap();
+    #[tokio::test]
+    async fn test_redis_hset_hget() {
+        let mut conn = setup_redis().await;
 
         // HSET
-        let _: i32 = conn.hset("user:1", "name", "Alice").unwrap();
-        let _: i32 = conn.hset("user:1", "age", "25").unwrap();
+        let _: i32 = conn.hset("user:1", "name", "Alice").await.unwrap();
+        let _: i32 = conn.hset("user:1", "age", "25").await.unwrap();
 
         // HGET
-        let name: String = conn.hget("user:1", "name").unwrap();
+        let name: String = conn.hget("user:1", "name").await.unwrap();
         assert_eq!(name, "Alice");
 
-        let age: String = conn.hget("user:1", "age").unwrap();
+        let age: String = conn.hget("user:1", "age").await.unwrap();
         assert_eq!(age, "25");
 
-        // Cleanup
-        let _: () = conn.del("user:1").unwrap();
-        println!("✓ HSET/HGET test passed");
+        // HGETALL
+        let all: Vec<(String, String)> = conn.hgetall("user:1").await.unwrap();
+        assert_eq!(
+            all,
+            vec![
+                ("age".to_string(), "25".to_string()),
+                ("name".to_string(), "Alice".to_string())
+            ]
+        );
+
+        // A hash is not a string
+        let err = conn.get::<_, String>("user:1").await.unwrap_err();
+        assert_eq!(err.code(), Some("WRONGTYPE"));
+
+        // Deleting the last field deletes the key
+        let _: i32 = conn.hdel("user:1", &["name", "age"]).await.unwrap();
+        let exists: i32 = conn.exists("user:1").await.unwrap();
+        assert_eq!(exists, 0);
     }
 
-    #[test]
-    fn test_redis_lpush_lpop() {
-        let conn = setup_redis();
-        if conn.is_err() {
-            println!("⚠️  Redis not available, skipping test");
-            return;
-        }
-
-        let mut conn = conn.unwrap();
+    #[tokio::test]
+    async fn test_redis_lpush_lpop() {
+        let mut conn = setup_redis().await;
 
         // LPUSH
-        let _: i32 = conn.lpush("queue", "task1").unwrap();
-        let _: i32 = conn.lpush("queue", "task2").unwrap();
-        let _: i32 = conn.lpush("queue", "task3").unwrap();
+        let _: i32 = conn.lpush("queue", "task1").await.unwrap();
+        let _: i32 = conn.lpush("queue", "task2").await.unwrap();
+        let _: i32 = conn.lpush("queue", "task3").await.unwrap();
+
+        // LRANGE
+        let all: Vec<String> = conn.lrange("queue", 0, -1).await.unwrap();
+        assert_eq!(all, vec!["task3", "task2", "task1"]);
 
         // LPOP
-        let result: String = conn.lpop("queue", None).unwrap();
+        let result: String = conn.lpop("queue", None).await.unwrap();
         assert_eq!(result, "task3"); // Last in, first out
 
-        let result: String = conn.lpop("queue", None).unwrap();
+        let result: String = conn.lpop("queue", None).await.unwrap();
         assert_eq!(result, "task2");
 
-        // Cleanup
-        let _: () = conn.del("queue").unwrap();
-        println!("✓ LPUSH/LPOP test passed");
+        // RPOP
+        let result: String = conn.rpop("queue", None).await.unwrap();
+        assert_eq!(result, "task1");
+
+        let len: i32 = conn.llen("queue").await.unwrap();
+        assert_eq!(len, 0);
     }
 
-    #[test]
-    fn test_full_workflow() {
-        let conn = setup_redis();
-        if conn.is_err() {
-            println!("⚠️  Redis not available, skipping test");
-            return;
-        }
+    #[tokio::test]
+    async fn test_redis_sets() {
+        let mut conn = setup_redis().await;
+
+        let added: i32 = conn.sadd("tags", &["rust", "redis", "rust"]).await.unwrap();
+        assert_eq!(added, 2);
+
+        let member: bool = conn.sismember("tags", "redis").await.unwrap();
+        assert!(member);
+
+        let mut members: Vec<String> = conn.smembers("tags").await.unwrap();
+        members.sort();
+        assert_eq!(members, vec!["redis", "rust"]);
+
+        let removed: i32 = conn.srem("tags", "redis").await.unwrap();
+        assert_eq!(removed, 1);
+        let count: i32 = conn.scard("tags").await.unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_redis_sorted_sets() {
+        let mut conn = setup_redis().await;
 
-        let mut conn = conn.unwrap();
+        let _: i32 = conn.zadd("scores", "alice", 30).await.unwrap();
+        let _: i32 = conn.zadd("scores", "bob", 10).await.unwrap();
+        let _: i32 = conn.zadd("scores", "carol", 20).await.unwrap();
+
+        let top: Vec<(String, f64)> = conn.zrevrange_withscores("scores", 0, 1).await.unwrap();
+        assert_eq!(top, vec![("alice".to_string(), 30.0), ("carol".to_string(), 20.0)]);
+
+        let score: f64 = conn.zincr("scores", "bob", 25).await.unwrap();
+        assert_eq!(score, 35.0);
+
+        let rank: Option<i64> = conn.zrevrank("scores", "bob").await.unwrap();
+        assert_eq!(rank, Some(0));
+
+        let middle: Vec<String> = conn.zrangebyscore("scores", 15, "(35").await.unwrap();
+        assert_eq!(middle, vec!["carol", "alice"]);
+    }
+
+    #[tokio::test]
+    async fn test_redis_pipeline() {
+        let mut conn = setup_redis().await;
+
+        let (_, value, len): ((), String, i32) = redis::pipe()
+            .set("pipe_key", "value")
+            .get("pipe_key")
+            .strlen("pipe_key")
+            .query_async(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(value, "value");
+        assert_eq!(len, 5);
+    }
+
+    #[tokio::test]
+    async fn test_redis_transaction_watch() {
+        let backend = MemoryBackend::new();
+        let mut conn = connect(&backend, 1).await;
+        let mut other = connect(&backend, 1).await;
+
+        // Untouched watched key: the transaction runs
+        let _: () = redis::cmd("WATCH").arg("balance").query_async(&mut conn).await.unwrap();
+        let result: Option<(i64,)> = redis::pipe()
+            .atomic()
+            .incr("balance", 10)
+            .query_async(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(result, Some((10,)));
+
+        // Watched key changed by another client: EXEC replies nil
+        let _: () = redis::cmd("WATCH").arg("balance").query_async(&mut conn).await.unwrap();
+        let _: i64 = other.incr("balance", 1).await.unwrap();
+        let result: Option<(i64,)> = redis::pipe()
+            .atomic()
+            .incr("balance", 10)
+            .query_async(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(result, None);
+
+        let balance: i64 = conn.get("balance").await.unwrap();
+        assert_eq!(balance, 11);
+    }
+
+    #[tokio::test]
+    async fn test_databases_are_isolated() {
+        let backend = MemoryBackend::new();
+        let mut first = connect(&backend, 1).await;
+        let mut second = connect(&backend, 2).await;
+
+        let _: () = first.set("shared_name", "first").await.unwrap();
+        let value: Option<String> = second.get("shared_name").await.unwrap();
+        assert_eq!(value, None);
+
+        let size: i64 = redis::cmd("DBSIZE").query_async(&mut second).await.unwrap();
+        assert_eq!(size, 0);
+    }
+
+    #[tokio::test]
+    async fn test_full_workflow() {
+        let mut conn = setup_redis().await;
 
         // 1. SET with expiration
-        let _: () = conn.set("workflow_key", "value").unwrap();
+        let _: () = conn.set("workflow_key", "value").await.unwrap();
         let _: i32 = redis::cmd("EXPIRE")
             .arg("workflow_key")
             .arg(300)
-            .query(&mut conn)
+            .query_async(&mut conn)
+            .await
             .unwrap();
 
         // 2. Verify EXISTS
         let exists: i32 = redis::cmd("EXISTS")
             .arg("workflow_key")
-            .query(&mut conn)
+            .query_async(&mut conn)
+            .await
             .unwrap();
         assert_eq!(exists, 1);
 
         // 3. Check TTL
         let ttl: i64 = redis::cmd("TTL")
             .arg("workflow_key")
-            .query(&mut conn)
+            .query_async(&mut conn)
+            .await
             .unwrap();
         assert!(ttl > 0);
 
         // 4. GET value
-        let value: String = conn.get("workflow_key").unwrap();
+        let value: String = conn.get("workflow_key").await.unwrap();
         assert_eq!(value, "value");
 
         // 5. DELETE
-        let deleted: i32 = conn.del("workflow_key").unwrap();
+        let deleted: i32 = conn.del("workflow_key").await.unwrap();
         assert_eq!(deleted, 1);
 
         // 6. Verify deleted
         let exists: i32 = redis::cmd("EXISTS")
             .arg("workflow_key")
-            .query(&mut conn)
+            .query_async(&mut conn)
+            .await
             .unwrap();
         assert_eq!(exists, 0);
-
-        println!("✓ Full workflow test passed");
     }
 }
 
@@ -255,23 +366,17 @@ mod performance_tests {
     use super::*;
     use std::time::Instant;
 
-    #[test]
-    fn benchmark_set_get() {
-        let conn = setup_redis();
-        if conn.is_err() {
-            println!("⚠️  Redis not available, skipping benchmark");
-            return;
-        }
-
-        let mut conn = conn.unwrap();
+    #[tokio::test]
+    async fn benchmark_set_get() {
+        let mut conn = setup_redis().await;
         let iterations = 1000;
 
         let start = Instant::now();
         for i in 0..iterations {
             let key = format!("bench_key_{}", i);
-            let _: () = conn.set(&key, "value").unwrap();
-            let _: String = conn.get(&key).unwrap();
-            let _: () = conn.del(&key).unwrap();
+            let _: () = conn.set(&key, "value").await.unwrap();
+            let _: String = conn.get(&key).await.unwrap();
+            let _: () = conn.del(&key).await.unwrap();
         }
         let duration = start.elapsed();
 
@@ -283,3 +388,247 @@ mod performance_tests {
     }
 }
 
+/// Tests of the gateway's command paths: policy and scope checks, then execution on the
+/// in-memory backend. The instance's policy and credentials are preloaded, so these run
+/// without PostgreSQL; the pool is never connected.
+#[cfg(test)]
+mod gateway_tests {
+    use axum::http::StatusCode;
+    use axum::Json;
+    use redisgate::auth::ApiKeyClaims;
+    use redisgate::config::{Config, RedisBackendKind};
+    use redisgate::handlers::redis::{
+        execute_command, execute_pipeline, execute_transaction, ErrorResponse, TransactionRequest,
+        ValueEncoding,
+    };
+    use redisgate::middleware::AppState;
+    use redisgate::models::RedisInstance;
+    use redisgate::services::command_policy::CommandPolicy;
+    use redisgate::services::redis_pool::RedisEndpoint;
+    use serde_json::{json, Value};
+    use sqlx::postgres::PgPoolOptions;
+    use std::time::Duration;
+    use uuid::Uuid;
+
+    /// State serving one instance with the default command policy and no password
+    async fn setup() -> (AppState, RedisInstance) {
+        // Any query would fail fast instead of reaching a database
+        let db_pool = PgPoolOptions::new()
+            .acquire_timeout(Duration::from_secs(1))
+            .connect_lazy("postgres://unused@127.0.0.1:1/unused")
+            .unwrap();
+
+        let mut config = Config::default();
+        config.redis.backend = RedisBackendKind::Memory;
+        // 32 zero bytes
+        config.credentials.master_key = Some("AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=".to_string());
+        let state = AppState::with_config(db_pool, "test-secret", &config);
+
+        let instance = instance();
+        state.command_policies.preload(instance.id, CommandPolicy::default()).await;
+        state.credentials.preload_password(instance.id, None).await;
+        (state, instance)
+    }
+
+    fn instance() -> RedisInstance {
+        RedisInstance {
+            id: Uuid::new_v4(),
+            name: "gateway-test".to_string(),
+            slug: "gateway-test".to_string(),
+            organization_id: Uuid::new_v4(),
+            api_key_id: None,
+            port: Some(6379),
+            private_ip_address: None,
+            public_ip_address: None,
+            domain: Some("cache.example.test".to_string()),
+            max_memory: None,
+            current_memory: None,
+            password_hash: None,
+            redis_version: None,
+            topology: "standalone".to_string(),
+            sentinel_master_name: None,
+            sentinel_endpoints: Vec::new(),
+            deployment_mode: "kubernetes".to_string(),
+            namespace: None,
+            pod_name: None,
+            service_name: None,
+            status: Some("running".to_string()),
+            last_health_check_at: None,
+            health_status: None,
+            cpu_usage_percent: None,
+            memory_usage_percent: None,
+            connections_count: None,
+            max_connections: None,
+            persistence_enabled: None,
+            backup_enabled: None,
+            last_backup_at: None,
+            created_at: None,
+            updated_at: None,
+            deleted_at: None,
+        }
+    }
+
+    fn claims(instance: &RedisInstance, scopes: &[&str]) -> ApiKeyClaims {
+        ApiKeyClaims::new(
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            instance.organization_id,
+            scopes.iter().map(|s| s.to_string()).collect(),
+            "rg_test".to_string(),
+            None,
+        )
+    }
+
+    async fn run(state: &AppState, instance: &RedisInstance, claims: &ApiKeyClaims, command: Value) -> Result<Value, ErrorResponse> {
+        let payload = command.as_array().unwrap().clone();
+        execute_command(state, instance, claims, &payload, ValueEncoding::Plain)
+            .await
+            .map(|response| response.0.result)
+    }
+
+    fn commands(value: Value) -> Vec<Vec<Value>> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_execute_command_set_get() {
+        let (state, instance) = setup().await;
+        let claims = claims(&instance, &["write"]);
+
+        assert_eq!(run(&state, &instance, &claims, json!(["SET", "name", "alice"])).await.unwrap(), json!("OK"));
+        assert_eq!(run(&state, &instance, &claims, json!(["GET", "name"])).await.unwrap(), json!("alice"));
+        assert_eq!(run(&state, &instance, &claims, json!(["GET", "missing"])).await.unwrap(), Value::Null);
+
+        // Errors Redis reports as plain ERR are the client's
+        let (status, Json(body)) = run(&state, &instance, &claims, json!(["INCR", "name"])).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], "COMMAND_ERROR");
+    }
+
+    #[tokio::test]
+    async fn test_read_scope_cannot_write() {
+        let (state, instance) = setup().await;
+        let claims = claims(&instance, &["read"]);
+
+        let (status, _) = run(&state, &instance, &claims, json!(["SET", "name", "alice"])).await.unwrap_err();
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(run(&state, &instance, &claims, json!(["GET", "name"])).await.unwrap(), Value::Null);
+    }
+
+    #[tokio::test]
+    async fn test_default_policy_denies_dangerous_commands() {
+        let (state, instance) = setup().await;
+        let claims = claims(&instance, &["*"]);
+
+        for command in [json!(["FLUSHALL"]), json!(["KEYS", "*"]), json!(["EVAL", "return 1", "0"])] {
+            let (status, _) = run(&state, &instance, &claims, command.clone()).await.unwrap_err();
+            assert_eq!(status, StatusCode::FORBIDDEN, "{} was allowed", command);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_results_per_command() {
+        let (state, instance) = setup().await;
+        let claims = claims(&instance, &["write"]);
+
+        let payload = commands(json!([
+            ["SET", "name", "alice"],
+            ["INCR", "name"],
+            ["GET", "name"],
+        ]));
+        let results = execute_pipeline(&state, &instance, &claims, &payload).await.unwrap().0;
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0], json!({"result": "OK"}));
+        assert_eq!(results[1]["code"], "COMMAND_ERROR");
+        assert_eq!(results[2], json!({"result": "alice"}));
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_checks_every_command_first() {
+        let (state, instance) = setup().await;
+        let claims = claims(&instance, &["write"]);
+
+        let payload = commands(json!([["SET", "name", "alice"], ["FLUSHALL"]]));
+        let (status, _) = execute_pipeline(&state, &instance, &claims, &payload).await.unwrap_err();
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        // Nothing ran
+        assert_eq!(run(&state, &instance, &claims, json!(["GET", "name"])).await.unwrap(), Value::Null);
+    }
+
+    #[test]
+    fn test_development_instances_are_marked_explicitly() {
+        let mut instance = instance();
+        instance.slug = "dev-foo".to_string();
+        instance.domain = Some("dev-foo.acme.redis.local".to_string());
+        assert!(!RedisEndpoint::is_local_development(&instance));
+
+        instance.deployment_mode = "development".to_string();
+        assert!(RedisEndpoint::is_local_development(&instance));
+    }
+
+    #[test]
+    fn test_development_channels_are_namespaced() {
+        let mut instance = instance();
+        let mut args = vec!["news".to_string(), "hello".to_string()];
+        RedisEndpoint::namespace_channels(&instance, "PUBLISH", &mut args);
+        assert_eq!(args, ["news", "hello"]);
+
+        instance.deployment_mode = "development".to_string();
+        RedisEndpoint::namespace_channels(&instance, "publish", &mut args);
+        assert_eq!(args, [format!("{}:news", instance.id), "hello".to_string()]);
+
+        let mut patterns = vec![b"news.*".to_vec(), b"alerts".to_vec()];
+        RedisEndpoint::namespace_channels(&instance, "PSUBSCRIBE", &mut patterns);
+        assert_eq!(patterns, [format!("{}:news.*", instance.id).into_bytes(), format!("{}:alerts", instance.id).into_bytes()]);
+
+        let mut keys = vec!["news".to_string()];
+        RedisEndpoint::namespace_channels(&instance, "GET", &mut keys);
+        assert_eq!(keys, ["news"]);
+    }
+
+    #[tokio::test]
+    async fn test_multi_exec() {
+        let (state, instance) = setup().await;
+        let claims = claims(&instance, &["write"]);
+
+        let request = TransactionRequest::Commands(commands(json!([
+            ["SET", "counter", "10"],
+            ["INCR", "counter"],
+            ["GET", "counter"],
+        ])));
+        let results = execute_transaction(&state, &instance, &claims, request).await.unwrap().0;
+        assert_eq!(
+            results,
+            vec![json!({"result": "OK"}), json!({"result": 11}), json!({"result": "11"})]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_multi_exec_reports_partial_execution() {
+        let (state, instance) = setup().await;
+        let claims = claims(&instance, &["write"]);
+
+        let request = TransactionRequest::Commands(commands(json!([
+            ["SET", "name", "alice"],
+            ["INCR", "name"],
+            ["SET", "after", "1"],
+        ])));
+        let (status, Json(body)) = execute_transaction(&state, &instance, &claims, request).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["partial"], json!(true));
+        // The command after the failed one was still committed
+        assert_eq!(run(&state, &instance, &claims, json!(["GET", "after"])).await.unwrap(), json!("1"));
+    }
+
+    #[tokio::test]
+    async fn test_multi_exec_checks_scopes() {
+        let (state, instance) = setup().await;
+        let claims = claims(&instance, &["read"]);
+
+        let request = TransactionRequest::Commands(commands(json!([["GET", "counter"], ["INCR", "counter"]])));
+        let (status, _) = execute_transaction(&state, &instance, &claims, request).await.unwrap_err();
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+}